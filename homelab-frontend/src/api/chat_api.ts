@@ -4,8 +4,10 @@ import axios from 'axios';
 import { websocket_base_url } from '@/../config.json';
 import { globalState } from '@/stores/store';
 
-export async function connectChat(token: String) {
-  const socket = new WebSocket(`${websocket_base_url}/chat/ws?auth_token=${token}`);
+export async function connectChat() {
+  // A single-use ticket keeps the auth token out of the websocket URL
+  const ticketResponse = await axios.post("/chat/ws/ticket");
+  const socket = new WebSocket(`${websocket_base_url}/chat/ws`, ["pat-chat", `pat-ticket.${ticketResponse.data.ticket}`]);
   socket.onmessage = (event) => {
     const websocketResponse = JSON.parse(event.data);
     if (websocketResponse.type === WebsocketResponseType.SendChatMessage) {
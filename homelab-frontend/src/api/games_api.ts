@@ -1,13 +1,25 @@
 import axios from 'axios';
 
-import type { CreateConnectionsGame } from '@/models/games_interfaces';
+import type { CreateConnectionsGame, ListConnectionsGamesParams, RateConnectionsGame, UpdateConnectionsGame } from '@/models/games_interfaces';
 
 export async function createConnectionsGame(gameData: CreateConnectionsGame) {
   return await axios.post("/games/connections", gameData);
 }
 
-export async function getAllConnectionGamesForOthers() {
-  return await axios.get("/games/connections");
+export async function updateConnectionsGame(gameId: string, gameData: UpdateConnectionsGame) {
+  return await axios.put(`/games/connections/${gameId}`, gameData);
+}
+
+export async function publishConnectionsGame(gameId: string) {
+  return await axios.put(`/games/connections/${gameId}/publish`);
+}
+
+export async function deleteConnectionsGame(gameId: string) {
+  return await axios.delete(`/games/connections/${gameId}`);
+}
+
+export async function getAllConnectionGamesForOthers(params: ListConnectionsGamesParams = {}) {
+  return await axios.get("/games/connections", { params });
 }
 
 export async function getAllConnectionGamesForMe() {
@@ -18,6 +30,38 @@ export async function getConnectionGameToPlay(gameSlug: string) {
   return await axios.get(`/games/connections/play/${gameSlug}`);
 }
 
+export async function startConnectionGameSession(gameSlug: string) {
+  return await axios.put(`/games/connections/play/${gameSlug}/session`);
+}
+
 export async function trySolveConnectionGameRow(gameSlug: string, data: Array<string>) {
   return await axios.put(`/games/connections/play/${gameSlug}/try_solve`, data);
 }
+
+export async function rateConnectionGame(gameSlug: string, data: RateConnectionsGame) {
+  return await axios.put(`/games/connections/play/${gameSlug}/rating`, data);
+}
+
+export async function getShareableConnectionResult(gameSlug: string) {
+  return await axios.get(`/games/connections/play/${gameSlug}/share`);
+}
+
+export async function shareConnectionResultToChat(gameSlug: string, channelId: string) {
+  return await axios.post(`/games/connections/play/${gameSlug}/share`, { channel_id: channelId });
+}
+
+export async function createWordGame(answer: string | null) {
+  return await axios.post("/games/words", { answer });
+}
+
+export async function getAllWordGamesForOthers() {
+  return await axios.get("/games/words");
+}
+
+export async function startWordGameSession(gameId: string) {
+  return await axios.put(`/games/words/${gameId}/session`);
+}
+
+export async function guessWord(gameId: string, guess: string) {
+  return await axios.put(`/games/words/${gameId}/guess`, { guess });
+}
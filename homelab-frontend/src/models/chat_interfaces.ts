@@ -95,4 +95,6 @@ export interface WebsocketMessageCreated {
 export interface WebSocketError {
   status_code: Number,
   msg: String,
+  // Only set when rate limited
+  retry_after_ms?: Number,
 }
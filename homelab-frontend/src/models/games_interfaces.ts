@@ -1,6 +1,8 @@
 export interface ConnectionGameRowInterface {
   category_name: string,
   category_clues: Array<string>,
+  // 0 for the most straightforward category up to 3 for the trickiest
+  difficulty?: number | null,
 }
 
 export class ConnectionGameRow implements ConnectionGameRowInterface {
@@ -16,9 +18,14 @@ export class ConnectionGameRow implements ConnectionGameRowInterface {
 export class CreateConnectionsGame {
   puzzle_name: string;
   connection_categories: Array<ConnectionGameRow>;
+  // Drafts are hidden from other players until they are published
+  draft: boolean;
+  tags: Array<string>;
 
   constructor() {
     this.puzzle_name = '';
+    this.draft = false;
+    this.tags = [];
     this.connection_categories = [];
     for (let i = 0; i < 4; i++) {
       this.connection_categories.push(new ConnectionGameRow());
@@ -49,6 +56,35 @@ export interface ListedConnectionGame {
   id: string,
   puzzle_name: string,
   slug: string,
+  tags: Array<string>,
+  // Only set when listing your own games
+  draft?: boolean,
+  // Only set when listing other authors' games
+  average_rating?: number | null,
+  rating_count?: number,
+  played?: boolean,
+}
+
+export type DifficultyVote = 'easy' | 'medium' | 'hard';
+
+export interface RateConnectionsGame {
+  rating: number,
+  difficulty: DifficultyVote,
+}
+
+export interface ListConnectionsGamesParams {
+  sort?: 'newest' | 'top_rated',
+  author_id?: string,
+  tag?: string,
+  unplayed?: boolean,
+  page?: number,
+  per_page?: number,
+}
+
+export interface UpdateConnectionsGame {
+  puzzle_name: string,
+  connection_categories: Array<ConnectionGameRowInterface>,
+  tags: Array<string>,
 }
 
 export interface ScrambledGame {
@@ -59,3 +95,25 @@ export interface ScrambledGame {
   scrambled_clues: Array<string>,
   slug: string,
 }
+
+export type SessionState = 'in_progress' | 'won' | 'lost';
+
+export interface SessionGuess {
+  clues: Array<string>,
+  category_name: string | null,
+  guessed_at: number,
+}
+
+export interface ConnectionGameSession {
+  game_id: string,
+  slug: string,
+  puzzle_name: string,
+  solved_categories: Array<ConnectionGameRowInterface>,
+  remaining_clues: Array<string>,
+  guesses: Array<SessionGuess>,
+  mistakes_remaining: number,
+  state: SessionState,
+  started_at: number,
+  finished_at: number | null,
+  answers: Array<ConnectionGameRowInterface> | null,
+}
@@ -61,6 +61,10 @@ export default defineStore("toaster-store", {
       else {
         if(payload.error.response.status < 500) {
           this.error({text: payload.error.response.data.msg});
+          // Validation failures say what was wrong with each field
+          for (const fieldError of payload.error.response.data.errors ?? []) {
+            this.error({text: `${fieldError.field}: ${fieldError.msg}`});
+          }
         }
         else {
           // TODO: Console log payload.error.response.data? Is there a guarantee anything will even be there?
@@ -0,0 +1,416 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::header::HeaderMap,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use mongodb::{
+    bson,
+    bson::{doc, Bson, Document},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{chat_controller::notify_channel_event, get_user_from_auth_header, return_data::ReturnData},
+    app::AppState,
+    db::{str_to_object_id, MongoModel},
+    error_handler::DbError,
+    models::chat::{
+        chat_channel::{ChannelType, ChatChannel, ReturnChannel},
+        chat_channel_db::{delete_chat_channel_and_contents, get_chat_channel_by_id, hydrate_chat_channel_subscribers, update_chat_channel_by_id},
+        invite::ChatInvite,
+        invite_db::{delete_chat_invite, get_chat_invite_by_code, get_chat_invites_for_channel, insert_chat_invite, use_chat_invite, InviteTarget},
+        packet::ChannelEvent,
+        server::permissions,
+        server_db::get_chat_server_by_id,
+        validation::{CreateInviteSchema, UpdateChannelSchema, MAX_INVITE_LIFETIME},
+    },
+};
+
+pub fn channel_moderation_routes() -> Router<Arc<AppState>> {
+    Router::<Arc<AppState>>::new()
+        .route("/chat/channels/:channel_id", put(update_channel))
+        .route("/chat/channels/:channel_id", delete(delete_channel))
+        .route("/chat/channels/:channel_id/invites", post(create_invite))
+        .route("/chat/channels/:channel_id/invites", get(list_invites))
+        .route("/chat/channels/:channel_id/kick", put(kick_member))
+        .route("/chat/channels/:channel_id/ban", put(ban_member))
+        .route("/chat/channels/:channel_id/unban", put(unban_member))
+        .route("/chat/channels/:channel_id/transfer", put(transfer_ownership))
+        .route("/chat/invites/:code", delete(revoke_invite))
+        .route("/chat/invites/:code/join", put(join_with_invite))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChannelMemberSchema {
+    pub user_id: String,
+}
+
+// Gets a channel and verifies that the requester owns it. Direct messages have no real owner, so
+// they can't be moderated, and server channels are moderated through their server
+async fn get_owned_channel<T>(app_state: &AppState, channel_id: &str, user_id: &str) -> Result<ChatChannel, ReturnData<T>> {
+    let channel = match get_chat_channel_by_id(&app_state.db, channel_id).await {
+        Ok(channel) => channel,
+        Err(db_err) => return Err(db_err.into()),
+    };
+    if channel.channel_type == ChannelType::Server {
+        return Err(ReturnData::bad_request("Server channels are managed through their server".to_string()));
+    }
+    if channel.owner_id != user_id {
+        return Err(ReturnData::forbidden("Only the owner of a channel can do this".to_string()));
+    }
+    if channel.channel_type == ChannelType::DirectMessage {
+        return Err(ReturnData::bad_request("Direct messages cannot be moderated".to_string()));
+    }
+    Ok(channel)
+}
+
+fn channel_filter_doc(channel: &ChatChannel) -> Result<Document, DbError> {
+    let channel_id = str_to_object_id(channel.id.as_str())?;
+    Ok(doc! {"_id": Bson::ObjectId(channel_id)})
+}
+
+async fn update_channel(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<String>,
+    Json(update_data): Json<UpdateChannelSchema>,
+) -> ReturnData<ReturnChannel> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let channel = match get_owned_channel(&app_state, channel_id.as_str(), user.get_id().as_str()).await {
+        Ok(channel) => channel,
+        Err(e) => return e,
+    };
+
+    let update = match bson::to_document(&update_data) {
+        Ok(res) => res,
+        Err(_) => return DbError::UnhandledException("Failed to process update request data".to_string()).into(),
+    };
+    if update.is_empty() {
+        return DbError::EmptyDbExpression(ChatChannel::model_name(), "updating".to_owned()).into();
+    }
+    let filter_doc = match channel_filter_doc(&channel) {
+        Ok(filter_doc) => filter_doc,
+        Err(db_err) => return db_err.into(),
+    };
+
+    match update_chat_channel_by_id(pool, filter_doc, doc! {"$set": update}).await {
+        Ok(chat_channel) => {
+            let event = ChannelEvent::ChannelUpdated {
+                name: chat_channel.name.clone(),
+                is_private: chat_channel.is_private,
+            };
+            notify_channel_event(&app_state, &chat_channel.subscribers, chat_channel.id.as_str(), event).await;
+            ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await)
+        }
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn delete_channel(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(channel_id): Path<String>) -> ReturnData<()> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let channel = match get_owned_channel(&app_state, channel_id.as_str(), user.get_id().as_str()).await {
+        Ok(channel) => channel,
+        Err(e) => return e,
+    };
+
+    if let Err(db_err) = delete_chat_channel_and_contents(pool, channel.id.as_str()).await {
+        return db_err.into();
+    }
+    app_state.channel_groups.invalidate(channel.id.as_str()).await;
+
+    notify_channel_event(&app_state, &channel.subscribers, channel.id.as_str(), ChannelEvent::ChannelDeleted).await;
+    ReturnData::ok(())
+}
+
+async fn create_invite(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<String>,
+    Json(invite_data): Json<CreateInviteSchema>,
+) -> ReturnData<ChatInvite> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let channel = match get_owned_channel(&app_state, channel_id.as_str(), user.get_id().as_str()).await {
+        Ok(channel) => channel,
+        Err(e) => return e,
+    };
+
+    if invite_data.expires_in.is_some_and(|expires_in| expires_in <= 0) {
+        return ReturnData::bad_request("An invite must expire in the future".to_string());
+    }
+    if invite_data.expires_in.is_some_and(|expires_in| expires_in > MAX_INVITE_LIFETIME) {
+        return ReturnData::bad_request("An invite can't expire more than a year from now".to_string());
+    }
+    if invite_data.max_uses.is_some_and(|max_uses| max_uses <= 0) {
+        return ReturnData::bad_request("An invite must have at least one use".to_string());
+    }
+
+    match insert_chat_invite(pool, &invite_data, InviteTarget::Channel(channel.id.as_str()), user.get_id().as_str()).await {
+        Ok(invite) => ReturnData::created(invite),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn list_invites(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(channel_id): Path<String>) -> ReturnData<Vec<ChatInvite>> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let channel = match get_owned_channel(&app_state, channel_id.as_str(), user.get_id().as_str()).await {
+        Ok(channel) => channel,
+        Err(e) => return e,
+    };
+    match get_chat_invites_for_channel(pool, channel.id.as_str()).await {
+        Ok(invites) => ReturnData::ok(invites),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn revoke_invite(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(code): Path<String>) -> ReturnData<()> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let invite = match get_chat_invite_by_code(pool, code.as_str()).await {
+        Ok(invite) => invite,
+        Err(db_err) => return db_err.into(),
+    };
+    match (&invite.channel_id, &invite.server_id) {
+        (Some(channel_id), _) => {
+            if let Err(e) = get_owned_channel::<()>(&app_state, channel_id.as_str(), user.get_id().as_str()).await {
+                return e;
+            }
+        }
+        (None, Some(server_id)) => match get_chat_server_by_id(pool, server_id.as_str()).await {
+            Ok(server) => {
+                if server.server_permissions(user.get_id().as_str()) & permissions::MANAGE == 0 {
+                    return ReturnData::forbidden("You do not have permission to manage this server".to_string());
+                }
+            }
+            Err(db_err) => return db_err.into(),
+        },
+        (None, None) => return DbError::UnhandledException("Chat invite has no channel or server".to_string()).into(),
+    }
+    match delete_chat_invite(pool, code.as_str()).await {
+        Ok(_) => ReturnData::ok(()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn join_with_invite(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(code): Path<String>) -> ReturnData<ReturnChannel> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let user_id = user.get_id();
+
+    let invite = match get_chat_invite_by_code(pool, code.as_str()).await {
+        Ok(invite) => invite,
+        Err(db_err) => return db_err.into(),
+    };
+    let channel_id = match invite.channel_id {
+        Some(channel_id) => channel_id,
+        None => return ReturnData::bad_request("This invite is for a server, join it through /chat/servers/join".to_string()),
+    };
+    let channel = match get_chat_channel_by_id(pool, channel_id.as_str()).await {
+        Ok(channel) => channel,
+        Err(db_err) => return db_err.into(),
+    };
+
+    // Check these before using the invite so a failed join doesn't use it up
+    if channel.banned_users.contains(&user_id) {
+        return ReturnData::forbidden("You are banned from this channel".to_string());
+    }
+    if channel.subscribers.contains(&user_id) {
+        return ReturnData::bad_request("You are already in this channel".to_string());
+    }
+
+    match use_chat_invite(pool, code.as_str()).await {
+        Ok(_) => (),
+        Err(DbError::NotFound(_)) => return ReturnData::bad_request("This invite has expired or has no uses left".to_string()),
+        Err(db_err) => return db_err.into(),
+    }
+
+    let filter_doc = match channel_filter_doc(&channel) {
+        Ok(filter_doc) => filter_doc,
+        Err(db_err) => return db_err.into(),
+    };
+    let update_doc = doc! {"$addToSet": {"subscribers": user_id.as_str()}};
+    match update_chat_channel_by_id(pool, filter_doc, update_doc).await {
+        Ok(chat_channel) => {
+            app_state.channel_groups.invalidate(chat_channel.id.as_str()).await;
+            let event = ChannelEvent::MemberJoined(user_id);
+            notify_channel_event(&app_state, &chat_channel.subscribers, chat_channel.id.as_str(), event).await;
+            ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await)
+        }
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn kick_member(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<String>,
+    Json(member): Json<ChannelMemberSchema>,
+) -> ReturnData<ReturnChannel> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let channel = match get_owned_channel(&app_state, channel_id.as_str(), user.get_id().as_str()).await {
+        Ok(channel) => channel,
+        Err(e) => return e,
+    };
+
+    if member.user_id == channel.owner_id {
+        return ReturnData::bad_request("The owner of a channel cannot be removed from it".to_string());
+    }
+    if !channel.subscribers.contains(&member.user_id) {
+        return ReturnData::bad_request("That user is not in this channel".to_string());
+    }
+
+    let filter_doc = match channel_filter_doc(&channel) {
+        Ok(filter_doc) => filter_doc,
+        Err(db_err) => return db_err.into(),
+    };
+    let update_doc = doc! {"$pull": {"subscribers": member.user_id.as_str()}};
+    match update_chat_channel_by_id(pool, filter_doc, update_doc).await {
+        Ok(chat_channel) => {
+            app_state.channel_groups.invalidate(chat_channel.id.as_str()).await;
+            // Notify everyone who was in the channel, including the user who was kicked
+            let event = ChannelEvent::MemberKicked(member.user_id);
+            notify_channel_event(&app_state, &channel.subscribers, chat_channel.id.as_str(), event).await;
+            ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await)
+        }
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn ban_member(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<String>,
+    Json(member): Json<ChannelMemberSchema>,
+) -> ReturnData<ReturnChannel> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let channel = match get_owned_channel(&app_state, channel_id.as_str(), user.get_id().as_str()).await {
+        Ok(channel) => channel,
+        Err(e) => return e,
+    };
+
+    if member.user_id == channel.owner_id {
+        return ReturnData::bad_request("The owner of a channel cannot be banned from it".to_string());
+    }
+
+    // A user does not need to be in the channel to be banned from it
+    let filter_doc = match channel_filter_doc(&channel) {
+        Ok(filter_doc) => filter_doc,
+        Err(db_err) => return db_err.into(),
+    };
+    let update_doc = doc! {
+        "$pull": {"subscribers": member.user_id.as_str()},
+        "$addToSet": {"banned_users": member.user_id.as_str()},
+    };
+    match update_chat_channel_by_id(pool, filter_doc, update_doc).await {
+        Ok(chat_channel) => {
+            app_state.channel_groups.invalidate(chat_channel.id.as_str()).await;
+            let event = ChannelEvent::MemberBanned(member.user_id);
+            notify_channel_event(&app_state, &channel.subscribers, chat_channel.id.as_str(), event).await;
+            ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await)
+        }
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn unban_member(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<String>,
+    Json(member): Json<ChannelMemberSchema>,
+) -> ReturnData<ReturnChannel> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let channel = match get_owned_channel(&app_state, channel_id.as_str(), user.get_id().as_str()).await {
+        Ok(channel) => channel,
+        Err(e) => return e,
+    };
+
+    if !channel.banned_users.contains(&member.user_id) {
+        return ReturnData::bad_request("That user is not banned from this channel".to_string());
+    }
+
+    let filter_doc = match channel_filter_doc(&channel) {
+        Ok(filter_doc) => filter_doc,
+        Err(db_err) => return db_err.into(),
+    };
+    let update_doc = doc! {"$pull": {"banned_users": member.user_id.as_str()}};
+    match update_chat_channel_by_id(pool, filter_doc, update_doc).await {
+        Ok(chat_channel) => ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn transfer_ownership(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<String>,
+    Json(member): Json<ChannelMemberSchema>,
+) -> ReturnData<ReturnChannel> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let channel = match get_owned_channel(&app_state, channel_id.as_str(), user.get_id().as_str()).await {
+        Ok(channel) => channel,
+        Err(e) => return e,
+    };
+
+    if member.user_id == channel.owner_id {
+        return ReturnData::bad_request("You already own this channel".to_string());
+    }
+    if !channel.subscribers.contains(&member.user_id) {
+        return ReturnData::bad_request("Ownership can only be transferred to a user in the channel".to_string());
+    }
+
+    // The slug is unique per owner, so this fails if the new owner has a channel with the same slug
+    let filter_doc = match channel_filter_doc(&channel) {
+        Ok(filter_doc) => filter_doc,
+        Err(db_err) => return db_err.into(),
+    };
+    let update_doc = doc! {"$set": {"owner_id": member.user_id.as_str()}};
+    match update_chat_channel_by_id(pool, filter_doc, update_doc).await {
+        Ok(chat_channel) => {
+            let event = ChannelEvent::OwnershipTransferred(member.user_id);
+            notify_channel_event(&app_state, &chat_channel.subscribers, chat_channel.id.as_str(), event).await;
+            ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await)
+        }
+        Err(DbError::AlreadyExists) => ReturnData::bad_request("The new owner already has a channel with this slug".to_string()),
+        Err(db_err) => db_err.into(),
+    }
+}
@@ -2,13 +2,27 @@ use super::{get_user_from_auth_header, get_user_from_token};
 use crate::api::return_data::ReturnData;
 use crate::error_handler::DbError;
 use crate::models::chat::{
-    chat_channel::ReturnChannel,
-    chat_channel_db::{get_chat_channel_by_id, hydrate_chat_channel_subscribers, insert_chat_channel, list_chat_channels, update_chat_channel_by_id},
-    message_db::{get_chat_message_span, insert_chat_message},
-    packet::{MessageCreatedResponse, WebSocketRequest, WebSocketResponse},
+    chat_channel::{ChannelType, ReturnChannel, ReturnDirectMessageChannel},
+    chat_channel_db::{
+        get_chat_channel_by_id, get_or_insert_direct_message_channel, hydrate_chat_channel_subscribers, insert_chat_channel, list_chat_channels,
+        update_chat_channel_by_id,
+    },
+    export::{export_channel, ExportFormat},
+    message::MessageSearchResult,
+    message_db::{get_chat_message_span, search_chat_messages},
+    packet::{
+        AuthenticatedResponse, ChannelEvent, ChannelEventResponse, WebSocketErrorCode, WebSocketRequest, WebSocketRequestFrame, WebSocketResponse,
+        WebSocketResponseFrame,
+    },
+    read_receipt_db::get_read_states_for_user,
+    server::permissions,
+    server_db::{filter_readable_channels, has_channel_permission},
+    socket_ticket::ReturnSocketTicket,
+    socket_ticket_db::{insert_socket_ticket, redeem_socket_ticket},
     validation::CreateChannelSchema,
 };
-use crate::{app::AppState, logger};
+use crate::models::user::user_db::db_get_user_by_id;
+use crate::{app::AppState, logger, realtime::dispatcher::SocketContext};
 use axum::{
     body::Body,
     extract::{
@@ -16,7 +30,10 @@ use axum::{
         ws::{Message, WebSocket, WebSocketUpgrade},
         Path, Query, State,
     },
-    http::{header::HeaderMap, Response, StatusCode},
+    http::{
+        header::{HeaderMap, CONTENT_DISPOSITION, CONTENT_TYPE, SEC_WEBSOCKET_PROTOCOL},
+        Response, StatusCode,
+    },
     response::IntoResponse,
     routing::{get, post, put},
     Json, Router,
@@ -25,18 +42,31 @@ use futures::{SinkExt, StreamExt};
 use mongodb::bson::oid::ObjectId;
 use mongodb::bson::{doc, Bson, Document};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::time;
+
+// The server pings every connection this often so a dead connection gets noticed
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+// A connection which has not sent anything, including a pong, in this long is closed
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
 
 pub fn chat_routes() -> Router<Arc<AppState>> {
     Router::<Arc<AppState>>::new()
         .route("/chat/ws", get(chat_connect))
+        .route("/chat/ws/ticket", post(create_socket_ticket))
         .route("/chat/channels", post(create_channel))
         .route("/chat/channels", get(list_channels))
         .route("/chat/channels/subscribe", put(channel_subscribe))
         .route("/chat/channels/unsubscribe", put(channel_unsubscribe))
         .route("/chat/channels/:channel_id", get(get_channel))
+        .route("/chat/channels/:channel_id/export", get(export_channel_history))
+        .route("/chat/direct_messages", put(open_direct_message))
+        .route("/chat/direct_messages", get(list_direct_messages))
+        .route("/chat/search", get(search_messages))
 }
 
 async fn create_channel(
@@ -49,6 +79,12 @@ async fn create_channel(
         Ok(user) => user,
         Err(e) => return e.into(),
     };
+    match channel_data.channel_type {
+        0 => return ReturnData::bad_request("Direct messages must be opened through /chat/direct_messages".to_string()),
+        1 => (),
+        2 => return ReturnData::bad_request("Server channels must be created through /chat/servers/:server_id/channels".to_string()),
+        _ => return ReturnData::bad_request("Invalid channel type".to_string()),
+    }
     match insert_chat_channel(pool, &channel_data, user.get_id()).await {
         Ok(chat_channel) => ReturnData::created(hydrate_chat_channel_subscribers(pool, chat_channel).await),
         Err(db_err) => db_err.into(),
@@ -77,6 +113,26 @@ async fn channel_subscribe(
         Err(_) => return DbError::BadId.into(),
     };
 
+    // Direct messages are only ever between their two participants, and private channels can
+    // only be joined with an invite
+    match get_chat_channel_by_id(pool, channel_data.channel_id.as_str()).await {
+        Ok(channel) => {
+            if channel.channel_type == ChannelType::DirectMessage {
+                return ReturnData::forbidden("Cannot subscribe to a direct message".to_string());
+            }
+            if channel.channel_type == ChannelType::Server {
+                return ReturnData::forbidden("Cannot subscribe to a server channel without joining the server".to_string());
+            }
+            if channel.is_private {
+                return ReturnData::forbidden("Cannot subscribe to a private channel without an invite".to_string());
+            }
+            if channel.banned_users.contains(&user_id) {
+                return ReturnData::forbidden("You are banned from this channel".to_string());
+            }
+        }
+        Err(db_err) => return db_err.into(),
+    }
+
     // subscribers filter verifies that the user is not already subscribed to this channel
     let filter_doc: Document = doc! {
         "_id": Bson::ObjectId(channel_id),
@@ -93,11 +149,25 @@ async fn channel_subscribe(
     };
 
     match update_chat_channel_by_id(pool, filter_doc, update_doc).await {
-        Ok(chat_channel) => ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await),
+        Ok(chat_channel) => {
+            app_state.channel_groups.invalidate(chat_channel.id.as_str()).await;
+            let event = ChannelEvent::MemberJoined(user_id.clone());
+            notify_channel_event(&app_state, &chat_channel.subscribers, chat_channel.id.as_str(), event).await;
+            ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await)
+        }
         Err(db_err) => db_err.into(),
     }
 }
 
+// Sends a ChannelEvent to every user in `user_ids` with an active connection
+pub async fn notify_channel_event(app_state: &AppState, user_ids: &[String], channel_id: &str, event: ChannelEvent) {
+    let response = WebSocketResponse::SendChannelEvent(ChannelEventResponse {
+        channel_id: channel_id.to_owned(),
+        event,
+    });
+    app_state.active_connections.send_to_users(user_ids, &response).await;
+}
+
 async fn channel_unsubscribe(
     State(app_state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -117,17 +187,24 @@ async fn channel_unsubscribe(
 
     // TODO: Should probably return a more meaningful error here than a 404 if a user tries to
     //       unsubscribe from a channel they aren't in, or their own channel
+    // Whoever opened a direct message is stored as its owner, but either participant can leave one
     let filter_doc: Document = doc! {
         "_id": Bson::ObjectId(channel_id),
         "subscribers": user_id.as_str(),
-        "owner_id": {"$ne": user_id.as_str()},
+        "$or": [
+            {"owner_id": {"$ne": user_id.as_str()}},
+            {"channel_type": ChannelType::DirectMessage},
+        ],
     };
     let update_doc: Document = doc! {
         "$pull": {"subscribers": user_id.as_str()}
     };
 
     match update_chat_channel_by_id(pool, filter_doc, update_doc).await {
-        Ok(chat_channel) => ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await),
+        Ok(chat_channel) => {
+            app_state.channel_groups.invalidate(chat_channel.id.as_str()).await;
+            ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await)
+        }
         Err(db_err) => db_err.into(),
     }
 }
@@ -184,14 +261,41 @@ async fn list_channels(
             }
         };
 
+        // Direct messages, private channels and server channels are hidden from anyone who isn't a participant
+        building_doc.insert(
+            "$or",
+            vec![
+                doc! {"channel_type": {"$nin": [ChannelType::DirectMessage, ChannelType::Server]}, "is_private": {"$ne": true}},
+                doc! {"subscribers": user_id.as_str()},
+            ],
+        );
+
         building_doc
     };
 
+    // Read markers for every channel the user has read, used to populate unread counts
+    let read_states: HashMap<String, i64> = match get_read_states_for_user(pool, user_id.as_str()).await {
+        Ok(read_states) => read_states
+            .into_iter()
+            .map(|read_state| (read_state.channel_id, read_state.last_read_atomic_id))
+            .collect(),
+        Err(db_err) => return db_err.into(),
+    };
+
     match list_chat_channels(pool, filter_doc).await {
         Ok(channels) => {
+            let channels = match filter_readable_channels(pool, user_id.as_str(), channels).await {
+                Ok(channels) => channels,
+                Err(db_err) => return db_err.into(),
+            };
             let mut return_channels = Vec::new();
             for channel in channels {
-                let return_channel = hydrate_chat_channel_subscribers(pool, channel).await;
+                let is_subscribed = channel.subscribers.contains(&user_id);
+                let mut return_channel = hydrate_chat_channel_subscribers(pool, channel).await;
+                if is_subscribed {
+                    let last_read = read_states.get(&return_channel._id).copied().unwrap_or(0);
+                    return_channel.set_read_state(last_read);
+                }
                 return_channels.push(return_channel);
             }
             ReturnData::ok(return_channels)
@@ -202,75 +306,376 @@ async fn list_channels(
 
 async fn get_channel(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(channel_id): Path<String>) -> ReturnData<ReturnChannel> {
     let pool = &app_state.db;
-    let _user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
         Ok(user) => user,
         Err(e) => return e.into(),
     };
     match get_chat_channel_by_id(pool, channel_id.as_str()).await {
-        Ok(channel) => ReturnData::ok(hydrate_chat_channel_subscribers(pool, channel).await),
+        Ok(channel) => {
+            if channel.channel_type == ChannelType::DirectMessage && !channel.subscribers.contains(&user.get_id()) {
+                return ReturnData::forbidden("Cannot view a direct message you are not a part of".to_string());
+            }
+            if channel.is_private && !channel.subscribers.contains(&user.get_id()) {
+                return ReturnData::forbidden("Cannot view a private channel you are not a part of".to_string());
+            }
+            if channel.server_id.is_some() && !has_channel_permission(pool, &channel, user.get_id().as_str(), permissions::READ).await {
+                return ReturnData::forbidden("You do not have permission to view this channel".to_string());
+            }
+            ReturnData::ok(hydrate_chat_channel_subscribers(pool, channel).await)
+        }
+        Err(db_err) => db_err.into(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ExportChannelQueryParams {
+    format: Option<ExportFormat>,
+}
+
+// Streams every message in the channel as it is loaded, so a large channel is never held in memory
+async fn export_channel_history(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<String>,
+    query_params: Query<ExportChannelQueryParams>,
+) -> Response<Body> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return ReturnData::<()>::from(e).into_response(),
+    };
+    let channel = match get_chat_channel_by_id(pool, channel_id.as_str()).await {
+        Ok(channel) => channel,
+        Err(db_err) => return ReturnData::<()>::from(db_err).into_response(),
+    };
+    if !has_channel_permission(pool, &channel, user.get_id().as_str(), permissions::READ).await {
+        return ReturnData::<()>::forbidden("Cannot export a channel you are not a part of".to_string()).into_response();
+    }
+
+    let format = query_params.format.unwrap_or_default();
+    let file_name = format!("channel-{}.{}", channel.id, format.file_extension());
+    let chunks = export_channel(pool.clone(), channel, format)
+        .map(|chunk| chunk.map_err(|_db_err| std::io::Error::other("Failed to load the messages of a channel export")));
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, format.content_type())
+        .header(CONTENT_DISPOSITION, format!("attachment; filename=\"{file_name}\""))
+        .body(Body::from_stream(chunks))
+        .unwrap()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OpenDirectMessageSchema {
+    pub user_id: String,
+}
+
+async fn open_direct_message(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(dm_data): Json<OpenDirectMessageSchema>,
+) -> ReturnData<ReturnDirectMessageChannel> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let user_id = user.get_id();
+
+    if dm_data.user_id == user_id {
+        return ReturnData::bad_request("Cannot open a direct message with yourself".to_string());
+    }
+    let other_user = match db_get_user_by_id(pool, dm_data.user_id.as_str()).await {
+        Ok(other_user) => other_user,
+        Err(db_err) => return db_err.into(),
+    };
+
+    match get_or_insert_direct_message_channel(pool, user_id.as_str(), other_user.get_id().as_str()).await {
+        Ok(chat_channel) => {
+            // Opening a DM again can put the participant opening it back in it
+            app_state.channel_groups.invalidate(chat_channel.id.as_str()).await;
+            ReturnData::ok(ReturnDirectMessageChannel {
+                channel: hydrate_chat_channel_subscribers(pool, chat_channel).await,
+                other_user: other_user.into(),
+            })
+        }
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn list_direct_messages(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<Vec<ReturnDirectMessageChannel>> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let user_id = user.get_id();
+
+    let filter_doc = doc! {
+        "channel_type": ChannelType::DirectMessage,
+        "subscribers": user_id.as_str(),
+    };
+    match list_chat_channels(pool, filter_doc).await {
+        Ok(channels) => {
+            let mut direct_messages = Vec::new();
+            for channel in channels {
+                let channel = hydrate_chat_channel_subscribers(pool, channel).await;
+                // If the other participant has left or deleted their account there is nobody to show
+                let other_user = match channel.subscribers.iter().find(|subscriber| subscriber.id != user_id) {
+                    Some(other_user) => other_user.clone(),
+                    None => continue,
+                };
+                direct_messages.push(ReturnDirectMessageChannel { channel, other_user });
+            }
+            ReturnData::ok(direct_messages)
+        }
         Err(db_err) => db_err.into(),
     }
 }
 
+#[derive(Deserialize)]
+struct SearchMessagesQueryParams {
+    query: String,
+    author_id: Option<String>,
+    channel_id: Option<String>,
+    // Unix timestamps bounding when a message was created, both inclusive
+    from: Option<i64>,
+    to: Option<i64>,
+    // Number of messages to include before and after each match
+    context: Option<i64>,
+    limit: Option<i64>,
+}
+
+async fn search_messages(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    query_params: Query<SearchMessagesQueryParams>,
+) -> ReturnData<Vec<MessageSearchResult>> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let user_id = user.get_id();
+
+    if query_params.query.trim().is_empty() {
+        return ReturnData::bad_request("Search query cannot be empty".to_string());
+    }
+    let context = query_params.context.unwrap_or(2);
+    if !(0..=5).contains(&context) {
+        return ReturnData::bad_request("Can only request between 0 and 5 context messages".to_string());
+    }
+    let limit = query_params.limit.unwrap_or(25);
+    if !(1..=50).contains(&limit) {
+        return ReturnData::bad_request("Can only request between 1 and 50 search results".to_string());
+    }
+
+    // Only search channels the user is subscribed to and can read
+    let subscribed_channels = match list_chat_channels(pool, doc! {"subscribers": user_id.as_str()}).await {
+        Ok(channels) => channels,
+        Err(db_err) => return db_err.into(),
+    };
+    let subscribed_channel_ids: Vec<String> = match filter_readable_channels(pool, user_id.as_str(), subscribed_channels).await {
+        Ok(channels) => channels.into_iter().map(|channel| channel.id).collect(),
+        Err(db_err) => return db_err.into(),
+    };
+
+    let filter_doc = {
+        let mut building_doc = doc! {"$text": {"$search": query_params.query.as_str()}};
+
+        match &query_params.channel_id {
+            Some(channel_id) => {
+                if !subscribed_channel_ids.contains(channel_id) {
+                    return ReturnData::bad_request("You are not in this chat channel".to_string());
+                }
+                building_doc.insert("channel_id", channel_id.as_str());
+            }
+            None => {
+                building_doc.insert("channel_id", doc! {"$in": subscribed_channel_ids});
+            }
+        }
+
+        if let Some(author_id) = &query_params.author_id {
+            building_doc.insert("author_id", author_id.as_str());
+        }
+
+        let mut created_at = doc! {};
+        if let Some(from) = query_params.from {
+            created_at.insert("$gte", from);
+        }
+        if let Some(to) = query_params.to {
+            created_at.insert("$lte", to);
+        }
+        if !created_at.is_empty() {
+            building_doc.insert("created_at", created_at);
+        }
+
+        building_doc
+    };
+
+    let matches = match search_chat_messages(pool, filter_doc, limit).await {
+        Ok(matches) => matches,
+        Err(db_err) => return db_err.into(),
+    };
+
+    let mut results = Vec::new();
+    for message in matches {
+        let (context_before, context_after) = match context {
+            0 => (Vec::new(), Vec::new()),
+            _ => {
+                let before = get_chat_message_span(pool, message.atomic_id - 1, message.channel_id.as_str(), context).await;
+                let after = get_chat_message_span(pool, message.atomic_id + context, message.channel_id.as_str(), context).await;
+                match (before, after) {
+                    (Ok(before), Ok(after)) => (before, after),
+                    (Err(db_err), _) | (_, Err(db_err)) => return db_err.into(),
+                }
+            }
+        };
+        results.push(MessageSearchResult {
+            message,
+            context_before,
+            context_after,
+        });
+    }
+    ReturnData::ok(results)
+}
+
 // WEBSOCKET
+// Clients authenticating with Sec-WebSocket-Protocol offer this alongside their ticket, and the
+// server picks it so the handshake has a protocol to agree on
+const CHAT_PROTOCOL: &str = "pat-chat";
+const TICKET_PROTOCOL_PREFIX: &str = "pat-ticket.";
+// How long a socket opened without credentials has to send its Authenticate
+const AUTHENTICATE_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Deserialize, Debug)]
 struct ChatConnectQueryParams {
-    auth_token: String,
+    // Deprecated, a ticket keeps the JWT out of the URL
+    auth_token: Option<String>,
+}
+
+async fn create_socket_ticket(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<ReturnSocketTicket> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    match insert_socket_ticket(pool, user.get_id().as_str()).await {
+        Ok(ticket) => ReturnData::created(ticket.into()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+fn ticket_from_protocols(headers: &HeaderMap) -> Option<&str> {
+    let protocols = headers.get(SEC_WEBSOCKET_PROTOCOL)?.to_str().ok()?;
+    protocols
+        .split(',')
+        .find_map(|protocol| protocol.trim().strip_prefix(TICKET_PROTOCOL_PREFIX))
+}
+
+fn unauthorized_upgrade() -> Response<Body> {
+    // Must return a Response<Body> here due to the return value of ws.on_upgrade
+    // TODO: Handle converting ws.on_upgrade response into a ReturnData response so I can
+    //       just return `ResponseData::from(e)` here.
+    //       ws.on_upgrade must return a 101 with specific headers set
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .body(Body::from("Failed to authorize websocket request"))
+        .unwrap()
 }
 
 async fn chat_connect(
     ws: WebSocketUpgrade,
     State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
     query_params: Query<ChatConnectQueryParams>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
 ) -> impl IntoResponse {
     let pool = &app_state.db;
-    let user = match get_user_from_token(pool, query_params.auth_token.as_str(), &app_state.config.app_secret).await {
-        Ok(user) => user,
-        Err(_e) => {
-            // Must return a Response<Body> here due to the return value of ws.on_upgrade
-            // TODO: Handle converting ws.on_upgrade response into a ReturnData response so I can
-            //       just return `ResponseData::from(e)` here.
-            //       ws.on_upgrade must return a 101 with specific headers set
-            return Response::builder()
-                .status(StatusCode::UNAUTHORIZED)
-                .body(Body::from("Failed to authorize websocket request"))
-                .unwrap();
+    // Credentials given with the upgrade are checked before it happens. A socket opened without any
+    // has to authenticate with its first request instead
+    let user_id = if let Some(ticket) = ticket_from_protocols(&headers) {
+        match redeem_socket_ticket(pool, ticket).await {
+            Ok(ticket) => Some(ticket.user_id),
+            Err(_e) => return unauthorized_upgrade(),
+        }
+    } else if let Some(auth_token) = &query_params.auth_token {
+        match get_user_from_token(pool, auth_token.as_str(), &app_state.config.app_secret).await {
+            Ok(user) => Some(user.get_id()),
+            Err(_e) => return unauthorized_upgrade(),
         }
+    } else {
+        None
     };
 
-    ws.on_upgrade(move |socket| handle_socket(socket, addr, user.get_id(), app_state))
+    let ws = ws.protocols([CHAT_PROTOCOL]);
+    match user_id {
+        Some(user_id) => ws.on_upgrade(move |socket| handle_socket(socket, addr, user_id, app_state)),
+        None => ws.on_upgrade(move |socket| authenticate_socket(socket, addr, app_state)),
+    }
 }
 
-async fn handle_socket(socket: WebSocket, _who: SocketAddr, user_id: String, app_state: Arc<AppState>) {
-    // TODO: This function is way too large and should be broken up
-
-    // Create a channel to send messages
-    let (tx, mut rx) = mpsc::unbounded_channel::<WebSocketResponse>();
-
-    // Register the connection
-    {
-        let mut connections = app_state.active_connections.write().await;
-
-        // Check if this user already has a connection
-        if connections.contains_key(user_id.as_str()) {
-            connections
-                .remove(user_id.as_str())
-                .expect("Hashmap should contain a value when .contains_key was true");
+// Waits for the Authenticate of a socket which was opened without credentials. The socket is
+// closed if anything else arrives first, or nothing arrives in time
+async fn authenticate_socket(mut socket: WebSocket, who: SocketAddr, app_state: Arc<AppState>) {
+    let text = match time::timeout(AUTHENTICATE_TIMEOUT, socket.recv()).await {
+        Ok(Some(Ok(Message::Text(text)))) => text,
+        _ => {
+            let _ = socket.send(Message::Close(None)).await;
+            return;
         }
+    };
+    let frame = serde_json::from_str::<WebSocketRequestFrame>(text.as_str());
+    let request_id = frame.as_ref().ok().and_then(|frame| frame.request_id.clone());
+    let user_id = match frame.map(|frame| frame.request) {
+        Ok(WebSocketRequest::Authenticate(authenticate)) => redeem_socket_ticket(&app_state.db, authenticate.ticket.as_str())
+            .await
+            .ok()
+            .map(|ticket| ticket.user_id),
+        _ => None,
+    };
 
-        connections.insert(user_id.clone(), tx);
+    let response = match &user_id {
+        Some(user_id) => WebSocketResponse::Authenticated(AuthenticatedResponse { user_id: user_id.clone() }),
+        None => WebSocketResponse::ws_error(
+            WebSocketErrorCode::Unauthorized,
+            "The first request on a socket must Authenticate with a valid ticket",
+        ),
+    };
+    if let Ok(text) = serde_json::to_string(&WebSocketResponseFrame { response, request_id }) {
+        if socket.send(Message::Text(text)).await.is_err() {
+            return;
+        }
     }
+    match user_id {
+        Some(user_id) => handle_socket(socket, who, user_id, app_state).await,
+        None => {
+            let _ = socket.send(Message::Close(None)).await;
+        }
+    }
+}
+
+async fn handle_socket(socket: WebSocket, _who: SocketAddr, user_id: String, app_state: Arc<AppState>) {
+    // Create a channel to send messages
+    let (tx, mut rx) = mpsc::unbounded_channel::<WebSocketResponseFrame>();
+
+    // Register the connection, a user can have a connection open on more than one device
+    let connection_id = app_state.active_connections.register(user_id.as_str(), tx.clone()).await;
 
     let (mut sender, mut receiver) = socket.split();
 
     // Spawn a task to receive messages from the socket
-    let user_id_read_task = user_id.clone();
-    let cloned_state = app_state.clone();
+    let ctx = Arc::new(SocketContext {
+        app_state: app_state.clone(),
+        user_id: user_id.clone(),
+        connection_id: connection_id.clone(),
+    });
+    // Responses to a request only go back to the connection which made it
+    let connection_tx = tx;
     let read_task = tokio::spawn(async move {
-        // Axum and the client will handle transmitting a heartbeat, so this will always return a Some
-        // until the connection is closed. When receiver.next() gets a None, the while loop is terminated
-        while let Some(Ok(msg)) = receiver.next().await {
+        // The write task pings the client every HEARTBEAT_INTERVAL and the client answers with a pong, so a
+        // live connection always has something to read before IDLE_TIMEOUT. The while loop is terminated when
+        // receiver.next() gets a None because the connection closed, or when nothing was read in time
+        while let Ok(Some(Ok(msg))) = time::timeout(IDLE_TIMEOUT, receiver.next()).await {
             // Can make this connection more resilient by doing `while let Some(maybe_errd_msg) = receiver.next().await
             // and then matching on maybe_errd_msg to handle the non-fatal-error myself, but that might not matter for
             // a simple chat app. As is currently written, a non-fatal-error will cause the `while let` to fail,
@@ -278,124 +683,41 @@ async fn handle_socket(socket: WebSocket, _who: SocketAddr, user_id: String, app
 
             // TODO: Should I match here instead of using an if_let to handle binary data or a Close?
             if let Message::Text(text) = msg {
-                match serde_json::from_str::<WebSocketRequest>(text.as_str()) {
-                    Ok(WebSocketRequest::CreateMessage(msg_to_create)) => {
-                        // Can this be made more readable
-
-                        // Verify that the message is being sent to a valid channel that the sender is in
-                        let channel_id = msg_to_create.channel_id.as_str();
-                        let maybe_response: Option<WebSocketResponse> = match get_chat_channel_by_id(&cloned_state.db, channel_id).await {
-                            Ok(channel) => {
-                                // Verify that the current user is a subscriber of the channel
-                                if channel.subscribers.contains(&user_id_read_task) {
-                                    // Create a db entry for this message
-                                    match insert_chat_message(&cloned_state.db, msg_to_create, user_id_read_task.as_str()).await {
-                                        Ok(chat_message) => {
-                                            // Check to see if any subscribers of the destination channel have active connections
-                                            for subscriber in channel.subscribers {
-                                                if let Some(tx) = cloned_state.active_connections.read().await.get(subscriber.as_str()) {
-                                                    let _ = tx.send(WebSocketResponse::SendChatMessage(chat_message.clone()));
-                                                }
-                                            }
-                                            let response = MessageCreatedResponse {
-                                                atomic_message_id: chat_message.atomic_id,
-                                                chat_channel_id: chat_message.channel_id,
-                                            };
-                                            Some(WebSocketResponse::MessageCreated(response))
-                                        }
-                                        Err(_e) => Some(WebSocketResponse::ws_error(500, "Unhandled failure while creating a chat message")),
-                                    }
-                                } else {
-                                    Some(WebSocketResponse::ws_error(400, "You are not in this chat channel"))
-                                }
-                            }
-                            Err(_) => {
-                                // TODO: Actual error handling, status code should be an enum
-                                Some(WebSocketResponse::ws_error(404, "Chat channel does not exist"))
-                            }
-                        };
-
-                        if let Some(response) = maybe_response {
-                            let connections = cloned_state.active_connections.read().await;
-                            match connections.get(user_id_read_task.as_str()) {
-                                Some(tx) => {
-                                    let _ = tx.send(response);
-                                }
-                                None => {
-                                    // Currently active connection is gone, log this?
-                                }
-                            };
-                        }
-                    }
-                    Ok(WebSocketRequest::GetChatState(msg_request)) => {
-                        let get_chat_state_res: WebSocketResponse = {
-                            // This should be done in a validation step instead of being checked like this
-                            if msg_request.message_count > 50 {
-                                WebSocketResponse::ws_error(400, "Can only request a maximum of 50 messages at a time")
-                            } else {
-                                match get_chat_channel_by_id(&cloned_state.db, msg_request.channel_id.as_str()).await {
-                                    // TODO: Getting the channel and checking if the user is in it is being repeated, this should be
-                                    //       abstracted better
-                                    Ok(channel) => {
-                                        if channel.subscribers.contains(&user_id_read_task) {
-                                            match get_chat_message_span(
-                                                &cloned_state.db,
-                                                msg_request.atomic_message_id,
-                                                msg_request.channel_id.as_str(),
-                                                msg_request.message_count,
-                                            )
-                                            .await
-                                            {
-                                                Ok(messages) => WebSocketResponse::SendChatState(messages),
-                                                Err(_e) => WebSocketResponse::ws_error(500, "Unhandled error while reading chat messages"),
-                                            }
-                                        } else {
-                                            WebSocketResponse::ws_error(400, "You are not in this chat channel")
-                                        }
-                                    }
-                                    Err(_e) => WebSocketResponse::ws_error(404, "Chat channel does not exist"),
-                                }
-                            }
-                        };
-                        let connections = cloned_state.active_connections.read().await;
-                        match connections.get(user_id_read_task.as_str()) {
-                            Some(tx) => {
-                                let _ = tx.send(get_chat_state_res);
-                            }
-                            None => {
-                                // Currently active connection is gone, log this?
-                            }
-                        };
-                    }
-                    Err(_e) => {
-                        // Would be nice to give more info to the user here about what failed
-                        let websocket_error = WebSocketResponse::ws_error(400, "Failed to decode received data");
-                        let connections = cloned_state.active_connections.read().await;
-                        match connections.get(user_id_read_task.as_str()) {
-                            Some(tx) => {
-                                let _ = tx.send(websocket_error);
-                            }
-                            None => {
-                                // Currently active connection is gone, log this?
-                            }
-                        };
-                        logger::log_msg("Error while deserializing a websocket packet from a string");
-                    }
+                if let Some(frame) = ctx.app_state.socket_dispatcher.dispatch(ctx.clone(), text.as_str()).await {
+                    let _ = connection_tx.send(frame);
                 }
             }
         }
 
         // Clean up on disconnect
-        cloned_state.active_connections.write().await.remove(user_id_read_task.as_str());
+        ctx.app_state
+            .active_connections
+            .unregister(ctx.user_id.as_str(), ctx.connection_id.as_str())
+            .await;
     });
 
     // Spawn a task to send messages to the socket
     let write_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            if let Ok(text) = serde_json::to_string(&msg) {
-                if sender.send(Message::Text(text)).await.is_err() {
-                    // TODO: HANDLE ERROR HERE
-                    logger::log_msg("Error while sending a WebsocketMessage to a sender");
+        let mut heartbeat = time::interval(HEARTBEAT_INTERVAL);
+        heartbeat.tick().await; // The first tick of an interval resolves instantly
+        loop {
+            tokio::select! {
+                msg = rx.recv() => {
+                    let msg = match msg {
+                        Some(msg) => msg,
+                        None => break,
+                    };
+                    if let Ok(text) = serde_json::to_string(&msg) {
+                        if sender.send(Message::Text(text)).await.is_err() {
+                            // TODO: HANDLE ERROR HERE
+                            logger::log_msg("Error while sending a WebsocketMessage to a sender");
+                        }
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    if sender.send(Message::Ping(Vec::new())).await.is_err() {
+                        break;
+                    }
                 }
             }
         }
@@ -415,5 +737,5 @@ async fn handle_socket(socket: WebSocket, _who: SocketAddr, user_id: String, app
     }
 
     // Cleanup, in case the write_task is closed before the read_task
-    app_state.active_connections.write().await.remove(user_id.as_str());
+    app_state.active_connections.unregister(user_id.as_str(), connection_id.as_str()).await;
 }
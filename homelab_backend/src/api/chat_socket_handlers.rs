@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use crate::{
+    error_handler::DbError,
+    models::chat::{
+        chat_channel::{ChannelType, ChatChannel},
+        chat_channel_db::{get_chat_channel_by_id, set_channel_pinned_message},
+        message_db::{
+            get_chat_message_by_id, get_chat_message_span, get_chat_messages_after, get_thread_replies, insert_chat_message, set_chat_message_pinned,
+        },
+        packet::{
+            GetThreadSchema, MarkReadSchema, MessageCreatedResponse, MessagePinnedResponse, PinMessageSchema, ReadReceiptResponse,
+            RequestMessagesSchema, ResumeResponse, ResumeSchema, ResumedChannel, ThreadResponse, WebSocketError, WebSocketErrorCode,
+            WebSocketResponse,
+        },
+        read_receipt_db::mark_channel_read,
+        server::permissions,
+        server_db::{get_channel_permissions, has_channel_permission},
+        validation::{CreateMessageSchema, MAX_MESSAGE_LENGTH},
+    },
+    realtime::{
+        dispatcher::{HandlerResult, SocketContext, SocketDispatcher},
+        event_bus::ChatEvent,
+    },
+};
+
+// Limits on how much a single Resume will replay
+const RESUME_MAX_CHANNELS: usize = 50;
+const RESUME_MAX_MESSAGES: i64 = 100;
+
+pub fn register_chat_handlers(dispatcher: &mut SocketDispatcher) {
+    dispatcher.register(create_message);
+    dispatcher.register(get_chat_state);
+    dispatcher.register(mark_read);
+    dispatcher.register(get_thread);
+    dispatcher.register(pin_message);
+    dispatcher.register(resume);
+}
+
+// Loads a channel the user can read and also has `permission` in. Every request which targets a
+// channel starts with this
+async fn channel_with_permission(ctx: &SocketContext, channel_id: &str, permission: i64) -> Result<ChatChannel, WebSocketError> {
+    let channel = get_chat_channel_by_id(&ctx.app_state.db, channel_id).await?;
+    let perms = get_channel_permissions(&ctx.app_state.db, &channel, ctx.user_id.as_str())
+        .await
+        .unwrap_or(0);
+    if perms & permissions::READ == 0 {
+        return Err(WebSocketError::new(WebSocketErrorCode::BadRequest, "You are not in this chat channel"));
+    }
+    if perms & permission != permission {
+        return Err(WebSocketError::new(WebSocketErrorCode::Forbidden, missing_permission_message(permission)));
+    }
+    Ok(channel)
+}
+
+fn missing_permission_message(permission: i64) -> &'static str {
+    match permission {
+        permissions::SEND => "You do not have permission to send messages in this channel",
+        permissions::PIN => "You do not have permission to pin messages in this channel",
+        _ => "You do not have permission to do that in this channel",
+    }
+}
+
+async fn create_message(ctx: Arc<SocketContext>, msg_to_create: CreateMessageSchema) -> HandlerResult {
+    if msg_to_create.contents.chars().count() > MAX_MESSAGE_LENGTH {
+        let msg = format!("Messages can be at most {MAX_MESSAGE_LENGTH} characters long");
+        return Err(WebSocketError::new(WebSocketErrorCode::BadRequest, msg.as_str()));
+    }
+    let db = &ctx.app_state.db;
+    let channel = channel_with_permission(&ctx, msg_to_create.channel_id.as_str(), permissions::SEND).await?;
+
+    // Checked after the channel so a flood of requests for made up channels can't fill the limiter
+    let rate_limiters = &ctx.app_state.rate_limiters;
+    rate_limiters
+        .user_messages
+        .check(ctx.user_id.as_str())
+        .map_err(|retry_after| WebSocketError::rate_limited("You are sending messages too quickly", retry_after))?;
+    rate_limiters
+        .channel_messages
+        .check(channel.id.as_str())
+        .map_err(|retry_after| WebSocketError::rate_limited("This channel is receiving too many messages", retry_after))?;
+
+    // Custom failures come from validation inside the message transaction, like an invalid reply_to,
+    // and are sent back as a BadRequest
+    let chat_message = insert_chat_message(db, msg_to_create, ctx.user_id.as_str()).await?;
+
+    // The author has obviously read their own message
+    let _ = mark_channel_read(db, ctx.user_id.as_str(), chat_message.channel_id.as_str(), chat_message.atomic_id).await;
+
+    // Every instance of the backend sends the message to its connections that can read the channel
+    ctx.app_state.event_bus.publish(ChatEvent::MessageCreated(chat_message.clone()));
+    let response = MessageCreatedResponse {
+        atomic_message_id: chat_message.atomic_id,
+        chat_channel_id: chat_message.channel_id,
+    };
+    Ok(Some(WebSocketResponse::MessageCreated(response)))
+}
+
+async fn get_chat_state(ctx: Arc<SocketContext>, msg_request: RequestMessagesSchema) -> HandlerResult {
+    // This should be done in a validation step instead of being checked like this
+    if msg_request.message_count > 50 {
+        return Err(WebSocketError::new(
+            WebSocketErrorCode::BadRequest,
+            "Can only request a maximum of 50 messages at a time",
+        ));
+    }
+    let channel = channel_with_permission(&ctx, msg_request.channel_id.as_str(), permissions::READ).await?;
+    let messages = get_chat_message_span(
+        &ctx.app_state.db,
+        msg_request.atomic_message_id,
+        channel.id.as_str(),
+        msg_request.message_count,
+    )
+    .await?;
+    Ok(Some(WebSocketResponse::SendChatState(messages)))
+}
+
+async fn mark_read(ctx: Arc<SocketContext>, mark_read: MarkReadSchema) -> HandlerResult {
+    let app_state = &ctx.app_state;
+    let channel = channel_with_permission(&ctx, mark_read.channel_id.as_str(), permissions::READ).await?;
+
+    // Clamp to the most recent message so a client can't mark messages that don't exist yet as read
+    let atomic_id = mark_read.atomic_message_id.min(channel.most_recent_message_id);
+    let read_state = mark_channel_read(&app_state.db, ctx.user_id.as_str(), channel.id.as_str(), atomic_id).await?;
+    let receipt = ReadReceiptResponse {
+        channel_id: read_state.channel_id,
+        user_id: read_state.user_id,
+        last_read_atomic_id: read_state.last_read_atomic_id,
+    };
+
+    // Read receipts are only shared in direct messages
+    if channel.channel_type == ChannelType::DirectMessage && mark_read.send_read_receipt.unwrap_or(true) {
+        let others = channel.subscribers.iter().filter(|s| **s != ctx.user_id);
+        let read_receipt = WebSocketResponse::ReadReceipt(receipt.clone());
+        app_state.active_connections.send_to_users(others, &read_receipt).await;
+    }
+
+    // Let the user's other devices know the channel was read too
+    let marked_read = WebSocketResponse::MarkedRead(receipt);
+    app_state
+        .active_connections
+        .send_to_other_connections(ctx.user_id.as_str(), ctx.connection_id.as_str(), &marked_read)
+        .await;
+    Ok(Some(marked_read))
+}
+
+async fn get_thread(ctx: Arc<SocketContext>, thread_request: GetThreadSchema) -> HandlerResult {
+    if thread_request.message_count > 50 || thread_request.message_count < 1 {
+        return Err(WebSocketError::new(
+            WebSocketErrorCode::BadRequest,
+            "Can only request between 1 and 50 replies at a time",
+        ));
+    }
+    let db = &ctx.app_state.db;
+    let channel = channel_with_permission(&ctx, thread_request.channel_id.as_str(), permissions::READ).await?;
+    let parent = get_chat_message_by_id(db, channel.id.as_str(), thread_request.message_id.as_str()).await?;
+    let (replies, has_more) = get_thread_replies(
+        db,
+        channel.id.as_str(),
+        parent.id.as_str(),
+        thread_request.after_atomic_id,
+        thread_request.message_count,
+    )
+    .await?;
+    Ok(Some(WebSocketResponse::SendThread(ThreadResponse { parent, replies, has_more })))
+}
+
+// Everyone who can read the channel hears about the pin, including whoever pinned the message, so
+// nothing is sent back directly unless it fails
+async fn pin_message(ctx: Arc<SocketContext>, pin_request: PinMessageSchema) -> HandlerResult {
+    let db = &ctx.app_state.db;
+    let channel = channel_with_permission(&ctx, pin_request.channel_id.as_str(), permissions::PIN).await?;
+    let message = set_chat_message_pinned(db, channel.id.as_str(), pin_request.message_id.as_str(), pin_request.pinned).await?;
+    set_channel_pinned_message(db, channel.id.as_str(), message.id.as_str(), message.pinned).await?;
+
+    let event = ChatEvent::MessagePinned(MessagePinnedResponse {
+        channel_id: message.channel_id,
+        message_id: message.id,
+        pinned: message.pinned,
+    });
+    ctx.app_state.event_bus.publish(event);
+    Ok(None)
+}
+
+// Replays the messages a client missed while it was disconnected. Messages sent after the client
+// reconnected can show up both live and in the replay, clients should de-duplicate on atomic_id
+async fn resume(ctx: Arc<SocketContext>, resume_request: ResumeSchema) -> HandlerResult {
+    if resume_request.channels.len() > RESUME_MAX_CHANNELS {
+        return Err(WebSocketError::new(
+            WebSocketErrorCode::BadRequest,
+            "Can only resume up to 50 channels at a time",
+        ));
+    }
+    let db = &ctx.app_state.db;
+    let mut channels = Vec::new();
+    let mut lost_channels = Vec::new();
+    for resume_channel in resume_request.channels {
+        let channel = match get_chat_channel_by_id(db, resume_channel.channel_id.as_str()).await {
+            Ok(channel) => channel,
+            Err(DbError::NotFound(_)) | Err(DbError::BadId) => {
+                lost_channels.push(resume_channel.channel_id);
+                continue;
+            }
+            Err(db_err) => return Err(db_err.into()),
+        };
+        if !has_channel_permission(db, &channel, ctx.user_id.as_str(), permissions::READ).await {
+            lost_channels.push(resume_channel.channel_id);
+            continue;
+        }
+        let (messages, has_more) = get_chat_messages_after(db, channel.id.as_str(), resume_channel.last_atomic_id, RESUME_MAX_MESSAGES).await?;
+        channels.push(ResumedChannel {
+            channel_id: channel.id,
+            messages,
+            has_more,
+        });
+    }
+    Ok(Some(WebSocketResponse::Resumed(ResumeResponse { channels, lost_channels })))
+}
@@ -4,16 +4,47 @@ use super::get_user_from_auth_header;
 use super::return_data::ReturnData;
 use crate::app::AppState;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::header::HeaderMap,
-    routing::{get, post, put},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 
-use crate::models::games::{
-    games_db::{get_all_connections_games, get_connection_game_by_slug, insert_connections_game},
-    validation::CreateConnectionGameSchema,
-    ConnectionGame, MinimalConnectionsGame, PlayConnectionGame, TrySolveRow,
+use mongodb::bson::doc;
+use serde::Deserialize;
+
+use crate::{
+    error_handler::DbError,
+    realtime::event_bus::ChatEvent,
+    util::{current_unix_time, is_valid_date, unix_time_to_date},
+};
+
+use crate::models::{
+    chat::{
+        chat_channel_db::get_chat_channel_by_id, message::ChatMessage, message_db::insert_chat_message, read_receipt_db::mark_channel_read,
+        server::permissions, server_db::get_channel_permissions, validation::CreateMessageSchema,
+    },
+    games::{
+        daily::{DailyPuzzle, ReturnDailyPuzzle},
+        daily_db::{delete_daily_puzzle_for_date, get_daily_puzzle_for_date, get_daily_puzzle_queue, insert_daily_puzzle},
+        games_db::{
+            delete_connections_game, get_all_connections_games, get_connection_game_by_id, get_connection_game_by_slug, insert_connections_game,
+            list_connections_games, publish_connections_game, update_connections_game,
+        },
+        rating::ConnectionGameRating,
+        rating_db::upsert_rating,
+        session::{ConnectionGameSession, ReturnConnectionGameSession},
+        session_db::{delete_sessions_for_user, game_has_player_sessions, get_session, save_session_guess, start_or_resume_session},
+        share::{result_grid, ShareableResult},
+        stats::{AuthorStats, LeaderboardEntry, PlayerStats},
+        stats_db::{get_author_stats, get_leaderboard, get_player_stats},
+        validation::{
+            normalize_tag, ConnectionsGameSort, CreateConnectionGameSchema, RateConnectionGameSchema, ScheduleDailyPuzzleSchema, ShareResultSchema,
+            UpdateConnectionGameSchema,
+        },
+        ConnectionGame, ListedConnectionsGame, MinimalConnectionsGame, PlayConnectionGame, TrySolveRow,
+    },
+    user::AuthLevel,
 };
 
 pub fn games_routes() -> Router<Arc<AppState>> {
@@ -21,8 +52,22 @@ pub fn games_routes() -> Router<Arc<AppState>> {
         .route("/games/connections", post(create_connections))
         .route("/games/connections", get(list_other_connections_games))
         .route("/games/connections/mine", get(list_my_connections_games))
+        .route("/games/connections/:game_id", put(update_connections))
+        .route("/games/connections/:game_id", delete(delete_connections))
+        .route("/games/connections/:game_id/publish", put(publish_connections))
         .route("/games/connections/play/:game_slug", get(get_game_to_play))
+        .route("/games/connections/play/:game_slug/session", put(start_or_resume_game))
         .route("/games/connections/play/:game_slug/try_solve", put(try_solve_row))
+        .route("/games/connections/play/:game_slug/leaderboard", get(get_game_leaderboard))
+        .route("/games/connections/play/:game_slug/rating", put(rate_game))
+        .route("/games/connections/play/:game_slug/share", get(get_shareable_result))
+        .route("/games/connections/play/:game_slug/share", post(share_result_to_chat))
+        .route("/games/connections/daily", get(get_daily_puzzle))
+        .route("/games/connections/daily", post(schedule_daily_puzzle))
+        .route("/games/connections/daily/queue", get(list_daily_puzzle_queue))
+        .route("/games/connections/daily/:date", delete(unschedule_daily_puzzle))
+        .route("/games/connections/stats/me", get(get_my_stats))
+        .route("/games/connections/stats/authored", get(get_my_author_stats))
 }
 
 async fn create_connections(
@@ -35,12 +80,84 @@ async fn create_connections(
         Ok(user) => user,
         Err(e) => return e.into(),
     };
+    if let Err(errors) = connection_data.validate() {
+        return ReturnData::validation_failed(errors);
+    }
     match insert_connections_game(pool, &connection_data, user.get_id()).await {
         Ok(connection_game) => ReturnData::created(connection_game),
         Err(db_err) => db_err.into(),
     }
 }
 
+// Gets a game for its author to manage, anyone else is Forbidden
+async fn get_own_connections_game<T>(app_state: &AppState, headers: &HeaderMap, game_id: &str) -> Result<ConnectionGame, ReturnData<T>> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return Err(e.into()),
+    };
+    let connections_game = match get_connection_game_by_id(pool, game_id).await {
+        Ok(connections_game) => connections_game,
+        Err(db_err) => return Err(db_err.into()),
+    };
+    if connections_game.author_id != user.get_id() {
+        return Err(ReturnData::forbidden("Only the author of a connections game can change it".to_string()));
+    }
+    Ok(connections_game)
+}
+
+async fn update_connections(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(game_id): Path<String>,
+    Json(update_data): Json<UpdateConnectionGameSchema>,
+) -> ReturnData<ConnectionGame> {
+    let pool = &app_state.db;
+    let connections_game = match get_own_connections_game(&app_state, &headers, game_id.as_str()).await {
+        Ok(connections_game) => connections_game,
+        Err(e) => return e,
+    };
+    if let Err(errors) = update_data.validate() {
+        return ReturnData::validation_failed(errors);
+    }
+    // Changing a puzzle out from under the people playing it would break their sessions, a puzzle
+    // which has been played has to be deleted and made again instead
+    match game_has_player_sessions(pool, connections_game.id.as_str(), connections_game.author_id.as_str()).await {
+        Ok(true) => return ReturnData::bad_request("A connections game which has been played can't be edited".to_string()),
+        Ok(false) => {}
+        Err(db_err) => return db_err.into(),
+    }
+    if let Err(db_err) = delete_sessions_for_user(pool, connections_game.id.as_str(), connections_game.author_id.as_str()).await {
+        return db_err.into();
+    }
+    match update_connections_game(pool, &connections_game, &update_data).await {
+        Ok(connections_game) => ReturnData::ok(connections_game),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn publish_connections(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(game_id): Path<String>) -> ReturnData<ConnectionGame> {
+    let connections_game = match get_own_connections_game(&app_state, &headers, game_id.as_str()).await {
+        Ok(connections_game) => connections_game,
+        Err(e) => return e,
+    };
+    match publish_connections_game(&app_state.db, &connections_game).await {
+        Ok(connections_game) => ReturnData::ok(connections_game),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn delete_connections(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(game_id): Path<String>) -> ReturnData<()> {
+    let connections_game = match get_own_connections_game(&app_state, &headers, game_id.as_str()).await {
+        Ok(connections_game) => connections_game,
+        Err(e) => return e,
+    };
+    match delete_connections_game(&app_state.db, &connections_game).await {
+        Ok(_) => ReturnData::ok(()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
 async fn list_my_connections_games(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<Vec<MinimalConnectionsGame>> {
     // TODO: This should be paginated
     // TODO: This and list_other_connections_games should both just call a shared function passing it a true/false
@@ -58,18 +175,70 @@ async fn list_my_connections_games(State(app_state): State<Arc<AppState>>, heade
     }
 }
 
-async fn list_other_connections_games(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<Vec<MinimalConnectionsGame>> {
-    // TODO: This should be paginated
+#[derive(Deserialize, Debug)]
+struct ListConnectionsGamesQueryParams {
+    #[serde(default)]
+    sort: ConnectionsGameSort,
+    author_id: Option<String>,
+    tag: Option<String>,
+    // Only puzzles the user hasn't started
+    #[serde(default)]
+    unplayed: bool,
+    // Starting from 1
+    page: Option<u64>,
+    per_page: Option<i64>,
+}
+
+async fn list_other_connections_games(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    query_params: Query<ListConnectionsGamesQueryParams>,
+) -> ReturnData<Vec<ListedConnectionsGame>> {
     let pool = &app_state.db;
     let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
         Ok(user) => user,
         Err(e) => return e.into(),
     };
-    match get_all_connections_games(pool, user.get_id().as_str(), false).await {
-        Ok(connections_games) => {
-            let minimized_games = connections_games.into_iter().map(|game| game.into()).collect();
-            ReturnData::ok(minimized_games)
+    let user_id = user.get_id();
+
+    let page = query_params.page.unwrap_or(1);
+    if page < 1 {
+        return ReturnData::bad_request("Pages start from 1".to_string());
+    }
+    let per_page = query_params.per_page.unwrap_or(20);
+    if !(1..=50).contains(&per_page) {
+        return ReturnData::bad_request("Can only request between 1 and 50 connections games per page".to_string());
+    }
+    // Mongo takes the skip as an i64, a page past that can't exist
+    let skip = match (page - 1).checked_mul(per_page as u64).and_then(|skip| i64::try_from(skip).ok()) {
+        Some(skip) => skip,
+        None => return ReturnData::bad_request("That page is out of range".to_string()),
+    };
+
+    let filter_doc = {
+        let mut author_filter = doc! {"$ne": user_id.as_str()};
+        if let Some(author_id) = &query_params.author_id {
+            author_filter.insert("$eq", author_id.as_str());
+        }
+        let mut building_doc = doc! {"author_id": author_filter, "draft": {"$ne": true}};
+        if let Some(tag) = &query_params.tag {
+            building_doc.insert("tags", normalize_tag(tag));
         }
+        building_doc
+    };
+
+    match list_connections_games(
+        pool,
+        user_id.as_str(),
+        filter_doc,
+        query_params.unplayed,
+        query_params.sort,
+        skip,
+        per_page,
+    )
+    .await
+    {
+        Ok(connections_games) => ReturnData::ok(connections_games),
         Err(db_err) => db_err.into(),
     }
 }
@@ -80,16 +249,36 @@ async fn get_game_to_play(
     Path(game_slug): Path<String>,
 ) -> ReturnData<PlayConnectionGame> {
     let pool = &app_state.db;
-    let _user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
         Ok(user) => user,
         Err(e) => return e.into(),
     };
-    match get_connection_game_by_slug(pool, game_slug.as_str()).await {
+    match get_connection_game_by_slug(pool, game_slug.as_str(), user.get_id().as_str()).await {
         Ok(connections_game) => ReturnData::ok(connections_game.into()),
         Err(db_err) => db_err.into(),
     }
 }
 
+async fn start_or_resume_game(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(game_slug): Path<String>,
+) -> ReturnData<ReturnConnectionGameSession> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let connections_game = match get_connection_game_by_slug(pool, game_slug.as_str(), user.get_id().as_str()).await {
+        Ok(connections_game) => connections_game,
+        Err(db_err) => return db_err.into(),
+    };
+    match start_or_resume_session(pool, user.get_id().as_str(), connections_game.id.as_str()).await {
+        Ok(session) => ReturnData::ok(ReturnConnectionGameSession::new(session, connections_game)),
+        Err(db_err) => db_err.into(),
+    }
+}
+
 async fn try_solve_row(
     State(app_state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -97,27 +286,339 @@ async fn try_solve_row(
     Json(row_guess): Json<[String; 4]>,
 ) -> ReturnData<TrySolveRow> {
     let pool = &app_state.db;
-    let _user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let connections_game = match get_connection_game_by_slug(pool, game_slug.as_str(), user.get_id().as_str()).await {
+        Ok(connections_game) => connections_game,
+        Err(db_err) => return db_err.into(),
+    };
+    // Guessing without starting a session first starts one
+    let mut session = match start_or_resume_session(pool, user.get_id().as_str(), connections_game.id.as_str()).await {
+        Ok(session) => session,
+        Err(db_err) => return db_err.into(),
+    };
+    if session.is_over() {
+        return ReturnData::bad_request("This game is already over".to_string());
+    }
+
+    if let Err(msg) = check_guess_is_playable(&connections_game, &session.solved_categories, &row_guess) {
+        return ReturnData::bad_request(msg);
+    }
+    // Making the same guess twice is almost always a misclick, so it doesn't cost a mistake
+    if session.has_guessed(&row_guess) {
+        return ReturnData::ok(TrySolveRow {
+            row_name: None,
+            correct_guess: false,
+            one_away: false,
+            already_guessed: true,
+            mistakes_remaining: session.mistakes_remaining,
+            state: session.state,
+        });
+    }
+
+    let (row_name, one_away) = check_if_solution_is_valid(&connections_game, &session.solved_categories, &row_guess);
+    session.apply_guess(row_guess, row_name.clone(), current_unix_time());
+    match save_session_guess(pool, &session).await {
+        Ok(session) => ReturnData::ok(TrySolveRow {
+            correct_guess: row_name.is_some(),
+            row_name,
+            one_away,
+            already_guessed: false,
+            mistakes_remaining: session.mistakes_remaining,
+            state: session.state,
+        }),
+        Err(DbError::NotFound(_)) => ReturnData::bad_request("Another guess was made at the same time, try again".to_string()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn get_game_leaderboard(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(game_slug): Path<String>,
+) -> ReturnData<Vec<LeaderboardEntry>> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let connections_game = match get_connection_game_by_slug(pool, game_slug.as_str(), user.get_id().as_str()).await {
+        Ok(connections_game) => connections_game,
+        Err(db_err) => return db_err.into(),
+    };
+    match get_leaderboard(pool, &connections_game).await {
+        Ok(leaderboard) => ReturnData::ok(leaderboard),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+// Players can rate a puzzle once they have finished it, and change their rating later
+async fn rate_game(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(game_slug): Path<String>,
+    Json(rating_data): Json<RateConnectionGameSchema>,
+) -> ReturnData<ConnectionGameRating> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    if let Err(errors) = rating_data.validate() {
+        return ReturnData::validation_failed(errors);
+    }
+    let user_id = user.get_id();
+    let connections_game = match get_connection_game_by_slug(pool, game_slug.as_str(), user_id.as_str()).await {
+        Ok(connections_game) => connections_game,
+        Err(db_err) => return db_err.into(),
+    };
+    if connections_game.author_id == user_id {
+        return ReturnData::bad_request("You can't rate your own connections game".to_string());
+    }
+    match get_session(pool, user_id.as_str(), connections_game.id.as_str()).await {
+        Ok(session) if session.is_over() => {}
+        Ok(_) | Err(DbError::NotFound(_)) => return ReturnData::bad_request("Finish this connections game before rating it".to_string()),
+        Err(db_err) => return db_err.into(),
+    }
+    match upsert_rating(pool, user_id.as_str(), connections_game.id.as_str(), &rating_data).await {
+        Ok(rating) => ReturnData::ok(rating),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+// The user's finished session for a game along with the game, results can't be shared until the
+// game is over
+async fn get_finished_session<T>(
+    app_state: &AppState,
+    user_id: &str,
+    game_slug: &str,
+) -> Result<(ConnectionGameSession, ConnectionGame), ReturnData<T>> {
+    let pool = &app_state.db;
+    let connections_game = match get_connection_game_by_slug(pool, game_slug, user_id).await {
+        Ok(connections_game) => connections_game,
+        Err(db_err) => return Err(db_err.into()),
+    };
+    match get_session(pool, user_id, connections_game.id.as_str()).await {
+        Ok(session) if session.is_over() => Ok((session, connections_game)),
+        Ok(_) | Err(DbError::NotFound(_)) => Err(ReturnData::bad_request("Finish this connections game before sharing it".to_string())),
+        Err(db_err) => Err(db_err.into()),
+    }
+}
+
+async fn get_shareable_result(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(game_slug): Path<String>,
+) -> ReturnData<ShareableResult> {
+    let user = match get_user_from_auth_header(&app_state.db, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    match get_finished_session(&app_state, user.get_id().as_str(), game_slug.as_str()).await {
+        Ok((session, connections_game)) => ReturnData::ok(ShareableResult {
+            text: result_grid(&session, &connections_game),
+        }),
+        Err(e) => e,
+    }
+}
+
+// Posts the result grid as a chat message from the user, held to the same rules as any other message
+async fn share_result_to_chat(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(game_slug): Path<String>,
+    Json(share_data): Json<ShareResultSchema>,
+) -> ReturnData<ChatMessage> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let user_id = user.get_id();
+    let (session, connections_game) = match get_finished_session(&app_state, user_id.as_str(), game_slug.as_str()).await {
+        Ok(finished) => finished,
+        Err(e) => return e,
+    };
+
+    let channel = match get_chat_channel_by_id(pool, share_data.channel_id.as_str()).await {
+        Ok(channel) => channel,
+        Err(db_err) => return db_err.into(),
+    };
+    let perms = get_channel_permissions(pool, &channel, user_id.as_str()).await.unwrap_or(0);
+    if perms & permissions::READ == 0 {
+        return ReturnData::bad_request("You are not in this chat channel".to_string());
+    }
+    if perms & permissions::SEND == 0 {
+        return ReturnData::forbidden("You do not have permission to send messages in this channel".to_string());
+    }
+    let rate_limiters = &app_state.rate_limiters;
+    if rate_limiters.user_messages.check(user_id.as_str()).is_err() {
+        return ReturnData::too_many_requests("You are sending messages too quickly".to_string());
+    }
+    if rate_limiters.channel_messages.check(channel.id.as_str()).is_err() {
+        return ReturnData::too_many_requests("This channel is receiving too many messages".to_string());
+    }
+
+    let message = CreateMessageSchema {
+        channel_id: channel.id,
+        contents: result_grid(&session, &connections_game),
+        reply_to: None,
+    };
+    let chat_message = match insert_chat_message(pool, message, user_id.as_str()).await {
+        Ok(chat_message) => chat_message,
+        Err(db_err) => return db_err.into(),
+    };
+    let _ = mark_channel_read(pool, user_id.as_str(), chat_message.channel_id.as_str(), chat_message.atomic_id).await;
+    app_state.event_bus.publish(ChatEvent::MessageCreated(chat_message.clone()));
+    ReturnData::created(chat_message)
+}
+
+async fn get_my_stats(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<PlayerStats> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    match get_player_stats(pool, user.get_id().as_str()).await {
+        Ok(stats) => ReturnData::ok(stats),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn get_my_author_stats(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<AuthorStats> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
         Ok(user) => user,
         Err(e) => return e.into(),
     };
-    match get_connection_game_by_slug(pool, game_slug.as_str()).await {
-        Ok(connections_game) => {
-            let (row_name, correct_guess) = check_if_solution_is_valid(&connections_game, &row_guess);
-            ReturnData::ok(TrySolveRow { row_name, correct_guess })
+    match get_author_stats(pool, user.get_id().as_str()).await {
+        Ok(stats) => ReturnData::ok(stats),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+fn today(app_state: &AppState) -> String {
+    unix_time_to_date(current_unix_time(), app_state.config.daily_puzzle_utc_offset)
+}
+
+async fn get_daily_puzzle(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<ReturnDailyPuzzle> {
+    let pool = &app_state.db;
+    if let Err(e) = get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        return e.into();
+    }
+    let daily_puzzle = match get_daily_puzzle_for_date(pool, today(&app_state).as_str()).await {
+        Ok(daily_puzzle) => daily_puzzle,
+        Err(db_err) => return db_err.into(),
+    };
+    match get_connection_game_by_id(pool, daily_puzzle.game_id.as_str()).await {
+        Ok(connections_game) => ReturnData::ok(ReturnDailyPuzzle {
+            date: daily_puzzle.date,
+            game: connections_game.into(),
+        }),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+// Only admins curate the daily puzzle
+async fn schedule_daily_puzzle(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(schedule_data): Json<ScheduleDailyPuzzleSchema>,
+) -> ReturnData<DailyPuzzle> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    if user.auth_level != AuthLevel::Admin {
+        return ReturnData::forbidden("Only an admin can schedule the daily puzzle".to_string());
+    }
+    if !is_valid_date(schedule_data.date.as_str()) {
+        return ReturnData::bad_request("date must be a real date written as YYYY-MM-DD".to_string());
+    }
+    if schedule_data.date < today(&app_state) {
+        return ReturnData::bad_request("Can't schedule a daily puzzle in the past".to_string());
+    }
+    match get_connection_game_by_id(pool, schedule_data.game_id.as_str()).await {
+        Ok(connections_game) if connections_game.draft => {
+            return ReturnData::bad_request("Only a published connections game can be the daily puzzle".to_string())
         }
+        Ok(_) => {}
+        Err(db_err) => return db_err.into(),
+    }
+    match insert_daily_puzzle(pool, &schedule_data, user.get_id().as_str()).await {
+        Ok(daily_puzzle) => ReturnData::created(daily_puzzle),
+        Err(DbError::AlreadyExists) => ReturnData::bad_request(format!("A daily puzzle is already scheduled for {}", schedule_data.date)),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn list_daily_puzzle_queue(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<Vec<DailyPuzzle>> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    // The queue gives away upcoming puzzles
+    if user.auth_level != AuthLevel::Admin {
+        return ReturnData::forbidden("Only an admin can view the daily puzzle queue".to_string());
+    }
+    match get_daily_puzzle_queue(pool, today(&app_state).as_str()).await {
+        Ok(queue) => ReturnData::ok(queue),
         Err(db_err) => db_err.into(),
     }
 }
 
-fn check_if_solution_is_valid(game: &ConnectionGame, guess: &[String; 4]) -> (Option<String>, bool) {
-    'outer: for category in &game.connection_categories {
-        for word in guess {
-            if !category.category_clues.contains(word) {
-                continue 'outer;
-            }
+async fn unschedule_daily_puzzle(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(date): Path<String>) -> ReturnData<()> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    if user.auth_level != AuthLevel::Admin {
+        return ReturnData::forbidden("Only an admin can unschedule the daily puzzle".to_string());
+    }
+    match delete_daily_puzzle_for_date(pool, date.as_str()).await {
+        Ok(_) => ReturnData::ok(()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+// A guess has to be four different clues which are still on the board. A guess which isn't is
+// rejected without costing a mistake
+fn check_guess_is_playable(game: &ConnectionGame, solved_categories: &[String], guess: &[String; 4]) -> Result<(), String> {
+    for (index, word) in guess.iter().enumerate() {
+        if guess[..index].contains(word) {
+            return Err(format!("'{word}' was guessed more than once"));
+        }
+        let on_board = game
+            .connection_categories
+            .iter()
+            .filter(|category| !solved_categories.contains(&category.category_name))
+            .any(|category| category.category_clues.contains(word));
+        if !on_board {
+            return Err(format!("'{word}' is not an unsolved clue in this puzzle"));
+        }
+    }
+    Ok(())
+}
+
+// The unsolved category a guess matches, if any, and whether the guess was one clue away from
+// matching one
+fn check_if_solution_is_valid(game: &ConnectionGame, solved_categories: &[String], guess: &[String; 4]) -> (Option<String>, bool) {
+    let mut one_away = false;
+    for category in &game.connection_categories {
+        if solved_categories.contains(&category.category_name) {
+            continue;
+        }
+        let matching = guess.iter().filter(|word| category.category_clues.contains(word)).count();
+        match matching {
+            4 => return (Some(category.category_name.clone()), false),
+            3 => one_away = true,
+            _ => {}
         }
-        return (Some(category.to_owned().category_name), true);
     }
-    (None, false)
+    (None, one_away)
 }
@@ -1,11 +1,15 @@
 use axum::http::header::HeaderMap;
 
+pub mod channel_moderation_controller;
 pub mod chat_controller;
+pub mod chat_socket_handlers;
 pub mod games_controller;
 pub mod log_controller;
 pub mod reminder_controller;
 pub mod return_data;
+pub mod server_controller;
 pub mod user_controller;
+pub mod word_games_controller;
 
 use crate::{
     db::PatDatabase,
@@ -1,9 +1,23 @@
 use axum::response::Response;
 use axum::{http::StatusCode, response::IntoResponse, Json};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_json::value::Value;
 
+// One problem with one field of a request body. `field` is a path into the body, like
+// "connection_categories[1].category_clues[2]"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub msg: String,
+}
+
+impl FieldError {
+    pub fn new(field: String, msg: &str) -> Self {
+        Self { field, msg: msg.to_owned() }
+    }
+}
+
 pub struct ReturnData<T> {
     status_code: StatusCode,
     data: Result<T, Value>,
@@ -37,6 +51,12 @@ impl<T> ReturnData<T> {
             data: Err(json!({"msg": error})),
         }
     }
+    pub fn validation_failed(errors: Vec<FieldError>) -> Self {
+        Self {
+            status_code: StatusCode::BAD_REQUEST,
+            data: Err(json!({"msg": "The request failed validation", "errors": errors})),
+        }
+    }
     pub fn forbidden(error: String) -> Self {
         Self {
             status_code: StatusCode::FORBIDDEN,
@@ -49,6 +69,12 @@ impl<T> ReturnData<T> {
             data: Err(json!({"msg": error})),
         }
     }
+    pub fn too_many_requests(error: String) -> Self {
+        Self {
+            status_code: StatusCode::TOO_MANY_REQUESTS,
+            data: Err(json!({"msg": error})),
+        }
+    }
 
     // 500
     pub fn internal_error(error: String) -> Self {
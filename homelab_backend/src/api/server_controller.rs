@@ -0,0 +1,455 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::header::HeaderMap,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+
+use crate::{
+    api::{get_user_from_auth_header, return_data::ReturnData},
+    app::AppState,
+    db::MongoModel,
+    error_handler::DbError,
+    models::chat::{
+        chat_channel::{ChatChannel, ReturnChannel},
+        chat_channel_db::{
+            add_subscriber_to_server_channels, delete_chat_channel_and_contents, get_chat_channel_by_id, hydrate_chat_channel_subscribers,
+            insert_server_channel, list_server_channels, remove_subscriber_from_server_channels, set_channel_permission_override,
+        },
+        invite::ChatInvite,
+        invite_db::{get_chat_invite_by_code, get_chat_invites_for_server, insert_chat_invite, use_chat_invite, InviteTarget},
+        server::{permissions, ChatServer, PermissionOverride, ReturnServer},
+        server_db::{
+            add_chat_server_member, filter_readable_channels, get_chat_server_by_id, get_chat_servers_for_user, insert_chat_server,
+            insert_chat_server_category, insert_chat_server_role, remove_chat_server_member, set_chat_server_member_roles,
+        },
+        validation::{
+            CreateInviteSchema, CreateServerCategorySchema, CreateServerChannelSchema, CreateServerRoleSchema, CreateServerSchema,
+            SetMemberRolesSchema, MAX_INVITE_LIFETIME,
+        },
+    },
+};
+
+pub fn server_routes() -> Router<Arc<AppState>> {
+    Router::<Arc<AppState>>::new()
+        .route("/chat/servers", post(create_server))
+        .route("/chat/servers", get(list_servers))
+        .route("/chat/servers/join/:code", put(join_server))
+        .route("/chat/servers/:server_id", get(get_server))
+        .route("/chat/servers/:server_id/channels", post(create_server_channel))
+        .route("/chat/servers/:server_id/channels/:channel_id", delete(delete_server_channel))
+        .route("/chat/servers/:server_id/channels/:channel_id/overrides", put(set_permission_override))
+        .route("/chat/servers/:server_id/categories", post(create_category))
+        .route("/chat/servers/:server_id/roles", post(create_role))
+        .route("/chat/servers/:server_id/members/:user_id/roles", put(set_member_roles))
+        .route("/chat/servers/:server_id/members/:user_id", delete(remove_member))
+        .route("/chat/servers/:server_id/invites", post(create_server_invite))
+        .route("/chat/servers/:server_id/invites", get(list_server_invites))
+}
+
+// Every server gets a channel to talk in when it is created
+const DEFAULT_CHANNEL_NAME: &str = "general";
+
+async fn get_member_server<T>(app_state: &AppState, server_id: &str, user_id: &str) -> Result<ChatServer, ReturnData<T>> {
+    let server = match get_chat_server_by_id(&app_state.db, server_id).await {
+        Ok(server) => server,
+        Err(db_err) => return Err(db_err.into()),
+    };
+    if !server.is_member(user_id) {
+        return Err(ReturnData::forbidden("You are not a member of this server".to_string()));
+    }
+    Ok(server)
+}
+
+async fn get_managed_server<T>(app_state: &AppState, server_id: &str, user_id: &str) -> Result<ChatServer, ReturnData<T>> {
+    let server = get_member_server(app_state, server_id, user_id).await?;
+    if server.server_permissions(user_id) & permissions::MANAGE == 0 {
+        return Err(ReturnData::forbidden("You do not have permission to manage this server".to_string()));
+    }
+    Ok(server)
+}
+
+async fn build_return_server(app_state: &AppState, server: ChatServer, user_id: &str) -> Result<ReturnServer, DbError> {
+    let pool = &app_state.db;
+    let channels = list_server_channels(pool, server.id.as_str()).await?;
+    let channels = filter_readable_channels(pool, user_id, channels).await?;
+    let mut return_channels = Vec::new();
+    for channel in channels {
+        return_channels.push(hydrate_chat_channel_subscribers(pool, channel).await);
+    }
+    Ok(ReturnServer {
+        server,
+        channels: return_channels,
+    })
+}
+
+async fn create_server(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(server_data): Json<CreateServerSchema>,
+) -> ReturnData<ReturnServer> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let user_id = user.get_id();
+
+    if server_data.name.trim().is_empty() {
+        return ReturnData::bad_request("A server must have a name".to_string());
+    }
+    let server = match insert_chat_server(pool, &server_data, user_id.as_str()).await {
+        Ok(server) => server,
+        Err(db_err) => return db_err.into(),
+    };
+    let default_channel = CreateServerChannelSchema {
+        name: DEFAULT_CHANNEL_NAME.to_string(),
+        slug: DEFAULT_CHANNEL_NAME.to_string(),
+        category_id: None,
+    };
+    if let Err(db_err) = insert_server_channel(pool, &default_channel, &server).await {
+        return db_err.into();
+    }
+
+    match build_return_server(&app_state, server, user_id.as_str()).await {
+        Ok(return_server) => ReturnData::created(return_server),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn list_servers(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<Vec<ChatServer>> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    match get_chat_servers_for_user(pool, user.get_id().as_str()).await {
+        Ok(servers) => ReturnData::ok(servers),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn get_server(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(server_id): Path<String>) -> ReturnData<ReturnServer> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let user_id = user.get_id();
+    let server = match get_member_server(&app_state, server_id.as_str(), user_id.as_str()).await {
+        Ok(server) => server,
+        Err(e) => return e,
+    };
+    match build_return_server(&app_state, server, user_id.as_str()).await {
+        Ok(return_server) => ReturnData::ok(return_server),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn create_server_channel(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(server_id): Path<String>,
+    Json(channel_data): Json<CreateServerChannelSchema>,
+) -> ReturnData<ReturnChannel> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let server = match get_managed_server(&app_state, server_id.as_str(), user.get_id().as_str()).await {
+        Ok(server) => server,
+        Err(e) => return e,
+    };
+    if let Some(category_id) = &channel_data.category_id {
+        if !server.has_category(category_id.as_str()) {
+            return ReturnData::bad_request("Category does not exist in this server".to_string());
+        }
+    }
+    match insert_server_channel(pool, &channel_data, &server).await {
+        Ok(chat_channel) => ReturnData::created(hydrate_chat_channel_subscribers(pool, chat_channel).await),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn delete_server_channel(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((server_id, channel_id)): Path<(String, String)>,
+) -> ReturnData<()> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let server = match get_managed_server(&app_state, server_id.as_str(), user.get_id().as_str()).await {
+        Ok(server) => server,
+        Err(e) => return e,
+    };
+    let channel = match get_chat_channel_by_id(pool, channel_id.as_str()).await {
+        Ok(channel) => channel,
+        Err(db_err) => return db_err.into(),
+    };
+    if channel.server_id.as_ref() != Some(&server.id) {
+        return DbError::NotFound(ChatChannel::model_name()).into();
+    }
+    match delete_chat_channel_and_contents(pool, channel.id.as_str()).await {
+        Ok(_) => {
+            app_state.channel_groups.invalidate(channel.id.as_str()).await;
+            ReturnData::ok(())
+        }
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn set_permission_override(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((server_id, channel_id)): Path<(String, String)>,
+    Json(permission_override): Json<PermissionOverride>,
+) -> ReturnData<ReturnChannel> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let server = match get_managed_server(&app_state, server_id.as_str(), user.get_id().as_str()).await {
+        Ok(server) => server,
+        Err(e) => return e,
+    };
+    let channel = match get_chat_channel_by_id(pool, channel_id.as_str()).await {
+        Ok(channel) => channel,
+        Err(db_err) => return db_err.into(),
+    };
+    if channel.server_id.as_ref() != Some(&server.id) {
+        return DbError::NotFound(ChatChannel::model_name()).into();
+    }
+    if permission_override.role_id != server.id && !server.has_role(permission_override.role_id.as_str()) {
+        return ReturnData::bad_request("Role does not exist in this server".to_string());
+    }
+    if !valid_permissions(permission_override.allow) || !valid_permissions(permission_override.deny) {
+        return ReturnData::bad_request("Invalid permissions".to_string());
+    }
+    match set_channel_permission_override(pool, &channel, permission_override).await {
+        Ok(chat_channel) => {
+            app_state.channel_groups.invalidate(chat_channel.id.as_str()).await;
+            ReturnData::ok(hydrate_chat_channel_subscribers(pool, chat_channel).await)
+        }
+        Err(db_err) => db_err.into(),
+    }
+}
+
+fn valid_permissions(perms: i64) -> bool {
+    perms & !permissions::ALL == 0
+}
+
+async fn create_category(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(server_id): Path<String>,
+    Json(category_data): Json<CreateServerCategorySchema>,
+) -> ReturnData<ChatServer> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let server = match get_managed_server(&app_state, server_id.as_str(), user.get_id().as_str()).await {
+        Ok(server) => server,
+        Err(e) => return e,
+    };
+    match insert_chat_server_category(pool, server.id.as_str(), &category_data).await {
+        Ok(server) => ReturnData::created(server),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn create_role(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(server_id): Path<String>,
+    Json(role_data): Json<CreateServerRoleSchema>,
+) -> ReturnData<ChatServer> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let server = match get_managed_server(&app_state, server_id.as_str(), user.get_id().as_str()).await {
+        Ok(server) => server,
+        Err(e) => return e,
+    };
+    if !valid_permissions(role_data.permissions) {
+        return ReturnData::bad_request("Invalid permissions".to_string());
+    }
+    match insert_chat_server_role(pool, server.id.as_str(), &role_data).await {
+        Ok(server) => ReturnData::created(server),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn set_member_roles(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((server_id, member_id)): Path<(String, String)>,
+    Json(roles_data): Json<SetMemberRolesSchema>,
+) -> ReturnData<ChatServer> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let server = match get_managed_server(&app_state, server_id.as_str(), user.get_id().as_str()).await {
+        Ok(server) => server,
+        Err(e) => return e,
+    };
+    if !server.is_member(member_id.as_str()) {
+        return ReturnData::bad_request("That user is not a member of this server".to_string());
+    }
+    if !roles_data.role_ids.iter().all(|role_id| server.has_role(role_id.as_str())) {
+        return ReturnData::bad_request("Role does not exist in this server".to_string());
+    }
+    match set_chat_server_member_roles(pool, server.id.as_str(), member_id.as_str(), &roles_data.role_ids).await {
+        Ok(server) => {
+            app_state.channel_groups.invalidate_server(server.id.as_str()).await;
+            ReturnData::ok(server)
+        }
+        Err(db_err) => db_err.into(),
+    }
+}
+
+// Managers can remove other members, and any member can remove themselves to leave the server
+async fn remove_member(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((server_id, member_id)): Path<(String, String)>,
+) -> ReturnData<()> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let user_id = user.get_id();
+    let server = match member_id == user_id {
+        true => get_member_server(&app_state, server_id.as_str(), user_id.as_str()).await,
+        false => get_managed_server(&app_state, server_id.as_str(), user_id.as_str()).await,
+    };
+    let server = match server {
+        Ok(server) => server,
+        Err(e) => return e,
+    };
+    if member_id == server.owner_id {
+        return ReturnData::bad_request("The owner of a server cannot be removed from it".to_string());
+    }
+
+    if let Err(db_err) = remove_chat_server_member(pool, server.id.as_str(), member_id.as_str()).await {
+        return db_err.into();
+    }
+    match remove_subscriber_from_server_channels(pool, server.id.as_str(), member_id.as_str()).await {
+        Ok(_) => {
+            app_state.channel_groups.invalidate_server(server.id.as_str()).await;
+            ReturnData::ok(())
+        }
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn create_server_invite(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(server_id): Path<String>,
+    Json(invite_data): Json<CreateInviteSchema>,
+) -> ReturnData<ChatInvite> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let server = match get_managed_server(&app_state, server_id.as_str(), user.get_id().as_str()).await {
+        Ok(server) => server,
+        Err(e) => return e,
+    };
+
+    if invite_data.expires_in.is_some_and(|expires_in| expires_in <= 0) {
+        return ReturnData::bad_request("An invite must expire in the future".to_string());
+    }
+    if invite_data.expires_in.is_some_and(|expires_in| expires_in > MAX_INVITE_LIFETIME) {
+        return ReturnData::bad_request("An invite can't expire more than a year from now".to_string());
+    }
+    if invite_data.max_uses.is_some_and(|max_uses| max_uses <= 0) {
+        return ReturnData::bad_request("An invite must have at least one use".to_string());
+    }
+
+    match insert_chat_invite(pool, &invite_data, InviteTarget::Server(server.id.as_str()), user.get_id().as_str()).await {
+        Ok(invite) => ReturnData::created(invite),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn list_server_invites(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(server_id): Path<String>,
+) -> ReturnData<Vec<ChatInvite>> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let server = match get_managed_server(&app_state, server_id.as_str(), user.get_id().as_str()).await {
+        Ok(server) => server,
+        Err(e) => return e,
+    };
+    match get_chat_invites_for_server(pool, server.id.as_str()).await {
+        Ok(invites) => ReturnData::ok(invites),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn join_server(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(code): Path<String>) -> ReturnData<ReturnServer> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let user_id = user.get_id();
+
+    let invite = match get_chat_invite_by_code(pool, code.as_str()).await {
+        Ok(invite) => invite,
+        Err(db_err) => return db_err.into(),
+    };
+    let server_id = match invite.server_id {
+        Some(server_id) => server_id,
+        None => return ReturnData::bad_request("This invite is for a channel, join it through /chat/invites".to_string()),
+    };
+    let server = match get_chat_server_by_id(pool, server_id.as_str()).await {
+        Ok(server) => server,
+        Err(db_err) => return db_err.into(),
+    };
+
+    // Check this before using the invite so a failed join doesn't use it up
+    if server.is_member(user_id.as_str()) {
+        return ReturnData::bad_request("You are already in this server".to_string());
+    }
+
+    match use_chat_invite(pool, code.as_str()).await {
+        Ok(_) => (),
+        Err(DbError::NotFound(_)) => return ReturnData::bad_request("This invite has expired or has no uses left".to_string()),
+        Err(db_err) => return db_err.into(),
+    }
+
+    let server = match add_chat_server_member(pool, server.id.as_str(), user_id.as_str()).await {
+        Ok(server) => server,
+        Err(db_err) => return db_err.into(),
+    };
+    if let Err(db_err) = add_subscriber_to_server_channels(pool, server.id.as_str(), user_id.as_str()).await {
+        return db_err.into();
+    }
+    app_state.channel_groups.invalidate_server(server.id.as_str()).await;
+    match build_return_server(&app_state, server, user_id.as_str()).await {
+        Ok(return_server) => ReturnData::ok(return_server),
+        Err(db_err) => db_err.into(),
+    }
+}
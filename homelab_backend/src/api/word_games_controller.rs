@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use super::get_user_from_auth_header;
+use super::return_data::{FieldError, ReturnData};
+use crate::app::AppState;
+use axum::{
+    extract::{Path, State},
+    http::header::HeaderMap,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+
+use crate::{error_handler::DbError, util::current_unix_time};
+
+use crate::models::games::words::{
+    dictionary::{normalize_word, random_answer},
+    evaluate_guess,
+    session::ReturnWordGameSession,
+    session_db::{save_word_session_guess, start_or_resume_word_session},
+    stats::WordPlayerStats,
+    stats_db::get_word_player_stats,
+    validation::{check_word, CreateWordGameSchema, WordGuessSchema},
+    words_db::{delete_word_game, get_all_word_games, get_word_game_by_id, insert_word_game},
+    MinimalWordGame, WordGuessResult,
+};
+
+pub fn word_games_routes() -> Router<Arc<AppState>> {
+    Router::<Arc<AppState>>::new()
+        .route("/games/words", post(create_word_game))
+        .route("/games/words", get(list_other_word_games))
+        .route("/games/words/mine", get(list_my_word_games))
+        .route("/games/words/stats/me", get(get_my_word_stats))
+        .route("/games/words/:game_id", delete(delete_word))
+        .route("/games/words/:game_id/session", put(start_or_resume_word_game))
+        .route("/games/words/:game_id/guess", put(guess_word))
+}
+
+// The answer is never sent back, so a game drawn from the word list can be played by its author
+async fn create_word_game(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(word_game_data): Json<CreateWordGameSchema>,
+) -> ReturnData<MinimalWordGame> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    if let Err(errors) = word_game_data.validate() {
+        return ReturnData::validation_failed(errors);
+    }
+    let (answer, from_dictionary) = match &word_game_data.answer {
+        Some(answer) => (normalize_word(answer), false),
+        None => (random_answer().to_owned(), true),
+    };
+    match insert_word_game(pool, answer.as_str(), user.get_id().as_str(), from_dictionary).await {
+        Ok(word_game) => ReturnData::created(word_game.into()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn list_my_word_games(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<Vec<MinimalWordGame>> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    match get_all_word_games(pool, user.get_id().as_str(), true).await {
+        Ok(word_games) => ReturnData::ok(word_games.into_iter().map(|game| game.into()).collect()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn list_other_word_games(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<Vec<MinimalWordGame>> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    match get_all_word_games(pool, user.get_id().as_str(), false).await {
+        Ok(word_games) => ReturnData::ok(word_games.into_iter().map(|game| game.into()).collect()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn delete_word(State(app_state): State<Arc<AppState>>, headers: HeaderMap, Path(game_id): Path<String>) -> ReturnData<()> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let word_game = match get_word_game_by_id(pool, game_id.as_str()).await {
+        Ok(word_game) => word_game,
+        Err(db_err) => return db_err.into(),
+    };
+    if word_game.author_id != user.get_id() {
+        return ReturnData::forbidden("Only the author of a word game can delete it".to_string());
+    }
+    match delete_word_game(pool, &word_game).await {
+        Ok(_) => ReturnData::ok(()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn start_or_resume_word_game(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(game_id): Path<String>,
+) -> ReturnData<ReturnWordGameSession> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let word_game = match get_word_game_by_id(pool, game_id.as_str()).await {
+        Ok(word_game) => word_game,
+        Err(db_err) => return db_err.into(),
+    };
+    if !word_game.is_playable_by(user.get_id().as_str()) {
+        return ReturnData::bad_request("You can't play a word game you picked the answer for".to_string());
+    }
+    match start_or_resume_word_session(pool, user.get_id().as_str(), word_game.id.as_str()).await {
+        Ok(session) => ReturnData::ok(ReturnWordGameSession::new(session, word_game)),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn guess_word(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(game_id): Path<String>,
+    Json(guess_data): Json<WordGuessSchema>,
+) -> ReturnData<WordGuessResult> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    let guess = normalize_word(guess_data.guess.as_str());
+    if let Err(msg) = check_word(guess.as_str()) {
+        return ReturnData::validation_failed(vec![FieldError::new("guess".to_owned(), msg)]);
+    }
+    let word_game = match get_word_game_by_id(pool, game_id.as_str()).await {
+        Ok(word_game) => word_game,
+        Err(db_err) => return db_err.into(),
+    };
+    if !word_game.is_playable_by(user.get_id().as_str()) {
+        return ReturnData::bad_request("You can't play a word game you picked the answer for".to_string());
+    }
+    // Guessing without starting a session first starts one
+    let mut session = match start_or_resume_word_session(pool, user.get_id().as_str(), word_game.id.as_str()).await {
+        Ok(session) => session,
+        Err(db_err) => return db_err.into(),
+    };
+    if session.is_over() {
+        return ReturnData::bad_request("This game is already over".to_string());
+    }
+
+    let letters = evaluate_guess(word_game.answer.as_str(), guess.as_str());
+    session.apply_guess(guess, letters.clone(), current_unix_time());
+    match save_word_session_guess(pool, &session).await {
+        Ok(session) => ReturnData::ok(WordGuessResult {
+            letters,
+            guesses_remaining: session.guesses_remaining(),
+            state: session.state,
+            answer: session.is_over().then_some(word_game.answer),
+        }),
+        Err(DbError::NotFound(_)) => ReturnData::bad_request("Another guess was made at the same time, try again".to_string()),
+        Err(db_err) => db_err.into(),
+    }
+}
+
+async fn get_my_word_stats(State(app_state): State<Arc<AppState>>, headers: HeaderMap) -> ReturnData<WordPlayerStats> {
+    let pool = &app_state.db;
+    let user = match get_user_from_auth_header(pool, &headers, &app_state.config.app_secret).await {
+        Ok(user) => user,
+        Err(e) => return e.into(),
+    };
+    match get_word_player_stats(pool, user.get_id().as_str()).await {
+        Ok(stats) => ReturnData::ok(stats),
+        Err(db_err) => db_err.into(),
+    }
+}
@@ -1,5 +1,4 @@
 use std::{
-    collections::HashMap,
     sync::{mpsc, Arc, Mutex},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
@@ -9,12 +8,9 @@ use axum::http::header::{
     SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_EXTENSIONS, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_PROTOCOL, SEC_WEBSOCKET_VERSION, USER_AGENT,
 };
 use axum::http::Method;
-use axum::{http::Request, routing::get, Router};
+use axum::{http::Request, middleware as axum_middleware, routing::get, Router};
 use hyper::header::UPGRADE;
-use tokio::{
-    sync::{mpsc as tokio_mpsc, watch, RwLock},
-    task, time,
-};
+use tokio::{sync::watch, task, time};
 use tower::ServiceBuilder;
 use tower_http::{
     cors::{Any, CorsLayer},
@@ -29,11 +25,22 @@ use mongodb::Database;
 use tower_http::trace::DefaultMakeSpan;
 
 use crate::{
-    api::{chat_controller, games_controller, log_controller, reminder_controller, user_controller},
+    api::{
+        channel_moderation_controller, chat_controller, chat_socket_handlers, games_controller, log_controller, reminder_controller,
+        server_controller, user_controller, word_games_controller,
+    },
     db::PatDatabase,
     logger,
-    models::{chat::packet::WebSocketResponse, user::jwt::get_and_decode_auth_token},
-    tasks::{log_creation_task, task_manager::TaskManager},
+    models::user::jwt::get_and_decode_auth_token,
+    rate_limit::{limit_http_requests, RateLimiters, RateLimits},
+    realtime::{
+        channel_groups::ChannelGroups,
+        connections::ConnectionRegistry,
+        dispatcher::SocketDispatcher,
+        event_bus::{create_event_bus, deliver_chat_events, ChatEventBus, EventBusBackend},
+    },
+    tasks::{daily_puzzle_task, log_creation_task, task_manager::TaskManager},
+    util::parse_utc_offset,
 };
 
 const LOGGABLE_METHODS: [Method; 4] = [Method::GET, Method::PUT, Method::POST, Method::DELETE];
@@ -41,11 +48,11 @@ const LOGGABLE_METHODS: [Method; 4] = [Method::GET, Method::PUT, Method::POST, M
 pub struct AppState {
     pub db: PatDatabase,
     pub config: Config,
-    // RwLock is potentially bad to use here in the scenario where there are many connects and
-    // disconnects are being made, maybe 400k users for a 4GHz processor. App state will lock every
-    // single time there is a connect/disconnect and prevent processing messages being sent while
-    // active_connections is being updated
-    pub active_connections: RwLock<HashMap<String, tokio_mpsc::UnboundedSender<WebSocketResponse>>>,
+    pub active_connections: ConnectionRegistry,
+    pub channel_groups: ChannelGroups,
+    pub event_bus: Box<dyn ChatEventBus>,
+    pub socket_dispatcher: SocketDispatcher,
+    pub rate_limiters: RateLimiters,
     pub task_manager: Arc<Mutex<TaskManager>>,
 }
 
@@ -55,6 +62,12 @@ pub struct Config {
     pub jwt_secret: String,
     pub jwt_max_age: i32,
     pub app_secret: String,
+    pub chat_event_bus: EventBusBackend,
+    pub rate_limits: RateLimits,
+    // Minutes ahead of UTC, decides when the daily puzzle changes
+    pub daily_puzzle_utc_offset: i64,
+    // Where the daily puzzle is announced, nothing is announced if this isn't set
+    pub daily_puzzle_channel_id: Option<String>,
 }
 
 impl Config {
@@ -63,19 +76,26 @@ impl Config {
         let jwt_secret = dotenv!("JWT_SECRET").to_owned();
         let jwt_max_age = dotenv!("JWT_MAX_AGE").to_owned();
         let app_secret = dotenv!("APP_SECRET").to_owned();
+        // Optional, only needed when more than one instance of the backend is running
+        let chat_event_bus = dotenv::var("CHAT_EVENT_BUS").unwrap_or_default();
+        let daily_puzzle_utc_offset = match dotenv::var("DAILY_PUZZLE_UTC_OFFSET") {
+            Ok(offset) => parse_utc_offset(offset.as_str()).expect("DAILY_PUZZLE_UTC_OFFSET must look like +02:00 or -05:30"),
+            Err(_) => 0,
+        };
         Self {
             connection_string,
             jwt_secret,
             jwt_max_age: jwt_max_age.parse::<i32>().expect("JWT_MAX_AGE was not an i32"),
             app_secret,
+            chat_event_bus: EventBusBackend::from_config(chat_event_bus.as_str()),
+            rate_limits: RateLimits::init(),
+            daily_puzzle_utc_offset,
+            daily_puzzle_channel_id: dotenv::var("DAILY_PUZZLE_CHANNEL_ID").ok(),
         }
     }
 }
 
-pub async fn generate_app(database: Database) -> (Router, Arc<Mutex<TaskManager>>) {
-    // Set up app config and the database pool
-    let config = Config::init();
-
+pub async fn generate_app(database: Database, config: Config) -> (Router, Arc<Mutex<TaskManager>>) {
     // Copy the app secret and db handle as they are passed to tasks and on_request events
     let app_secret = config.app_secret.clone();
     let handle = PatDatabase::new(database);
@@ -86,7 +106,10 @@ pub async fn generate_app(database: Database) -> (Router, Arc<Mutex<TaskManager>
         .merge(log_controller::log_routes())
         .merge(reminder_controller::reminder_routes())
         .merge(games_controller::games_routes())
-        .merge(chat_controller::chat_routes());
+        .merge(word_games_controller::word_games_routes())
+        .merge(chat_controller::chat_routes())
+        .merge(channel_moderation_controller::channel_moderation_routes())
+        .merge(server_controller::server_routes());
 
     // Create a channel to pass log information to the db write task
     let (log_tx, log_rx) = mpsc::channel();
@@ -192,19 +215,66 @@ pub async fn generate_app(database: Database) -> (Router, Arc<Mutex<TaskManager>
         }
     });
 
-    let task_manager = Arc::new(Mutex::new(TaskManager::new(handle.clone(), log_sender, log_response_receiver)));
+    let (daily_puzzle_sender, mut daily_puzzle_receiver) = watch::channel("daily puzzle trigger channel");
+    let (daily_puzzle_response_sender, daily_puzzle_response_receiver) = watch::channel("daily puzzle response channel");
+    let task_manager = Arc::new(Mutex::new(TaskManager::new(
+        handle.clone(),
+        log_sender,
+        log_response_receiver,
+        daily_puzzle_sender,
+        daily_puzzle_response_receiver,
+    )));
 
     // Create app state and the router
+    let event_bus = create_event_bus(config.chat_event_bus, &handle);
+    let chat_events = event_bus.subscribe();
+    // Every module which handles requests sent over the chat socket registers its handlers here
+    let mut socket_dispatcher = SocketDispatcher::new();
+    chat_socket_handlers::register_chat_handlers(&mut socket_dispatcher);
+    let rate_limiters = RateLimiters::new(&config.rate_limits);
     let state = Arc::new(AppState {
         db: handle,
         config,
-        active_connections: RwLock::new(HashMap::new()),
+        active_connections: ConnectionRegistry::new(),
+        channel_groups: ChannelGroups::new(),
+        event_bus,
+        socket_dispatcher,
+        rate_limiters,
         task_manager: task_manager.clone(),
     });
+
+    // Deliver chat events to the connections on this instance
+    #[allow(clippy::let_underscore_future)]
+    let _deliver_chat_events = task::spawn(deliver_chat_events(state.clone(), chat_events));
+
+    // Announce the daily puzzle once the day changes. Like the log task this can be run manually,
+    // and responds when it was
+    let daily_puzzle_state = state.clone();
+    #[allow(clippy::let_underscore_future)]
+    let _announce_daily_puzzle = task::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(60));
+        loop {
+            let mut should_respond = false;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = daily_puzzle_receiver.changed() => {
+                    interval.reset();
+                    should_respond = true;
+                }
+            }
+            daily_puzzle_task::announce_daily_puzzle(&daily_puzzle_state).await;
+            if should_respond {
+                let _ = daily_puzzle_response_sender.send("Jobs done");
+            }
+        }
+    });
     (
         Router::<Arc<AppState>>::new()
             .route("/", get(root))
-            .nest("/api", api_routes)
+            .nest(
+                "/api",
+                api_routes.layer(axum_middleware::from_fn_with_state(state.clone(), limit_http_requests)),
+            )
             .with_state(state)
             .layer(middleware)
             .layer(trace_layer),
@@ -1,10 +1,18 @@
+use futures::TryStreamExt;
 use mongodb::{bson::doc, options::IndexOptions, Client, Collection, Database, IndexModel};
+use std::collections::HashSet;
 
 use crate::{
-    db::PatDatabase,
+    db::{str_to_object_id, PatDatabase},
     models::{
-        chat::{chat_channel::ChatChannel, message::ChatMessage},
-        games::ConnectionGame,
+        chat::{
+            chat_channel::ChatChannel, invite::ChatInvite, message::ChatMessage, read_receipt::ChannelReadState, server::ChatServer,
+            socket_ticket::SocketTicket,
+        },
+        games::{
+            daily::DailyPuzzle, games_db::first_free_slug, rating::ConnectionGameRating, session::ConnectionGameSession,
+            words::session::WordGameSession, ConnectionGame,
+        },
         reminder::Category,
         user::{user_db::db_create_user, AuthLevel, User},
     },
@@ -46,10 +54,18 @@ pub async fn check_indexes(db_handle: &PatDatabase) {
     // Game
     create_category_indexes(db_handle).await;
     create_connections_game_indexes(db_handle).await;
+    create_game_session_indexes(db_handle).await;
+    create_daily_puzzle_indexes(db_handle).await;
+    create_game_rating_indexes(db_handle).await;
+    create_word_game_session_indexes(db_handle).await;
 
     // Chat
     create_chat_channels_indexes(db_handle).await;
     create_chat_message_indexes(db_handle).await;
+    create_chat_read_state_indexes(db_handle).await;
+    create_chat_invite_indexes(db_handle).await;
+    create_chat_server_indexes(db_handle).await;
+    create_chat_socket_ticket_indexes(db_handle).await;
 }
 
 pub async fn create_user_indexes(db_handle: &PatDatabase) {
@@ -79,16 +95,46 @@ pub async fn create_category_indexes(db_handle: &PatDatabase) {
 pub async fn create_connections_game_indexes(db_handle: &PatDatabase) {
     let game_connections_collection: Collection<ConnectionGame> = db_handle.get_collection();
 
-    // Slug index, unique on slug and author_id
-    let category_index_options = IndexOptions::builder().unique(true).name(Some("slug_and_author".to_owned())).build();
-    let category_index = IndexModel::builder()
-        .keys(doc! {"slug": 1, "author_id": 1})
-        .options(category_index_options)
-        .build();
+    // Slugs used to only be unique per author, which made play URLs ambiguous. The old index is
+    // dropped, failing if it was already dropped, and games sharing a slug are given suffixes so
+    // the new index can be created
+    let _ = game_connections_collection.drop_index("slug_and_author").await;
+    suffix_duplicate_connections_game_slugs(&game_connections_collection).await;
+
+    // Slug index, unique on slug
+    let slug_index_options = IndexOptions::builder().unique(true).name(Some("slug".to_owned())).build();
+    let slug_index = IndexModel::builder().keys(doc! {"slug": 1}).options(slug_index_options).build();
     game_connections_collection
-        .create_index(category_index)
+        .create_index(slug_index)
+        .await
+        .expect("Failed to create a slug index on the game_connections collection");
+}
+
+// The oldest game with a slug keeps it, newer ones get the next free suffix
+async fn suffix_duplicate_connections_game_slugs(collection: &Collection<ConnectionGame>) {
+    let games: Vec<ConnectionGame> = collection
+        .find(doc! {})
+        .sort(doc! {"creation_datetime": 1})
+        .await
+        .expect("Failed to read connections games while checking for duplicate slugs")
+        .try_collect()
         .await
-        .expect("Failed to create a slug_and_author index on the game_connections collection");
+        .expect("Failed to read connections games while checking for duplicate slugs");
+    let mut taken_slugs: Vec<String> = games.iter().map(|game| game.slug.clone()).collect();
+    let mut seen_slugs: HashSet<String> = HashSet::new();
+    for game in games {
+        if seen_slugs.insert(game.slug.clone()) {
+            continue;
+        }
+        let slug = first_free_slug(game.slug.as_str(), taken_slugs.as_slice());
+        let game_id = str_to_object_id(game.id.as_str()).expect("A connections game had an invalid id");
+        collection
+            .update_one(doc! {"_id": game_id}, doc! {"$set": {"slug": slug.as_str()}})
+            .await
+            .expect("Failed to give a connections game a unique slug");
+        taken_slugs.push(slug.clone());
+        seen_slugs.insert(slug);
+    }
 }
 
 pub async fn create_chat_channels_indexes(db_handle: &PatDatabase) {
@@ -104,6 +150,19 @@ pub async fn create_chat_channels_indexes(db_handle: &PatDatabase) {
         .create_index(category_index)
         .await
         .expect("Failed to create a slug_and_owner_id index on the chat_channels collection");
+
+    // Direct message index, unique on the pair of participants. Partial so channels without a
+    // dm_key are not indexed
+    let dm_key_index_options = IndexOptions::builder()
+        .unique(true)
+        .name(Some("dm_key".to_owned()))
+        .partial_filter_expression(Some(doc! {"dm_key": {"$type": "string"}}))
+        .build();
+    let dm_key_index = IndexModel::builder().keys(doc! {"dm_key": 1}).options(dm_key_index_options).build();
+    chat_channels_collection
+        .create_index(dm_key_index)
+        .await
+        .expect("Failed to create a dm_key index on the chat_channels collection");
 }
 
 pub async fn create_chat_message_indexes(db_handle: &PatDatabase) {
@@ -123,4 +182,161 @@ pub async fn create_chat_message_indexes(db_handle: &PatDatabase) {
         .create_index(category_index)
         .await
         .expect("Failed to create a channel_and_atomic_ids index on the chat_messages collection");
+
+    // Used to page through the replies to a message
+    let thread_index_options = IndexOptions::builder().name(Some("channel_and_reply_to".to_owned())).build();
+    let thread_index = IndexModel::builder()
+        .keys(doc! {"channel_id": 1, "reply_to": 1, "atomic_id": 1})
+        .options(thread_index_options)
+        .build();
+    chat_messages_collection
+        .create_index(thread_index)
+        .await
+        .expect("Failed to create a channel_and_reply_to index on the chat_messages collection");
+
+    // Text index used for message search, a collection can only have one text index
+    let contents_index_options = IndexOptions::builder().name(Some("contents_text".to_owned())).build();
+    let contents_index = IndexModel::builder()
+        .keys(doc! {"contents": "text"})
+        .options(contents_index_options)
+        .build();
+    chat_messages_collection
+        .create_index(contents_index)
+        .await
+        .expect("Failed to create a contents_text index on the chat_messages collection");
+}
+
+pub async fn create_chat_read_state_indexes(db_handle: &PatDatabase) {
+    let chat_read_states_collection: Collection<ChannelReadState> = db_handle.get_collection();
+
+    // A user has a single read marker per channel
+    let read_state_index_options = IndexOptions::builder().unique(true).name(Some("user_and_channel_id".to_owned())).build();
+    let read_state_index = IndexModel::builder()
+        .keys(doc! {"user_id": 1, "channel_id": 1})
+        .options(read_state_index_options)
+        .build();
+    chat_read_states_collection
+        .create_index(read_state_index)
+        .await
+        .expect("Failed to create a user_and_channel_id index on the chat_read_states collection");
+}
+
+pub async fn create_chat_invite_indexes(db_handle: &PatDatabase) {
+    let chat_invites_collection: Collection<ChatInvite> = db_handle.get_collection();
+
+    // Code index, unique on code
+    let invite_index_options = IndexOptions::builder().unique(true).name(Some("code".to_owned())).build();
+    let invite_index = IndexModel::builder().keys(doc! {"code": 1}).options(invite_index_options).build();
+    chat_invites_collection
+        .create_index(invite_index)
+        .await
+        .expect("Failed to create a code index on the chat_invites collection");
+}
+
+pub async fn create_chat_server_indexes(db_handle: &PatDatabase) {
+    let chat_servers_collection: Collection<ChatServer> = db_handle.get_collection();
+
+    // Used to find the servers a user is a member of
+    let member_index_options = IndexOptions::builder().name(Some("member_user_id".to_owned())).build();
+    let member_index = IndexModel::builder()
+        .keys(doc! {"members.user_id": 1})
+        .options(member_index_options)
+        .build();
+    chat_servers_collection
+        .create_index(member_index)
+        .await
+        .expect("Failed to create a member_user_id index on the chat_servers collection");
+
+    // Used to find the channels inside of a server
+    let chat_channels_collection: Collection<ChatChannel> = db_handle.get_collection();
+    let server_channel_index_options = IndexOptions::builder().name(Some("server_id".to_owned())).build();
+    let server_channel_index = IndexModel::builder()
+        .keys(doc! {"server_id": 1})
+        .options(server_channel_index_options)
+        .build();
+    chat_channels_collection
+        .create_index(server_channel_index)
+        .await
+        .expect("Failed to create a server_id index on the chat_channels collection");
+}
+
+pub async fn create_chat_socket_ticket_indexes(db_handle: &PatDatabase) {
+    let socket_tickets_collection: Collection<SocketTicket> = db_handle.get_collection();
+
+    // ticket index, unique on ticket
+    let ticket_index_options = IndexOptions::builder().unique(true).name(Some("ticket".to_owned())).build();
+    let ticket_index = IndexModel::builder().keys(doc! {"ticket": 1}).options(ticket_index_options).build();
+    socket_tickets_collection
+        .create_index(ticket_index)
+        .await
+        .expect("Failed to create a ticket index on the chat_socket_tickets collection");
+}
+
+pub async fn create_game_session_indexes(db_handle: &PatDatabase) {
+    let game_sessions_collection: Collection<ConnectionGameSession> = db_handle.get_collection();
+
+    // A user only gets one session per game, unique on user_id and game_id
+    let session_index_options = IndexOptions::builder().unique(true).name(Some("user_and_game".to_owned())).build();
+    let session_index = IndexModel::builder()
+        .keys(doc! {"user_id": 1, "game_id": 1})
+        .options(session_index_options)
+        .build();
+    game_sessions_collection
+        .create_index(session_index)
+        .await
+        .expect("Failed to create a user_and_game index on the game_sessions collection");
+
+    // Leaderboards and author stats look up every session for a game
+    let game_index_options = IndexOptions::builder().name(Some("game_and_state".to_owned())).build();
+    let game_index = IndexModel::builder()
+        .keys(doc! {"game_id": 1, "state": 1})
+        .options(game_index_options)
+        .build();
+    game_sessions_collection
+        .create_index(game_index)
+        .await
+        .expect("Failed to create a game_and_state index on the game_sessions collection");
+}
+
+pub async fn create_daily_puzzle_indexes(db_handle: &PatDatabase) {
+    let daily_puzzles_collection: Collection<DailyPuzzle> = db_handle.get_collection();
+
+    // date index, unique on date so only one puzzle can be scheduled per day
+    let date_index_options = IndexOptions::builder().unique(true).name(Some("date".to_owned())).build();
+    let date_index = IndexModel::builder().keys(doc! {"date": 1}).options(date_index_options).build();
+    daily_puzzles_collection
+        .create_index(date_index)
+        .await
+        .expect("Failed to create a date index on the game_daily_puzzles collection");
+}
+
+pub async fn create_game_rating_indexes(db_handle: &PatDatabase) {
+    let game_ratings_collection: Collection<ConnectionGameRating> = db_handle.get_collection();
+
+    // A user only gets one rating per game, unique on game_id and user_id. Game first so a game's
+    // ratings can be looked up on their own
+    let rating_index_options = IndexOptions::builder().unique(true).name(Some("game_and_user".to_owned())).build();
+    let rating_index = IndexModel::builder()
+        .keys(doc! {"game_id": 1, "user_id": 1})
+        .options(rating_index_options)
+        .build();
+    game_ratings_collection
+        .create_index(rating_index)
+        .await
+        .expect("Failed to create a game and user index on the game_ratings collection");
+}
+
+pub async fn create_word_game_session_indexes(db_handle: &PatDatabase) {
+    let word_game_sessions_collection: Collection<WordGameSession> = db_handle.get_collection();
+
+    // A user only gets one session per word game, unique on user_id and game_id
+    let session_index_options = IndexOptions::builder().unique(true).name(Some("user_and_game".to_owned())).build();
+    let session_index = IndexModel::builder()
+        .keys(doc! {"user_id": 1, "game_id": 1})
+        .options(session_index_options)
+        .build();
+    word_game_sessions_collection
+        .create_index(session_index)
+        .await
+        .expect("Failed to create a user and game index on the word_game_sessions collection");
 }
@@ -1,7 +1,7 @@
 use crate::error_handler::DbError;
 use futures::TryStreamExt;
 use mongodb::{
-    bson::{doc, oid::ObjectId, Bson, Document},
+    bson::{doc, from_document, oid::ObjectId, Bson, Document},
     error::Error,
     options::FindOneAndUpdateOptions,
     Collection, Database,
@@ -84,6 +84,28 @@ impl PatDatabase {
         }
     }
 
+    // Runs a pipeline against T's collection, each resulting document is deserialized into an R
+    pub async fn aggregate<T, R>(&self, pipeline: Vec<Document>) -> Result<Vec<R>, DbError>
+    where
+        T: MongoModel + Send + Sync + DeserializeOwned,
+        R: DeserializeOwned,
+    {
+        let collection: Collection<T> = self.pool.collection(T::collection_name());
+        let documents: Vec<Document> = match collection.aggregate(pipeline).await {
+            Ok(cursor) => match cursor.try_collect().await {
+                Ok(documents) => documents,
+                Err(e) => return Err(e.into()),
+            },
+            Err(e) => return Err(e.into()),
+        };
+        documents
+            .into_iter()
+            .map(|document| {
+                from_document(document).map_err(|_| DbError::UnhandledException("Failed to read the result of an aggregation".to_owned()))
+            })
+            .collect()
+    }
+
     pub async fn insert_one<T>(&self, insertion_data: Document) -> Result<ObjectId, DbError>
     where
         T: MongoModel + Send + Sync + DeserializeOwned,
@@ -150,6 +172,68 @@ impl PatDatabase {
         }
     }
 
+    pub async fn upsert_one<T>(&self, filter_doc: Document, update_doc: Document) -> Result<T, DbError>
+    where
+        T: MongoModel + Send + Sync + DeserializeOwned,
+    {
+        let collection: Collection<T> = self.pool.collection(T::collection_name());
+
+        // Same as find_and_update_one, but a document is created from the filter and update
+        // if nothing matches the filter
+        let update_options = FindOneAndUpdateOptions::builder()
+            .upsert(Some(true))
+            .return_document(Some(mongodb::options::ReturnDocument::After))
+            .build();
+        match collection
+            .find_one_and_update(filter_doc, update_doc)
+            .with_options(Some(update_options))
+            .await
+        {
+            Ok(update_res) => match update_res {
+                Some(res) => Ok(res),
+                None => Err(DbError::UnhandledException("Upsert failed to produce a document".to_owned())),
+            },
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn update_many<T>(&self, filter_doc: Document, update_doc: Document) -> Result<u64, DbError>
+    where
+        T: MongoModel + Send + Sync + DeserializeOwned,
+    {
+        let collection: Collection<T> = self.pool.collection(T::collection_name());
+        match collection.update_many(filter_doc, update_doc).await {
+            Ok(update_res) => Ok(update_res.modified_count),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn delete_many<T>(&self, filter_doc: Document) -> Result<u64, DbError>
+    where
+        T: MongoModel + Send + Sync + DeserializeOwned,
+    {
+        let collection: Collection<T> = self.pool.collection(T::collection_name());
+        match collection.delete_many(filter_doc).await {
+            Ok(delete_res) => Ok(delete_res.deleted_count),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Deletes a document and returns it, so whatever was deleted can only ever be handed out once
+    pub async fn find_and_delete_one<T>(&self, filter_doc: Document) -> Result<T, DbError>
+    where
+        T: MongoModel + Send + Sync + DeserializeOwned,
+    {
+        let collection: Collection<T> = self.pool.collection(T::collection_name());
+        match collection.find_one_and_delete(filter_doc).await {
+            Ok(delete_res) => match delete_res {
+                Some(res) => Ok(res),
+                None => Err(DbError::NotFound(T::model_name())),
+            },
+            Err(e) => Err(e.into()),
+        }
+    }
+
     pub async fn delete_one<T>(&self, filter_doc: Document) -> Result<(), DbError>
     where
         T: MongoModel + Send + Sync + DeserializeOwned,
@@ -2,6 +2,7 @@ use mongodb::error::{ErrorKind, WriteFailure};
 use std::sync::Arc;
 
 use crate::api::return_data::ReturnData;
+use crate::models::chat::packet::{WebSocketError, WebSocketErrorCode, WebSocketResponse};
 
 #[derive(Debug)]
 pub enum DbError {
@@ -32,6 +33,8 @@ impl From<mongodb::error::Error> for DbError {
                 },
                 _ => DbError::UnhandledException("Unhandled error while writing data".to_owned()),
             },
+            // A findAndModify which breaks a unique index fails as a command rather than a write
+            ErrorKind::Command(command_error) if command_error.code == 11000 => DbError::AlreadyExists,
             ErrorKind::Custom(custom_message) => {
                 if let Ok(custom_error_message) = custom_message.downcast::<String>() {
                     if let Some(owned_error_message) = Arc::into_inner(custom_error_message) {
@@ -66,6 +69,40 @@ impl<T> From<DbError> for ReturnData<T> {
     }
 }
 
+impl From<DbError> for WebSocketError {
+    fn from(value: DbError) -> Self {
+        let (status_code, msg) = match value {
+            DbError::AlreadyExists => (
+                WebSocketErrorCode::BadRequest,
+                "Tried to create a resource which violated a unique constraint".to_string(),
+            ),
+            DbError::NotFound(resource_type) => (WebSocketErrorCode::NotFound, format!("{resource_type} not found")),
+            DbError::RelationshipViolation(resource_type, identifier) => (
+                WebSocketErrorCode::BadRequest,
+                format!("The request violates a relationship constraint on {resource_type} with identifier {identifier}"),
+            ),
+            DbError::EmptyDbExpression(resource_type, operation) => (
+                WebSocketErrorCode::BadRequest,
+                format!("Received no data while {operation} {resource_type}, resulting in a no-op"),
+            ),
+            DbError::BadId => (WebSocketErrorCode::NotFound, "The provided ID was not valid".to_owned()),
+            DbError::AuthFailure => (WebSocketErrorCode::Unauthorized, "Auth failure while reading database".to_owned()),
+            DbError::CustomMongoFailure(custom_message) => (WebSocketErrorCode::BadRequest, custom_message),
+            DbError::UnhandledException(error_while) => (
+                WebSocketErrorCode::InternalError,
+                format!("Unhandled exception when making a database request: {error_while}"),
+            ),
+        };
+        WebSocketError::new(status_code, msg.as_str())
+    }
+}
+
+impl From<DbError> for WebSocketResponse {
+    fn from(value: DbError) -> Self {
+        WebSocketResponse::SendError(value.into())
+    }
+}
+
 pub enum ServerError {
     FailedAuthentication(String),
     InternalFailure(String),
@@ -9,6 +9,8 @@ mod db;
 pub mod error_handler;
 mod logger;
 mod models;
+mod rate_limit;
+mod realtime;
 mod tasks;
 mod testing;
 pub mod util;
@@ -25,7 +27,7 @@ async fn main() {
     // run our app with hyper, listening on 127.0.0.1:3000
     let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await.unwrap();
     logger::log_msg(format!("listening on {}", listener.local_addr().unwrap()));
-    let (app, _) = app::generate_app(database).await;
+    let (app, _) = app::generate_app(database, app::Config::init()).await;
     axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
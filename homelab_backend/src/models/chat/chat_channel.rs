@@ -1,4 +1,5 @@
 use super::super::deserialize_id;
+use super::server::PermissionOverride;
 use crate::models::user::ReturnUser;
 use mongodb::bson::{doc, Bson};
 use serde::{Deserialize, Deserializer, Serialize};
@@ -7,7 +8,7 @@ use serde::{Deserialize, Deserializer, Serialize};
 pub enum ChannelType {
     DirectMessage,
     Group,
-    Server, // Stub type to support a more featured group chat eventually
+    Server, // A text channel inside of a ChatServer
 }
 
 fn deserialize_channel_type<'de, D>(deserializer: D) -> Result<ChannelType, D::Error>
@@ -79,6 +80,22 @@ pub struct ChatChannel {
     pub owner_id: String,
     pub created_at: i64,
     pub most_recent_message_id: i64,
+    // Sorted IDs of both participants of a direct message, unique so two users only ever share
+    // one direct message channel
+    #[serde(default)]
+    pub dm_key: Option<String>,
+    #[serde(default)]
+    pub is_private: bool,
+    #[serde(default)]
+    pub banned_users: Vec<String>, // Vec of user IDs
+    // Only set for channels inside of a server. A server channel is owned by the server, so its
+    // owner_id is the server's ID and its subscribers are the server's members
+    #[serde(default)]
+    pub server_id: Option<String>,
+    #[serde(default)]
+    pub category_id: Option<String>,
+    #[serde(default)]
+    pub permission_overrides: Vec<PermissionOverride>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -95,6 +112,19 @@ pub struct ReturnChannel {
     pub owner_id: String,
     pub created_at: i64,
     pub most_recent_message_id: i64,
+    #[serde(default)]
+    pub is_private: bool,
+    #[serde(default)]
+    pub server_id: Option<String>,
+    #[serde(default)]
+    pub category_id: Option<String>,
+    #[serde(default)]
+    pub permission_overrides: Vec<PermissionOverride>,
+    // Read state for the requester, only populated when listing channels
+    #[serde(default)]
+    pub unread_count: i64,
+    #[serde(default)]
+    pub first_unread_id: Option<i64>,
 }
 
 impl From<ChatChannel> for ReturnChannel {
@@ -109,6 +139,34 @@ impl From<ChatChannel> for ReturnChannel {
             owner_id: value.owner_id,
             created_at: value.created_at,
             most_recent_message_id: value.most_recent_message_id,
+            is_private: value.is_private,
+            server_id: value.server_id,
+            category_id: value.category_id,
+            permission_overrides: value.permission_overrides,
+            unread_count: 0,
+            first_unread_id: None,
         }
     }
 }
+
+impl ReturnChannel {
+    pub fn set_read_state(&mut self, last_read_atomic_id: i64) {
+        self.unread_count = (self.most_recent_message_id - last_read_atomic_id).max(0);
+        self.first_unread_id = match self.unread_count {
+            0 => None,
+            _ => Some(last_read_atomic_id + 1),
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ReturnDirectMessageChannel {
+    pub channel: ReturnChannel,
+    pub other_user: ReturnUser,
+}
+
+pub fn direct_message_key(user_id: &str, other_user_id: &str) -> String {
+    let mut participants = [user_id, other_user_id];
+    participants.sort();
+    participants.join("-")
+}
@@ -4,8 +4,12 @@ use crate::{
     logger::log_msg,
     models::{
         chat::{
-            chat_channel::{ChatChannel, ReturnChannel},
-            validation::CreateChannelSchema,
+            chat_channel::{direct_message_key, ChannelType, ChatChannel, ReturnChannel},
+            invite_db::delete_chat_invites_for_channel,
+            message_db::delete_chat_messages_for_channel,
+            read_receipt_db::delete_read_states_for_channel,
+            server::{ChatServer, PermissionOverride},
+            validation::{CreateChannelSchema, CreateServerChannelSchema},
         },
         user::{user_db::db_get_user_by_id, ReturnUser},
     },
@@ -41,10 +45,119 @@ pub async fn insert_chat_channel(db_handle: &PatDatabase, data: &CreateChannelSc
         "owner_id": user_id.clone(),
         "created_at": date_time,
         "most_recent_message_id": 0,
+        "is_private": data.is_private,
+        "banned_users": [],
     };
     db_handle.insert_and_retrieve_one(doc).await
 }
 
+pub async fn insert_server_channel(db_handle: &PatDatabase, data: &CreateServerChannelSchema, server: &ChatServer) -> Result<ChatChannel, DbError> {
+    let date_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let subscribers: Vec<&str> = server.members.iter().map(|member| member.user_id.as_str()).collect();
+    let category_id = match &data.category_id {
+        Some(category_id) => Bson::String(category_id.clone()),
+        None => Bson::Null,
+    };
+    let doc = doc! {
+        "slug": data.slug.as_str(),
+        "channel_type": ChannelType::Server,
+        "name": data.name.as_str(),
+        "pinned_messages": [],
+        "subscribers": subscribers,
+        "owner_id": server.id.as_str(),
+        "created_at": date_time,
+        "most_recent_message_id": 0,
+        "is_private": false,
+        "banned_users": [],
+        "server_id": server.id.as_str(),
+        "category_id": category_id,
+        "permission_overrides": [],
+    };
+    db_handle.insert_and_retrieve_one(doc).await
+}
+
+pub async fn list_server_channels(db_handle: &PatDatabase, server_id: &str) -> Result<Vec<ChatChannel>, DbError> {
+    let doc = doc! {"server_id": server_id};
+    db_handle.find(doc).await
+}
+
+// Server members are subscribed to every channel in the server
+pub async fn add_subscriber_to_server_channels(db_handle: &PatDatabase, server_id: &str, user_id: &str) -> Result<u64, DbError> {
+    let filter_doc = doc! {"server_id": server_id};
+    let update_doc = doc! {"$addToSet": {"subscribers": user_id}};
+    db_handle.update_many::<ChatChannel>(filter_doc, update_doc).await
+}
+
+pub async fn remove_subscriber_from_server_channels(db_handle: &PatDatabase, server_id: &str, user_id: &str) -> Result<u64, DbError> {
+    let filter_doc = doc! {"server_id": server_id};
+    let update_doc = doc! {"$pull": {"subscribers": user_id}};
+    db_handle.update_many::<ChatChannel>(filter_doc, update_doc).await
+}
+
+// Replaces any existing override for the same role
+pub async fn set_channel_permission_override(
+    db_handle: &PatDatabase,
+    channel: &ChatChannel,
+    permission_override: PermissionOverride,
+) -> Result<ChatChannel, DbError> {
+    let mut overrides: Vec<PermissionOverride> = channel
+        .permission_overrides
+        .iter()
+        .filter(|o| o.role_id != permission_override.role_id)
+        .cloned()
+        .collect();
+    overrides.push(permission_override);
+
+    let channel_id = str_to_object_id(channel.id.as_str())?;
+    let filter_doc = doc! {"_id": Bson::ObjectId(channel_id)};
+    let update_doc = doc! {"$set": {"permission_overrides": overrides}};
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+pub async fn set_channel_pinned_message(db_handle: &PatDatabase, channel_id: &str, message_id: &str, pinned: bool) -> Result<ChatChannel, DbError> {
+    let object_id = str_to_object_id(channel_id)?;
+    let filter_doc = doc! {"_id": Bson::ObjectId(object_id)};
+    let update_doc = match pinned {
+        true => doc! {"$addToSet": {"pinned_messages": message_id}},
+        false => doc! {"$pull": {"pinned_messages": message_id}},
+    };
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+// Finds the direct message channel between two users, creating it if it does not exist yet
+pub async fn get_or_insert_direct_message_channel(db_handle: &PatDatabase, user_id: &str, other_user_id: &str) -> Result<ChatChannel, DbError> {
+    let dm_key = direct_message_key(user_id, other_user_id);
+    let filter_doc = doc! {"dm_key": dm_key.as_str()};
+
+    // Either participant may have left, opening the DM again only puts the user opening it back in.
+    // Someone who left stays out until they open it again themselves
+    let update_doc = doc! {"$addToSet": {"subscribers": user_id}};
+    match db_handle.find_and_update_one(filter_doc.clone(), update_doc.clone()).await {
+        Err(DbError::NotFound(_)) => (),
+        res => return res,
+    }
+
+    let date_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let doc = doc! {
+        "slug": format!("dm-{dm_key}"),
+        "channel_type": ChannelType::DirectMessage,
+        "name": Bson::Null,
+        "pinned_messages": [],
+        "subscribers": [user_id, other_user_id],
+        "owner_id": user_id,
+        "created_at": date_time,
+        "most_recent_message_id": 0,
+        "dm_key": dm_key.as_str(),
+        "is_private": true,
+        "banned_users": [],
+    };
+    match db_handle.insert_and_retrieve_one(doc).await {
+        // The other user opened the same DM at the same time, use theirs
+        Err(DbError::AlreadyExists) => db_handle.find_and_update_one(filter_doc, update_doc).await,
+        res => res,
+    }
+}
+
 // pub async fn get_chat_channel_by_slug_and_user_id(db_handle: &PatDatabase, slug: &str, user_id: &str) -> Result<ChatChannel, DbError> {
 //     let doc = doc! { "slug": slug, "owner_id": user_id };
 //     db_handle.find_one(doc).await
@@ -60,6 +173,24 @@ pub async fn update_chat_channel_by_id(db_handle: &PatDatabase, filter_doc: Docu
     db_handle.find_and_update_one(filter_doc, update_doc).await
 }
 
+pub async fn delete_chat_channel_by_id(db_handle: &PatDatabase, id: &str) -> Result<(), DbError> {
+    let channel_id = str_to_object_id(id)?;
+    let filter_doc = doc! {"_id": Bson::ObjectId(channel_id)};
+    db_handle.delete_one::<ChatChannel>(filter_doc).await
+}
+
+// Deletes a channel along with its messages, read markers and invites. The channel is deleted
+// first so nothing new can be sent to it while the rest is cleaned up
+// TODO: This should be a task once the task manager supports one-off tasks, large channels
+//       will hold up the request
+pub async fn delete_chat_channel_and_contents(db_handle: &PatDatabase, id: &str) -> Result<(), DbError> {
+    delete_chat_channel_by_id(db_handle, id).await?;
+    delete_chat_messages_for_channel(db_handle, id).await?;
+    delete_read_states_for_channel(db_handle, id).await?;
+    delete_chat_invites_for_channel(db_handle, id).await?;
+    Ok(())
+}
+
 pub async fn list_chat_channels(db_handle: &PatDatabase, filter_doc: Document) -> Result<Vec<ChatChannel>, DbError> {
     db_handle.find(filter_doc).await
 }
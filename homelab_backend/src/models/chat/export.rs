@@ -0,0 +1,228 @@
+use super::{chat_channel::ChatChannel, message::Reactions, message_db::get_chat_message_span};
+use crate::{db::PatDatabase, error_handler::DbError, models::user::user_db::db_get_user_by_id, util::format_unix_time};
+use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Messages are loaded this many atomic IDs at a time, so only one page of a channel is ever in memory
+const EXPORT_PAGE_SIZE: i64 = 200;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    // One JSON object per line, one line per message
+    #[default]
+    Ndjson,
+    // A standalone page which can be opened in a browser
+    Html,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+// A message as it appears in an export. Messages can't have attachments yet, so there are no
+// attachment links to include
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExportedMessage {
+    pub id: String,
+    pub atomic_id: i64,
+    pub author_id: String,
+    // None when the author has deleted their account
+    pub author_username: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub contents: String,
+    pub reply_to: Option<String>,
+    pub reply_count: i64,
+    pub reactions: Vec<Reactions>,
+    pub pinned: bool,
+}
+
+enum ExportStage {
+    Header,
+    Messages,
+    Done,
+}
+
+struct ChannelExport {
+    db_handle: PatDatabase,
+    channel_id: String,
+    channel_name: String,
+    format: ExportFormat,
+    stage: ExportStage,
+    next_atomic_id: i64,
+    // Messages sent after the export started are left out
+    last_atomic_id: i64,
+    usernames: HashMap<String, Option<String>>,
+}
+
+impl ChannelExport {
+    // The next piece of the export, or None once it is finished
+    async fn next_chunk(&mut self) -> Result<Option<String>, DbError> {
+        match self.stage {
+            ExportStage::Header => {
+                self.stage = ExportStage::Messages;
+                Ok(Some(self.render_header()))
+            }
+            ExportStage::Messages if self.next_atomic_id > self.last_atomic_id => {
+                self.stage = ExportStage::Done;
+                Ok(Some(self.render_footer()))
+            }
+            ExportStage::Messages => {
+                let page_end = (self.next_atomic_id + EXPORT_PAGE_SIZE - 1).min(self.last_atomic_id);
+                let page_size = page_end - self.next_atomic_id + 1;
+                let messages = get_chat_message_span(&self.db_handle, page_end, self.channel_id.as_str(), page_size).await?;
+                self.next_atomic_id = page_end + 1;
+
+                let mut chunk = String::new();
+                for message in messages {
+                    let author_username = self.username(message.author_id.as_str()).await;
+                    let exported = ExportedMessage {
+                        id: message.id,
+                        atomic_id: message.atomic_id,
+                        author_id: message.author_id,
+                        author_username,
+                        created_at: message.created_at,
+                        updated_at: message.updated_at,
+                        contents: message.contents,
+                        reply_to: message.reply_to,
+                        reply_count: message.reply_count,
+                        reactions: message.reactions,
+                        pinned: message.pinned,
+                    };
+                    chunk.push_str(self.render_message(&exported).as_str());
+                }
+                Ok(Some(chunk))
+            }
+            ExportStage::Done => Ok(None),
+        }
+    }
+
+    // Each author is only looked up once per export
+    async fn username(&mut self, user_id: &str) -> Option<String> {
+        if let Some(username) = self.usernames.get(user_id) {
+            return username.clone();
+        }
+        let username = db_get_user_by_id(&self.db_handle, user_id).await.ok().map(|user| user.username);
+        self.usernames.insert(user_id.to_owned(), username.clone());
+        username
+    }
+
+    fn render_header(&self) -> String {
+        match self.format {
+            ExportFormat::Ndjson => String::new(),
+            ExportFormat::Html => {
+                let name = escape_html(self.channel_name.as_str());
+                format!(
+                    "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{name}</title>\n<style>\n\
+                     body {{ font-family: sans-serif; max-width: 50rem; margin: 2rem auto; }}\n\
+                     .message {{ padding: 0.5rem 0; border-bottom: 1px solid #ddd; }}\n\
+                     .author {{ font-weight: bold; }}\n\
+                     .time, .reply, .reactions {{ color: #666; font-size: 0.85rem; }}\n\
+                     .contents {{ white-space: pre-wrap; }}\n\
+                     </style>\n</head>\n<body>\n<h1>{name}</h1>\n"
+                )
+            }
+        }
+    }
+
+    fn render_message(&self, message: &ExportedMessage) -> String {
+        match self.format {
+            // Serializing a struct of strings, numbers and vecs can't fail
+            ExportFormat::Ndjson => serde_json::to_string(message).expect("Failed to serialize an exported message") + "\n",
+            ExportFormat::Html => {
+                let author = match &message.author_username {
+                    Some(username) => escape_html(username.as_str()),
+                    None => "Deleted user".to_owned(),
+                };
+                let pinned = if message.pinned { " &middot; pinned" } else { "" };
+                let reply = match &message.reply_to {
+                    Some(reply_to) => format!(
+                        "<div class=\"reply\">Reply to <a href=\"#message-{}\">an earlier message</a></div>\n",
+                        escape_html(reply_to.as_str())
+                    ),
+                    None => String::new(),
+                };
+                let reactions = match message.reactions.is_empty() {
+                    true => String::new(),
+                    false => {
+                        let reactions: Vec<String> = message
+                            .reactions
+                            .iter()
+                            .map(|reaction| format!("{} {}", escape_html(reaction.emoji.name.as_str()), reaction.count))
+                            .collect();
+                        format!("<div class=\"reactions\">{}</div>\n", reactions.join(" &middot; "))
+                    }
+                };
+                format!(
+                    "<div class=\"message\" id=\"message-{}\">\n<div><span class=\"author\">{}</span> <span class=\"time\">{}{}</span></div>\n{}<div class=\"contents\">{}</div>\n{}</div>\n",
+                    escape_html(message.id.as_str()),
+                    author,
+                    format_unix_time(message.created_at),
+                    pinned,
+                    reply,
+                    escape_html(message.contents.as_str()),
+                    reactions
+                )
+            }
+        }
+    }
+
+    fn render_footer(&self) -> String {
+        match self.format {
+            ExportFormat::Ndjson => String::new(),
+            ExportFormat::Html => "</body>\n</html>\n".to_owned(),
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Every message in a channel, oldest first, rendered one page at a time as the stream is read. A
+// failure part way through ends the stream with an error
+pub fn export_channel(db_handle: PatDatabase, channel: ChatChannel, format: ExportFormat) -> impl Stream<Item = Result<String, DbError>> {
+    let export = ChannelExport {
+        db_handle,
+        channel_name: channel.name.clone().unwrap_or_else(|| channel.slug.clone()),
+        channel_id: channel.id,
+        format,
+        stage: ExportStage::Header,
+        next_atomic_id: 1,
+        last_atomic_id: channel.most_recent_message_id,
+        usernames: HashMap::new(),
+    };
+    stream::unfold(Some(export), |export| async move {
+        let mut export = export?;
+        match export.next_chunk().await {
+            Ok(Some(chunk)) => Some((Ok(chunk), Some(export))),
+            Ok(None) => None,
+            Err(db_err) => Some((Err(db_err), None)),
+        }
+    })
+}
@@ -0,0 +1,18 @@
+use super::super::deserialize_id;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChatInvite {
+    #[serde(rename = "_id", deserialize_with = "deserialize_id")]
+    pub id: String,
+    pub code: String,
+    // An invite is for either a channel or a server
+    pub channel_id: Option<String>,
+    #[serde(default)]
+    pub server_id: Option<String>,
+    pub creator_id: String,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub max_uses: Option<i64>,
+    pub uses: i64,
+}
@@ -0,0 +1,105 @@
+use super::{invite::ChatInvite, validation::CreateInviteSchema};
+use crate::{
+    db::{MongoModel, PatDatabase},
+    error_handler::DbError,
+    util::current_unix_time,
+};
+use mongodb::bson::{doc, oid::ObjectId, Bson};
+use rand::{distr::Alphanumeric, Rng};
+
+const INVITE_CODE_LENGTH: usize = 10;
+
+impl MongoModel for ChatInvite {
+    fn collection_name() -> &'static str {
+        "chat_invites"
+    }
+    fn model_name() -> &'static str {
+        "Chat Invite"
+    }
+    fn mongo_id(&self) -> Result<ObjectId, DbError> {
+        match self.id.parse::<ObjectId>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(DbError::BadId),
+        }
+    }
+}
+
+pub enum InviteTarget<'a> {
+    Channel(&'a str),
+    Server(&'a str),
+}
+
+fn generate_invite_code() -> String {
+    rand::rng().sample_iter(&Alphanumeric).take(INVITE_CODE_LENGTH).map(char::from).collect()
+}
+
+pub async fn insert_chat_invite(
+    db_handle: &PatDatabase,
+    data: &CreateInviteSchema,
+    target: InviteTarget<'_>,
+    user_id: &str,
+) -> Result<ChatInvite, DbError> {
+    let current_time = current_unix_time();
+    let expires_at = match data.expires_in {
+        Some(expires_in) => Bson::Int64(current_time + expires_in),
+        None => Bson::Null,
+    };
+    let max_uses = match data.max_uses {
+        Some(max_uses) => Bson::Int64(max_uses),
+        None => Bson::Null,
+    };
+    let (channel_id, server_id) = match target {
+        InviteTarget::Channel(channel_id) => (Bson::String(channel_id.to_owned()), Bson::Null),
+        InviteTarget::Server(server_id) => (Bson::Null, Bson::String(server_id.to_owned())),
+    };
+    let doc = doc! {
+        "code": generate_invite_code(),
+        "channel_id": channel_id,
+        "server_id": server_id,
+        "creator_id": user_id,
+        "created_at": current_time,
+        "expires_at": expires_at,
+        "max_uses": max_uses,
+        "uses": 0,
+    };
+    db_handle.insert_and_retrieve_one(doc).await
+}
+
+pub async fn get_chat_invite_by_code(db_handle: &PatDatabase, code: &str) -> Result<ChatInvite, DbError> {
+    let doc = doc! { "code": code };
+    db_handle.find_one(doc).await
+}
+
+pub async fn get_chat_invites_for_channel(db_handle: &PatDatabase, channel_id: &str) -> Result<Vec<ChatInvite>, DbError> {
+    let doc = doc! { "channel_id": channel_id };
+    db_handle.find(doc).await
+}
+
+pub async fn get_chat_invites_for_server(db_handle: &PatDatabase, server_id: &str) -> Result<Vec<ChatInvite>, DbError> {
+    let doc = doc! { "server_id": server_id };
+    db_handle.find(doc).await
+}
+
+// Uses up an invite, failing with a NotFound if the invite has expired or has no uses left. The
+// check and the increment happen in one operation so an invite can't be over-used by racing joins
+pub async fn use_chat_invite(db_handle: &PatDatabase, code: &str) -> Result<ChatInvite, DbError> {
+    let filter_doc = doc! {
+        "code": code,
+        "$and": [
+            {"$or": [{"expires_at": Bson::Null}, {"expires_at": {"$gt": current_unix_time()}}]},
+            {"$or": [{"max_uses": Bson::Null}, {"$expr": {"$lt": ["$uses", "$max_uses"]}}]},
+        ],
+    };
+    let update_doc = doc! { "$inc": { "uses": 1 } };
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+pub async fn delete_chat_invite(db_handle: &PatDatabase, code: &str) -> Result<(), DbError> {
+    let doc = doc! { "code": code };
+    db_handle.delete_one::<ChatInvite>(doc).await
+}
+
+pub async fn delete_chat_invites_for_channel(db_handle: &PatDatabase, channel_id: &str) -> Result<u64, DbError> {
+    let doc = doc! { "channel_id": channel_id };
+    db_handle.delete_many::<ChatInvite>(doc).await
+}
@@ -4,8 +4,8 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct EmojiDetails {
-    id: String,
-    name: String,
+    pub id: String,
+    pub name: String,
 }
 
 impl From<EmojiDetails> for Bson {
@@ -19,8 +19,8 @@ impl From<EmojiDetails> for Bson {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Reactions {
-    count: i64,
-    emoji: EmojiDetails,
+    pub count: i64,
+    pub emoji: EmojiDetails,
 }
 
 impl From<Reactions> for Bson {
@@ -45,7 +45,18 @@ pub struct ChatMessage {
     pub updated_at: i64,
     pub contents: String,
     pub reply_to: Option<String>,
+    // Number of messages which reply directly to this one
+    #[serde(default)]
+    pub reply_count: i64,
     pub reactions: Vec<Reactions>,
     pub pinned: bool,
     pub atomic_id: i64,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MessageSearchResult {
+    pub message: ChatMessage,
+    // Messages immediately before and after the match in its channel, oldest first
+    pub context_before: Vec<ChatMessage>,
+    pub context_after: Vec<ChatMessage>,
+}
@@ -63,6 +63,26 @@ async fn execute_chat_message_transaction(
         None => return Err(MongoError::custom("Failed to find a chat channel with the given ID".to_string())),
     };
 
+    // A reply must point at a message in the same channel, the parent tracks how many replies it has
+    let reply_to_id = match &data.reply_to {
+        Some(reply_to) => {
+            let parent_id = str_to_object_id(reply_to.as_str())?;
+            let parent_filter_doc = doc! {"_id": Bson::ObjectId(parent_id), "channel_id": data.channel_id.as_str()};
+            if chat_message_collection
+                .find_one(parent_filter_doc)
+                .session(&mut *session)
+                .await?
+                .is_none()
+            {
+                return Err(MongoError::custom(
+                    "Tried to reply to a message which does not exist in this channel".to_string(),
+                ));
+            }
+            Some(parent_id)
+        }
+        None => None,
+    };
+
     // Create a message
     let new_atomic_id = channel.most_recent_message_id + 1;
     let insert_message_doc = data.create_message_doc(user_id, new_atomic_id);
@@ -80,6 +100,12 @@ async fn execute_chat_message_transaction(
         .update_one(channel_filter_doc, channel_update)
         .session(&mut *session)
         .await?;
+    if let Some(parent_id) = reply_to_id {
+        chat_message_collection
+            .update_one(doc! {"_id": Bson::ObjectId(parent_id)}, doc! {"$inc": {"reply_count": 1}})
+            .session(&mut *session)
+            .await?;
+    }
     let mut loop_counter = 0;
     loop {
         // Emergency safety valve to stop an infinite hang if mongo behaves strangely
@@ -126,3 +152,91 @@ pub async fn get_chat_message_span(
         Err(e) => Err(e.into()),
     }
 }
+
+pub async fn get_chat_message_by_id(db_handle: &PatDatabase, channel_id: &str, message_id: &str) -> Result<ChatMessage, DbError> {
+    let message_id = str_to_object_id(message_id)?;
+    let doc = doc! {"_id": Bson::ObjectId(message_id), "channel_id": channel_id};
+    db_handle.find_one(doc).await
+}
+
+pub async fn set_chat_message_pinned(db_handle: &PatDatabase, channel_id: &str, message_id: &str, pinned: bool) -> Result<ChatMessage, DbError> {
+    let message_id = str_to_object_id(message_id)?;
+    let filter_doc = doc! {"_id": Bson::ObjectId(message_id), "channel_id": channel_id};
+    let update_doc = doc! {"$set": {"pinned": pinned}};
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+// Returns up to `message_count` direct replies to a message, oldest first, and whether there are
+// more replies after them
+pub async fn get_thread_replies(
+    db_handle: &PatDatabase,
+    channel_id: &str,
+    parent_id: &str,
+    after_atomic_id: i64,
+    message_count: i64,
+) -> Result<(Vec<ChatMessage>, bool), DbError> {
+    let collection: Collection<ChatMessage> = db_handle.get_collection();
+    let doc = doc! {
+        "channel_id": channel_id,
+        "reply_to": parent_id,
+        "atomic_id": {"$gt": after_atomic_id},
+    };
+    let sort = doc! {"atomic_id": 1};
+    // Grab one extra reply to find out if there is another page
+    let mut replies: Vec<ChatMessage> = match collection.find(doc).sort(sort).limit(message_count + 1).await {
+        Ok(cursor) => match cursor.try_collect().await {
+            Ok(res) => res,
+            Err(e) => return Err(e.into()),
+        },
+        Err(e) => return Err(e.into()),
+    };
+    let has_more = replies.len() as i64 > message_count;
+    replies.truncate(message_count as usize);
+    Ok((replies, has_more))
+}
+
+// Messages in a channel newer than `after_atomic_id`, oldest first, and whether there are more
+// past `message_count`
+pub async fn get_chat_messages_after(
+    db_handle: &PatDatabase,
+    channel_id: &str,
+    after_atomic_id: i64,
+    message_count: i64,
+) -> Result<(Vec<ChatMessage>, bool), DbError> {
+    let collection: Collection<ChatMessage> = db_handle.get_collection();
+    let doc = doc! {
+        "channel_id": channel_id,
+        "atomic_id": {"$gt": after_atomic_id},
+    };
+    let sort = doc! {"atomic_id": 1};
+    // Grab one extra message to find out if there are more
+    let mut messages: Vec<ChatMessage> = match collection.find(doc).sort(sort).limit(message_count + 1).await {
+        Ok(cursor) => match cursor.try_collect().await {
+            Ok(res) => res,
+            Err(e) => return Err(e.into()),
+        },
+        Err(e) => return Err(e.into()),
+    };
+    let has_more = messages.len() as i64 > message_count;
+    messages.truncate(message_count as usize);
+    Ok((messages, has_more))
+}
+
+// Full text search over message contents, the filter_doc must contain a $text expression. Results
+// are sorted by relevance and then by recency
+pub async fn search_chat_messages(db_handle: &PatDatabase, filter_doc: Document, limit: i64) -> Result<Vec<ChatMessage>, DbError> {
+    let collection: Collection<ChatMessage> = db_handle.get_collection();
+    let sort = doc! {"score": {"$meta": "textScore"}, "created_at": -1};
+    match collection.find(filter_doc).sort(sort).limit(limit).await {
+        Ok(cursor) => match cursor.try_collect().await {
+            Ok(res) => Ok(res),
+            Err(e) => Err(e.into()),
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn delete_chat_messages_for_channel(db_handle: &PatDatabase, channel_id: &str) -> Result<u64, DbError> {
+    let doc = doc! {"channel_id": channel_id};
+    db_handle.delete_many::<ChatMessage>(doc).await
+}
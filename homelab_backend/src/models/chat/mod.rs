@@ -1,6 +1,15 @@
 pub mod chat_channel;
 pub mod chat_channel_db;
+pub mod export;
+pub mod invite;
+pub mod invite_db;
 pub mod message;
 pub mod message_db;
 pub mod packet;
+pub mod read_receipt;
+pub mod read_receipt_db;
+pub mod server;
+pub mod server_db;
+pub mod socket_ticket;
+pub mod socket_ticket_db;
 pub mod validation;
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use super::message::ChatMessage;
 use super::validation::CreateMessageSchema;
+use crate::realtime::dispatcher::SocketRequest;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct RequestMessagesSchema {
@@ -10,12 +12,118 @@ pub struct RequestMessagesSchema {
     pub channel_id: String,
 }
 
-// TODO: Define error codes, maybe just make an enum that serializes to ints?
-// TODO: I should have a from/into to convert DbError into a WebSocketError
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct GetThreadSchema {
+    pub channel_id: String,
+    pub message_id: String,
+    // Replies are returned oldest first, starting after this atomic ID. Use 0 to start from the
+    // beginning of a thread
+    pub after_atomic_id: i64,
+    pub message_count: i64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct MarkReadSchema {
+    pub channel_id: String,
+    pub atomic_message_id: i64,
+    // Read receipts are only broadcast for direct messages, and can be suppressed by the reader
+    pub send_read_receipt: Option<bool>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PinMessageSchema {
+    pub channel_id: String,
+    pub message_id: String,
+    // Set to false to unpin the message
+    pub pinned: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ResumeChannelSchema {
+    pub channel_id: String,
+    // The newest message the client has in this channel
+    pub last_atomic_id: i64,
+}
+
+// Sent after reconnecting to catch up on everything missed while disconnected
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ResumeSchema {
+    pub channels: Vec<ResumeChannelSchema>,
+}
+
+// Has to be the first thing sent on a socket which was opened without any other credentials
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AuthenticateSchema {
+    pub ticket: String,
+}
+
+// Serialized as ints which mirror HTTP status codes, so a client can handle them the same way as
+// errors from the REST API
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(into = "i64", try_from = "i64")]
+pub enum WebSocketErrorCode {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    RateLimited,
+    InternalError,
+}
+
+impl From<WebSocketErrorCode> for i64 {
+    fn from(value: WebSocketErrorCode) -> Self {
+        match value {
+            WebSocketErrorCode::BadRequest => 400,
+            WebSocketErrorCode::Unauthorized => 401,
+            WebSocketErrorCode::Forbidden => 403,
+            WebSocketErrorCode::NotFound => 404,
+            WebSocketErrorCode::RateLimited => 429,
+            WebSocketErrorCode::InternalError => 500,
+        }
+    }
+}
+
+impl TryFrom<i64> for WebSocketErrorCode {
+    type Error = String;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            400 => Ok(WebSocketErrorCode::BadRequest),
+            401 => Ok(WebSocketErrorCode::Unauthorized),
+            403 => Ok(WebSocketErrorCode::Forbidden),
+            404 => Ok(WebSocketErrorCode::NotFound),
+            429 => Ok(WebSocketErrorCode::RateLimited),
+            500 => Ok(WebSocketErrorCode::InternalError),
+            _ => Err(format!("{value} is not a websocket error code")),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct WebSocketError {
-    pub status_code: i64,
+    pub status_code: WebSocketErrorCode,
     pub msg: String,
+    // Only set on a RateLimited error, how many milliseconds to wait before trying again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+}
+
+impl WebSocketError {
+    pub fn new(status_code: WebSocketErrorCode, msg: &str) -> Self {
+        Self {
+            status_code,
+            msg: msg.to_owned(),
+            retry_after_ms: None,
+        }
+    }
+
+    pub fn rate_limited(msg: &str, retry_after: Duration) -> Self {
+        Self {
+            status_code: WebSocketErrorCode::RateLimited,
+            msg: msg.to_owned(),
+            retry_after_ms: Some(retry_after.as_millis() as u64),
+        }
+    }
 }
 
 /*
@@ -33,6 +141,49 @@ The above will serialize the enum to look like
 pub enum WebSocketRequest {
     CreateMessage(CreateMessageSchema),
     GetChatState(RequestMessagesSchema),
+    MarkRead(MarkReadSchema),
+    GetThread(GetThreadSchema),
+    PinMessage(PinMessageSchema),
+    Resume(ResumeSchema),
+    // Only valid as the first request on a socket, it isn't routed by the dispatcher
+    Authenticate(AuthenticateSchema),
+}
+
+// Requests are routed by the SocketDispatcher, each request's TYPE has to match its variant name
+// here so clients can keep building requests from this enum
+impl SocketRequest for CreateMessageSchema {
+    const TYPE: &'static str = "CreateMessage";
+}
+
+impl SocketRequest for RequestMessagesSchema {
+    const TYPE: &'static str = "GetChatState";
+}
+
+impl SocketRequest for MarkReadSchema {
+    const TYPE: &'static str = "MarkRead";
+}
+
+impl SocketRequest for GetThreadSchema {
+    const TYPE: &'static str = "GetThread";
+}
+
+impl SocketRequest for PinMessageSchema {
+    const TYPE: &'static str = "PinMessage";
+}
+
+impl SocketRequest for ResumeSchema {
+    const TYPE: &'static str = "Resume";
+}
+
+// What actually travels over the socket. A client can give any request a request_id, and it is
+// echoed back in every response to that request so the client can tell which request a
+// MessageCreated or an error belongs to
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WebSocketRequestFrame {
+    #[serde(flatten)]
+    pub request: WebSocketRequest,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl From<CreateMessageSchema> for WebSocketRequest {
@@ -47,30 +198,146 @@ impl From<RequestMessagesSchema> for WebSocketRequest {
     }
 }
 
+impl From<MarkReadSchema> for WebSocketRequest {
+    fn from(value: MarkReadSchema) -> Self {
+        WebSocketRequest::MarkRead(value)
+    }
+}
+
+impl From<GetThreadSchema> for WebSocketRequest {
+    fn from(value: GetThreadSchema) -> Self {
+        WebSocketRequest::GetThread(value)
+    }
+}
+
+impl From<PinMessageSchema> for WebSocketRequest {
+    fn from(value: PinMessageSchema) -> Self {
+        WebSocketRequest::PinMessage(value)
+    }
+}
+
+impl From<ResumeSchema> for WebSocketRequest {
+    fn from(value: ResumeSchema) -> Self {
+        WebSocketRequest::Resume(value)
+    }
+}
+
+impl From<AuthenticateSchema> for WebSocketRequest {
+    fn from(value: AuthenticateSchema) -> Self {
+        WebSocketRequest::Authenticate(value)
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct MessageCreatedResponse {
     pub atomic_message_id: i64,
     pub chat_channel_id: String,
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ReadReceiptResponse {
+    pub channel_id: String,
+    pub user_id: String,
+    pub last_read_atomic_id: i64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ThreadResponse {
+    pub parent: ChatMessage,
+    pub replies: Vec<ChatMessage>,
+    pub has_more: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct MessagePinnedResponse {
+    pub channel_id: String,
+    pub message_id: String,
+    pub pinned: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ResumedChannel {
+    pub channel_id: String,
+    // Oldest first. When has_more is true the client missed too much to replay and should
+    // fetch the channel with GetChatState instead
+    pub messages: Vec<ChatMessage>,
+    pub has_more: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ResumeResponse {
+    pub channels: Vec<ResumedChannel>,
+    // Channels the user can no longer read, they were removed or the channel was deleted while
+    // the client was disconnected
+    pub lost_channels: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AuthenticatedResponse {
+    pub user_id: String,
+}
+
+// Changes to a channel made by its owner, sent to everyone affected by the change
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", content = "data")]
+pub enum ChannelEvent {
+    MemberJoined(String),
+    MemberKicked(String),
+    MemberBanned(String),
+    OwnershipTransferred(String),
+    ChannelUpdated { name: Option<String>, is_private: bool },
+    ChannelDeleted,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChannelEventResponse {
+    pub channel_id: String,
+    pub event: ChannelEvent,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(tag = "type", content = "data")]
 pub enum WebSocketResponse {
     MessageCreated(MessageCreatedResponse),
     SendChatMessage(ChatMessage),
     SendChatState(Vec<ChatMessage>),
+    // Sent to the reader after a MarkRead
+    MarkedRead(ReadReceiptResponse),
+    // Sent to the other participants of a direct message after a MarkRead
+    ReadReceipt(ReadReceiptResponse),
+    SendThread(ThreadResponse),
+    SendChannelEvent(ChannelEventResponse),
+    // Sent to everyone who can read the channel, including whoever pinned the message
+    MessagePinned(MessagePinnedResponse),
+    Resumed(ResumeResponse),
+    // Sent once an Authenticate succeeds, requests can be made after this
+    Authenticated(AuthenticatedResponse),
     SendError(WebSocketError),
 }
 
 impl WebSocketResponse {
-    pub fn ws_error(status_code: i64, msg: &str) -> WebSocketResponse {
-        WebSocketResponse::SendError(WebSocketError {
-            status_code,
-            msg: msg.to_owned(),
-        })
+    pub fn ws_error(status_code: WebSocketErrorCode, msg: &str) -> WebSocketResponse {
+        WebSocketResponse::SendError(WebSocketError::new(status_code, msg))
+    }
+}
+
+// Broadcasts, like a message sent by someone else, don't belong to a request and have no request_id
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct WebSocketResponseFrame {
+    #[serde(flatten)]
+    pub response: WebSocketResponse,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl From<WebSocketResponse> for WebSocketResponseFrame {
+    fn from(value: WebSocketResponse) -> Self {
+        Self {
+            response: value,
+            request_id: None,
+        }
     }
 }
 
 // TODO: React to message packet (receive and send)
-// TODO: Pin message packet (receive and send)
 // TODO: Edit message
@@ -0,0 +1,14 @@
+use super::super::deserialize_id;
+use serde::{Deserialize, Serialize};
+
+// Tracks how far a user has read in a channel. Atomic IDs are a per-channel counter with no gaps,
+// so the unread count for a channel is just the channel's most recent atomic ID minus this one
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChannelReadState {
+    #[serde(rename = "_id", deserialize_with = "deserialize_id")]
+    pub id: String,
+    pub user_id: String,
+    pub channel_id: String,
+    pub last_read_atomic_id: i64,
+    pub updated_at: i64,
+}
@@ -0,0 +1,42 @@
+use super::read_receipt::ChannelReadState;
+use crate::{
+    db::{MongoModel, PatDatabase},
+    error_handler::DbError,
+    util::current_unix_time,
+};
+use mongodb::bson::{doc, oid::ObjectId};
+
+impl MongoModel for ChannelReadState {
+    fn collection_name() -> &'static str {
+        "chat_read_states"
+    }
+    fn model_name() -> &'static str {
+        "Channel Read State"
+    }
+    fn mongo_id(&self) -> Result<ObjectId, DbError> {
+        match self.id.parse::<ObjectId>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(DbError::BadId),
+        }
+    }
+}
+
+pub async fn mark_channel_read(db_handle: &PatDatabase, user_id: &str, channel_id: &str, atomic_id: i64) -> Result<ChannelReadState, DbError> {
+    // $max means a stale or out of order MarkRead can never move the read marker backwards
+    let filter_doc = doc! { "user_id": user_id, "channel_id": channel_id };
+    let update_doc = doc! {
+        "$max": { "last_read_atomic_id": atomic_id },
+        "$set": { "updated_at": current_unix_time() },
+    };
+    db_handle.upsert_one(filter_doc, update_doc).await
+}
+
+pub async fn get_read_states_for_user(db_handle: &PatDatabase, user_id: &str) -> Result<Vec<ChannelReadState>, DbError> {
+    let doc = doc! { "user_id": user_id };
+    db_handle.find(doc).await
+}
+
+pub async fn delete_read_states_for_channel(db_handle: &PatDatabase, channel_id: &str) -> Result<u64, DbError> {
+    let doc = doc! { "channel_id": channel_id };
+    db_handle.delete_many::<ChannelReadState>(doc).await
+}
@@ -0,0 +1,161 @@
+use super::super::deserialize_id;
+use super::chat_channel::ReturnChannel;
+use mongodb::bson::{doc, Bson};
+use serde::{Deserialize, Serialize};
+
+// Permissions are bitflags so a role or override can hold any combination of them
+pub mod permissions {
+    pub const READ: i64 = 1;
+    pub const SEND: i64 = 1 << 1;
+    pub const PIN: i64 = 1 << 2;
+    pub const MANAGE: i64 = 1 << 3;
+    pub const ALL: i64 = READ | SEND | PIN | MANAGE;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerMember {
+    pub user_id: String,
+    pub role_ids: Vec<String>,
+}
+
+impl From<ServerMember> for Bson {
+    fn from(value: ServerMember) -> Self {
+        Bson::Document(doc! {
+            "user_id": value.user_id,
+            "role_ids": value.role_ids,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerRole {
+    pub id: String,
+    pub name: String,
+    pub permissions: i64,
+}
+
+impl From<ServerRole> for Bson {
+    fn from(value: ServerRole) -> Self {
+        Bson::Document(doc! {
+            "id": value.id,
+            "name": value.name,
+            "permissions": value.permissions,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerCategory {
+    pub id: String,
+    pub name: String,
+}
+
+impl From<ServerCategory> for Bson {
+    fn from(value: ServerCategory) -> Self {
+        Bson::Document(doc! {
+            "id": value.id,
+            "name": value.name,
+        })
+    }
+}
+
+// Changes the permissions a role has in a single channel. Using the server's ID as the role_id
+// targets every member of the server
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PermissionOverride {
+    pub role_id: String,
+    pub allow: i64,
+    pub deny: i64,
+}
+
+impl From<PermissionOverride> for Bson {
+    fn from(value: PermissionOverride) -> Self {
+        Bson::Document(doc! {
+            "role_id": value.role_id,
+            "allow": value.allow,
+            "deny": value.deny,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChatServer {
+    #[serde(rename = "_id", deserialize_with = "deserialize_id")]
+    pub id: String,
+    pub name: String,
+    pub owner_id: String,
+    pub created_at: i64,
+    // Permissions every member has before roles are applied
+    pub default_permissions: i64,
+    pub members: Vec<ServerMember>,
+    pub roles: Vec<ServerRole>,
+    pub categories: Vec<ServerCategory>,
+}
+
+impl ChatServer {
+    pub fn get_member(&self, user_id: &str) -> Option<&ServerMember> {
+        self.members.iter().find(|member| member.user_id == user_id)
+    }
+
+    pub fn is_member(&self, user_id: &str) -> bool {
+        self.get_member(user_id).is_some()
+    }
+
+    pub fn has_role(&self, role_id: &str) -> bool {
+        self.roles.iter().any(|role| role.id == role_id)
+    }
+
+    pub fn has_category(&self, category_id: &str) -> bool {
+        self.categories.iter().any(|category| category.id == category_id)
+    }
+
+    // Permissions a user has across the whole server, ignoring channel overrides
+    pub fn server_permissions(&self, user_id: &str) -> i64 {
+        if self.owner_id == user_id {
+            return permissions::ALL;
+        }
+        let member = match self.get_member(user_id) {
+            Some(member) => member,
+            None => return 0,
+        };
+        let perms = self
+            .roles
+            .iter()
+            .filter(|role| member.role_ids.contains(&role.id))
+            .fold(self.default_permissions, |perms, role| perms | role.permissions);
+        match perms & permissions::MANAGE {
+            0 => perms,
+            _ => permissions::ALL,
+        }
+    }
+
+    // Permissions a user has in a channel of this server. The override for everyone is applied
+    // first and then the overrides for the member's roles, so a role can re-allow something
+    // denied to everyone. Managers can't be locked out of a channel
+    pub fn channel_permissions(&self, user_id: &str, overrides: &[PermissionOverride]) -> i64 {
+        let mut perms = self.server_permissions(user_id);
+        if perms & permissions::MANAGE != 0 {
+            return perms;
+        }
+        let member = match self.get_member(user_id) {
+            Some(member) => member,
+            None => return 0,
+        };
+
+        if let Some(everyone) = overrides.iter().find(|o| o.role_id == self.id) {
+            perms = (perms & !everyone.deny) | everyone.allow;
+        }
+        let (allow, deny) = overrides
+            .iter()
+            .filter(|o| member.role_ids.contains(&o.role_id))
+            .fold((0, 0), |(allow, deny), o| (allow | o.allow, deny | o.deny));
+        (perms & !deny) | allow
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ReturnServer {
+    pub server: ChatServer,
+    // Only the channels the requester can read
+    pub channels: Vec<ReturnChannel>,
+}
@@ -0,0 +1,176 @@
+use super::{
+    chat_channel::{ChannelType, ChatChannel},
+    server::{permissions, ChatServer, ServerCategory, ServerMember, ServerRole},
+    validation::{CreateServerCategorySchema, CreateServerRoleSchema, CreateServerSchema},
+};
+use crate::{
+    db::{str_to_object_id, MongoModel, PatDatabase},
+    error_handler::DbError,
+    util::current_unix_time,
+};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use std::collections::HashMap;
+
+// Members can read and send in every channel of a new server until roles or overrides say otherwise
+const DEFAULT_SERVER_PERMISSIONS: i64 = permissions::READ | permissions::SEND;
+
+impl MongoModel for ChatServer {
+    fn collection_name() -> &'static str {
+        "chat_servers"
+    }
+    fn model_name() -> &'static str {
+        "Chat Server"
+    }
+    fn mongo_id(&self) -> Result<ObjectId, DbError> {
+        match self.id.parse::<ObjectId>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(DbError::BadId),
+        }
+    }
+}
+
+fn server_filter_doc(server_id: &str) -> Result<Document, DbError> {
+    let server_id = str_to_object_id(server_id)?;
+    Ok(doc! {"_id": Bson::ObjectId(server_id)})
+}
+
+pub async fn insert_chat_server(db_handle: &PatDatabase, data: &CreateServerSchema, user_id: &str) -> Result<ChatServer, DbError> {
+    let owner = ServerMember {
+        user_id: user_id.to_owned(),
+        role_ids: Vec::new(),
+    };
+    let doc = doc! {
+        "name": data.name.as_str(),
+        "owner_id": user_id,
+        "created_at": current_unix_time(),
+        "default_permissions": DEFAULT_SERVER_PERMISSIONS,
+        "members": [owner],
+        "roles": [],
+        "categories": [],
+    };
+    db_handle.insert_and_retrieve_one(doc).await
+}
+
+pub async fn get_chat_server_by_id(db_handle: &PatDatabase, server_id: &str) -> Result<ChatServer, DbError> {
+    let filter_doc = server_filter_doc(server_id)?;
+    db_handle.find_one(filter_doc).await
+}
+
+pub async fn get_chat_servers_for_user(db_handle: &PatDatabase, user_id: &str) -> Result<Vec<ChatServer>, DbError> {
+    let doc = doc! {"members.user_id": user_id};
+    db_handle.find(doc).await
+}
+
+pub async fn add_chat_server_member(db_handle: &PatDatabase, server_id: &str, user_id: &str) -> Result<ChatServer, DbError> {
+    let mut filter_doc = server_filter_doc(server_id)?;
+    filter_doc.insert("members.user_id", doc! {"$ne": user_id});
+    let member = ServerMember {
+        user_id: user_id.to_owned(),
+        role_ids: Vec::new(),
+    };
+    let update_doc = doc! {"$push": {"members": member}};
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+pub async fn remove_chat_server_member(db_handle: &PatDatabase, server_id: &str, user_id: &str) -> Result<ChatServer, DbError> {
+    let mut filter_doc = server_filter_doc(server_id)?;
+    filter_doc.insert("members.user_id", user_id);
+    let update_doc = doc! {"$pull": {"members": {"user_id": user_id}}};
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+pub async fn set_chat_server_member_roles(
+    db_handle: &PatDatabase,
+    server_id: &str,
+    user_id: &str,
+    role_ids: &[String],
+) -> Result<ChatServer, DbError> {
+    let mut filter_doc = server_filter_doc(server_id)?;
+    filter_doc.insert("members.user_id", user_id);
+    let update_doc = doc! {"$set": {"members.$.role_ids": role_ids}};
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+pub async fn insert_chat_server_role(db_handle: &PatDatabase, server_id: &str, data: &CreateServerRoleSchema) -> Result<ChatServer, DbError> {
+    let filter_doc = server_filter_doc(server_id)?;
+    let role = ServerRole {
+        id: ObjectId::new().to_hex(),
+        name: data.name.clone(),
+        permissions: data.permissions,
+    };
+    let update_doc = doc! {"$push": {"roles": role}};
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+pub async fn insert_chat_server_category(db_handle: &PatDatabase, server_id: &str, data: &CreateServerCategorySchema) -> Result<ChatServer, DbError> {
+    let filter_doc = server_filter_doc(server_id)?;
+    let category = ServerCategory {
+        id: ObjectId::new().to_hex(),
+        name: data.name.clone(),
+    };
+    let update_doc = doc! {"$push": {"categories": category}};
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+// Permissions a user has in a channel. Channels outside of a server don't have roles, so their
+// subscribers can read and send, and the owner or either participant of a direct message can do
+// everything else
+pub async fn get_channel_permissions(db_handle: &PatDatabase, channel: &ChatChannel, user_id: &str) -> Result<i64, DbError> {
+    if let Some(server_id) = &channel.server_id {
+        let server = get_chat_server_by_id(db_handle, server_id.as_str()).await?;
+        return Ok(server.channel_permissions(user_id, &channel.permission_overrides));
+    }
+    if !channel.subscribers.iter().any(|subscriber| subscriber == user_id) {
+        return Ok(0);
+    }
+    if channel.owner_id == user_id || channel.channel_type == ChannelType::DirectMessage {
+        return Ok(permissions::ALL);
+    }
+    Ok(permissions::READ | permissions::SEND)
+}
+
+// A failed lookup grants nothing, so callers can treat this like a membership check
+pub async fn has_channel_permission(db_handle: &PatDatabase, channel: &ChatChannel, user_id: &str, permission: i64) -> bool {
+    match get_channel_permissions(db_handle, channel, user_id).await {
+        Ok(perms) => perms & permission == permission,
+        Err(_) => false,
+    }
+}
+
+// The subscribers of a channel who can read it, the ones who should receive its messages
+pub async fn get_channel_readers(db_handle: &PatDatabase, channel: &ChatChannel) -> Result<Vec<String>, DbError> {
+    let server = match &channel.server_id {
+        Some(server_id) => get_chat_server_by_id(db_handle, server_id.as_str()).await?,
+        None => return Ok(channel.subscribers.clone()),
+    };
+    Ok(channel
+        .subscribers
+        .iter()
+        .filter(|subscriber| server.channel_permissions(subscriber, &channel.permission_overrides) & permissions::READ != 0)
+        .cloned()
+        .collect())
+}
+
+// Drops the server channels a user can't read, loading each server only once
+pub async fn filter_readable_channels(db_handle: &PatDatabase, user_id: &str, channels: Vec<ChatChannel>) -> Result<Vec<ChatChannel>, DbError> {
+    let mut servers: HashMap<String, ChatServer> = HashMap::new();
+    let mut readable = Vec::new();
+    for channel in channels {
+        let server_id = match &channel.server_id {
+            Some(server_id) => server_id.clone(),
+            None => {
+                readable.push(channel);
+                continue;
+            }
+        };
+        if !servers.contains_key(&server_id) {
+            let server = get_chat_server_by_id(db_handle, server_id.as_str()).await?;
+            servers.insert(server_id.clone(), server);
+        }
+        let server = servers.get(&server_id).expect("Server should have been loaded");
+        if server.channel_permissions(user_id, &channel.permission_overrides) & permissions::READ != 0 {
+            readable.push(channel);
+        }
+    }
+    Ok(readable)
+}
@@ -0,0 +1,28 @@
+use super::super::deserialize_id;
+use serde::{Deserialize, Serialize};
+
+// A short-lived, single-use ticket which authenticates a chat websocket. Tickets are handed out by a
+// normal authenticated request, so a long-lived JWT never has to go in a websocket URL
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SocketTicket {
+    #[serde(rename = "_id", deserialize_with = "deserialize_id")]
+    pub id: String,
+    pub ticket: String,
+    pub user_id: String,
+    pub expires_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReturnSocketTicket {
+    pub ticket: String,
+    pub expires_at: i64,
+}
+
+impl From<SocketTicket> for ReturnSocketTicket {
+    fn from(value: SocketTicket) -> Self {
+        Self {
+            ticket: value.ticket,
+            expires_at: value.expires_at,
+        }
+    }
+}
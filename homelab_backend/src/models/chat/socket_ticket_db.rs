@@ -0,0 +1,55 @@
+use super::socket_ticket::SocketTicket;
+use crate::{
+    db::{MongoModel, PatDatabase},
+    error_handler::DbError,
+    util::current_unix_time,
+};
+use mongodb::bson::{doc, oid::ObjectId};
+use rand::{distr::Alphanumeric, Rng};
+
+const TICKET_LENGTH: usize = 32;
+// Long enough to open a socket right after asking for a ticket, and no longer
+const TICKET_LIFETIME: i64 = 30;
+
+impl MongoModel for SocketTicket {
+    fn collection_name() -> &'static str {
+        "chat_socket_tickets"
+    }
+    fn model_name() -> &'static str {
+        "Socket Ticket"
+    }
+    fn mongo_id(&self) -> Result<ObjectId, DbError> {
+        match self.id.parse::<ObjectId>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(DbError::BadId),
+        }
+    }
+}
+
+fn generate_ticket() -> String {
+    rand::rng().sample_iter(&Alphanumeric).take(TICKET_LENGTH).map(char::from).collect()
+}
+
+pub async fn insert_socket_ticket(db_handle: &PatDatabase, user_id: &str) -> Result<SocketTicket, DbError> {
+    let current_time = current_unix_time();
+    // Tickets which were never redeemed are cleaned up as new ones are handed out
+    db_handle
+        .delete_many::<SocketTicket>(doc! { "expires_at": {"$lt": current_time} })
+        .await?;
+    let doc = doc! {
+        "ticket": generate_ticket(),
+        "user_id": user_id,
+        "expires_at": current_time + TICKET_LIFETIME,
+    };
+    db_handle.insert_and_retrieve_one(doc).await
+}
+
+// Uses up a ticket, failing with a NotFound if it doesn't exist or has expired. Finding and deleting
+// happen in one operation so a ticket can only ever be redeemed once
+pub async fn redeem_socket_ticket(db_handle: &PatDatabase, ticket: &str) -> Result<SocketTicket, DbError> {
+    let doc = doc! {
+        "ticket": ticket,
+        "expires_at": {"$gte": current_unix_time()},
+    };
+    db_handle.find_and_delete_one(doc).await
+}
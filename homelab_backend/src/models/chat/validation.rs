@@ -8,8 +8,61 @@ pub struct CreateChannelSchema {
     pub name: Option<String>,
     pub channel_type: i64,
     pub slug: String,
+    // Private channels can only be joined with an invite
+    #[serde(default)]
+    pub is_private: bool,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct UpdateChannelSchema {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_private: Option<bool>,
+}
+
+// An invite which should last longer than a year can just never expire
+pub const MAX_INVITE_LIFETIME: i64 = 365 * 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateInviteSchema {
+    // Seconds until the invite expires, never expires if not set. Can be at most MAX_INVITE_LIFETIME
+    pub expires_in: Option<i64>,
+    // Number of times the invite can be used, unlimited if not set
+    pub max_uses: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateServerSchema {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateServerChannelSchema {
+    pub name: String,
+    pub slug: String,
+    pub category_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateServerCategorySchema {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateServerRoleSchema {
+    pub name: String,
+    pub permissions: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SetMemberRolesSchema {
+    pub role_ids: Vec<String>,
+}
+
+// Counted in characters rather than bytes
+pub const MAX_MESSAGE_LENGTH: usize = 4000;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CreateMessageSchema {
     pub channel_id: String,
@@ -26,6 +79,7 @@ impl CreateMessageSchema {
             "created_at": current_time,
             "updated_at": current_time,
             "contents": self.contents,
+            "reply_count": 0,
             "reactions": Vec::<Reactions>::new(),
             "pinned": false,
             "atomic_id": atomic_id,
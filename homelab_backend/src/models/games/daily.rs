@@ -0,0 +1,22 @@
+use super::MinimalConnectionsGame;
+use crate::models::deserialize_id;
+use serde::{Deserialize, Serialize};
+
+// A published connections game scheduled as the puzzle of the day. There is at most one per date
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DailyPuzzle {
+    #[serde(rename = "_id", deserialize_with = "deserialize_id")]
+    pub id: String,
+    // "YYYY-MM-DD" in the timezone set by DAILY_PUZZLE_UTC_OFFSET
+    pub date: String,
+    pub game_id: String,
+    // The admin who scheduled it, announcements are posted as them
+    pub scheduled_by: String,
+    pub announced: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReturnDailyPuzzle {
+    pub date: String,
+    pub game: MinimalConnectionsGame,
+}
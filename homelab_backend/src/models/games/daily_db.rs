@@ -0,0 +1,71 @@
+use super::{daily::DailyPuzzle, validation::ScheduleDailyPuzzleSchema};
+use crate::{
+    db::{MongoModel, PatDatabase},
+    error_handler::DbError,
+};
+use mongodb::bson::{doc, oid::ObjectId};
+
+impl MongoModel for DailyPuzzle {
+    fn collection_name() -> &'static str {
+        "game_daily_puzzles"
+    }
+    fn model_name() -> &'static str {
+        "Daily Puzzle"
+    }
+    fn mongo_id(&self) -> Result<ObjectId, DbError> {
+        match self.id.parse::<ObjectId>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(DbError::BadId),
+        }
+    }
+}
+
+// Fails with an AlreadyExists if something is already scheduled for the date
+pub async fn insert_daily_puzzle(db_handle: &PatDatabase, data: &ScheduleDailyPuzzleSchema, user_id: &str) -> Result<DailyPuzzle, DbError> {
+    let doc = doc! {
+        "date": data.date.as_str(),
+        "game_id": data.game_id.as_str(),
+        "scheduled_by": user_id,
+        "announced": false,
+    };
+    db_handle.insert_and_retrieve_one(doc).await
+}
+
+pub async fn get_daily_puzzle_for_date(db_handle: &PatDatabase, date: &str) -> Result<DailyPuzzle, DbError> {
+    let doc = doc! { "date": date };
+    db_handle.find_one(doc).await
+}
+
+// Every puzzle scheduled for `from_date` or later, soonest first
+pub async fn get_daily_puzzle_queue(db_handle: &PatDatabase, from_date: &str) -> Result<Vec<DailyPuzzle>, DbError> {
+    let doc = doc! { "date": { "$gte": from_date } };
+    let mut queue: Vec<DailyPuzzle> = db_handle.find(doc).await?;
+    // Dates are "YYYY-MM-DD" so they sort as strings
+    queue.sort_by(|a, b| a.date.cmp(&b.date));
+    Ok(queue)
+}
+
+pub async fn delete_daily_puzzle_for_date(db_handle: &PatDatabase, date: &str) -> Result<(), DbError> {
+    let doc = doc! { "date": date };
+    db_handle.delete_one::<DailyPuzzle>(doc).await
+}
+
+pub async fn delete_daily_puzzles_for_game(db_handle: &PatDatabase, game_id: &str) -> Result<u64, DbError> {
+    let doc = doc! { "game_id": game_id };
+    db_handle.delete_many::<DailyPuzzle>(doc).await
+}
+
+// Claims the announcement for a date, so when several instances are running only one of them
+// posts it. Fails with a NotFound if there is nothing to announce
+pub async fn claim_daily_puzzle_announcement(db_handle: &PatDatabase, date: &str) -> Result<DailyPuzzle, DbError> {
+    let filter_doc = doc! { "date": date, "announced": false };
+    let update_doc = doc! { "$set": { "announced": true } };
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+// Hands a claimed announcement back, so a run which failed to post it leaves it for the next run
+pub async fn release_daily_puzzle_announcement(db_handle: &PatDatabase, date: &str) -> Result<u64, DbError> {
+    let filter_doc = doc! { "date": date, "announced": true };
+    let update_doc = doc! { "$set": { "announced": false } };
+    db_handle.update_one::<DailyPuzzle>(filter_doc, update_doc).await
+}
@@ -1,10 +1,17 @@
-use super::{validation::CreateConnectionGameSchema, ConnectionGame};
+use super::{
+    daily_db::delete_daily_puzzles_for_game,
+    rating::{ConnectionGameRating, DifficultyVote},
+    rating_db::{count_difficulty, delete_ratings_for_game},
+    session::ConnectionGameSession,
+    validation::{normalize_tag, ConnectionsGameSort, CreateConnectionGameSchema, UpdateConnectionGameSchema},
+    ConnectionGame, ListedConnectionsGame,
+};
 use crate::{
-    db::{MongoModel, PatDatabase},
+    db::{str_to_object_id, MongoModel, PatDatabase},
     error_handler::DbError,
     models::name_to_slug,
 };
-use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 impl MongoModel for ConnectionGame {
@@ -22,35 +29,242 @@ impl MongoModel for ConnectionGame {
     }
 }
 
+// Another game grabbing the same slug between picking one and saving it is retried this many times
+const MAX_SLUG_ATTEMPTS: usize = 5;
+
+// Slugs only contain letters and dashes, so a dash followed by digits can only be a suffix added
+// to tell apart games with the same name
+fn slug_base(slug: &str) -> &str {
+    match slug.rsplit_once('-') {
+        Some((base, suffix)) if !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()) => base,
+        _ => slug,
+    }
+}
+
+// The slug itself if it's free, otherwise the slug with the lowest unused suffix starting at 2
+pub fn first_free_slug(base: &str, taken_slugs: &[String]) -> String {
+    if !taken_slugs.iter().any(|taken| taken == base) {
+        return base.to_owned();
+    }
+    let mut suffix = 2;
+    loop {
+        let candidate = format!("{base}-{suffix}");
+        if !taken_slugs.contains(&candidate) {
+            return candidate;
+        }
+        suffix += 1;
+    }
+}
+
+async fn find_free_slug(db_handle: &PatDatabase, base: &str) -> Result<String, DbError> {
+    let doc = doc! { "slug": { "$regex": format!("^{base}(-[0-9]+)?$") } };
+    let taken_slugs: Vec<String> = db_handle.find::<ConnectionGame>(doc).await?.into_iter().map(|game| game.slug).collect();
+    Ok(first_free_slug(base, taken_slugs.as_slice()))
+}
+
+// Puzzles are validated before they get here, so this only fails if that was skipped
+fn puzzle_name_to_slug(puzzle_name: &str) -> Result<String, DbError> {
+    match name_to_slug(puzzle_name) {
+        Some(slug) => Ok(slug),
+        None => Err(DbError::UnhandledException(
+            "Tried to save a connections game without a valid slug".to_owned(),
+        )),
+    }
+}
+
+// Tags are validated before they get here, so none of them normalize to the same tag
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    tags.iter().map(|tag| normalize_tag(tag)).collect()
+}
+
+// Slugs are unique across every author, a game named the same as an existing one gets a numbered
+// suffix, like "my-puzzle-2"
 pub async fn insert_connections_game(db_handle: &PatDatabase, data: &CreateConnectionGameSchema, user_id: String) -> Result<ConnectionGame, DbError> {
-    let slug = name_to_slug(data.puzzle_name.as_str());
+    let base = puzzle_name_to_slug(data.puzzle_name.as_str())?;
     let date_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
 
+    for _ in 0..MAX_SLUG_ATTEMPTS {
+        let doc = doc! {
+            "connection_categories": [
+                data.connection_categories[0].to_doc(),
+                data.connection_categories[1].to_doc(),
+                data.connection_categories[2].to_doc(),
+                data.connection_categories[3].to_doc(),
+            ],
+            "puzzle_name": data.puzzle_name.clone(),
+            "slug": find_free_slug(db_handle, base.as_str()).await?,
+            "author_id": user_id.clone(),
+            "creation_datetime": date_time,
+            "draft": data.draft,
+            "tags": normalize_tags(&data.tags),
+        };
+        match db_handle.insert_and_retrieve_one(doc).await {
+            Err(DbError::AlreadyExists) => continue,
+            res => return res,
+        }
+    }
+    Err(DbError::UnhandledException(
+        "Failed to find a free slug for a connections game".to_owned(),
+    ))
+}
+
+// Slugs are unique, drafts are only found for their author
+pub async fn get_connection_game_by_slug(db_handle: &PatDatabase, slug: &str, user_id: &str) -> Result<ConnectionGame, DbError> {
     let doc = doc! {
-        "connection_categories": [
-            data.connection_categories[0].to_doc(),
-            data.connection_categories[1].to_doc(),
-            data.connection_categories[2].to_doc(),
-            data.connection_categories[3].to_doc(),
-        ],
-        "puzzle_name": data.puzzle_name.clone(),
-        "slug": slug.clone(),
-        "author_id": user_id,
-        "creation_datetime": date_time,
+        "slug": slug,
+        "$or": [{ "draft": { "$ne": true } }, { "author_id": user_id }],
     };
-    db_handle.insert_and_retrieve_one(doc).await
+    db_handle.find_one(doc).await
 }
 
-pub async fn get_connection_game_by_slug(db_handle: &PatDatabase, slug: &str) -> Result<ConnectionGame, DbError> {
-    let doc = doc! { "slug": slug };
+pub async fn get_connection_game_by_id(db_handle: &PatDatabase, id: &str) -> Result<ConnectionGame, DbError> {
+    let game_id = str_to_object_id(id)?;
+    let doc = doc! { "_id": Bson::ObjectId(game_id) };
     db_handle.find_one(doc).await
 }
 
+// A game keeps its slug unless its name changes enough to change the slug
+pub async fn update_connections_game(
+    db_handle: &PatDatabase,
+    game: &ConnectionGame,
+    data: &UpdateConnectionGameSchema,
+) -> Result<ConnectionGame, DbError> {
+    let base = puzzle_name_to_slug(data.puzzle_name.as_str())?;
+    let filter_doc = doc! { "_id": game.mongo_id()? };
+
+    for _ in 0..MAX_SLUG_ATTEMPTS {
+        let slug = match slug_base(game.slug.as_str()) == base {
+            true => game.slug.clone(),
+            false => find_free_slug(db_handle, base.as_str()).await?,
+        };
+        let update_doc = doc! {
+            "$set": {
+                "connection_categories": [
+                    data.connection_categories[0].to_doc(),
+                    data.connection_categories[1].to_doc(),
+                    data.connection_categories[2].to_doc(),
+                    data.connection_categories[3].to_doc(),
+                ],
+                "puzzle_name": data.puzzle_name.clone(),
+                "slug": slug,
+                "tags": normalize_tags(&data.tags),
+            }
+        };
+        match db_handle.find_and_update_one(filter_doc.clone(), update_doc).await {
+            Err(DbError::AlreadyExists) => continue,
+            res => return res,
+        }
+    }
+    Err(DbError::UnhandledException(
+        "Failed to find a free slug for a connections game".to_owned(),
+    ))
+}
+
+pub async fn publish_connections_game(db_handle: &PatDatabase, game: &ConnectionGame) -> Result<ConnectionGame, DbError> {
+    let filter_doc = doc! { "_id": game.mongo_id()? };
+    let update_doc = doc! { "$set": { "draft": false } };
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+// Deletes a game along with everyone's sessions for it and ratings of it, and any days it was
+// scheduled for
+pub async fn delete_connections_game(db_handle: &PatDatabase, game: &ConnectionGame) -> Result<(), DbError> {
+    db_handle.delete_one::<ConnectionGame>(doc! { "_id": game.mongo_id()? }).await?;
+    db_handle
+        .delete_many::<ConnectionGameSession>(doc! { "game_id": game.id.as_str() })
+        .await?;
+    delete_ratings_for_game(db_handle, game.id.as_str()).await?;
+    delete_daily_puzzles_for_game(db_handle, game.id.as_str()).await?;
+    Ok(())
+}
+
 pub async fn get_all_connections_games(db_handle: &PatDatabase, user_id: &str, this_users_games: bool) -> Result<Vec<ConnectionGame>, DbError> {
     let doc = match this_users_games {
         true => doc! { "author_id": user_id },
-        false => doc! { "author_id": { "$ne": user_id } },
+        false => doc! { "author_id": { "$ne": user_id }, "draft": { "$ne": true } },
     };
 
     db_handle.find(doc).await
 }
+
+// One page of the games matching `filter_doc`, with their ratings and whether the user has started
+// them. Games store their IDs as ObjectIds but sessions and ratings store them as hex strings
+pub async fn list_connections_games(
+    db_handle: &PatDatabase,
+    user_id: &str,
+    filter_doc: Document,
+    unplayed_only: bool,
+    sort: ConnectionsGameSort,
+    skip: i64,
+    limit: i64,
+) -> Result<Vec<ListedConnectionsGame>, DbError> {
+    let mut pipeline = vec![
+        doc! { "$match": filter_doc },
+        doc! { "$lookup": {
+            "from": ConnectionGameRating::collection_name(),
+            "let": { "game_id": { "$toString": "$_id" } },
+            "pipeline": [
+                { "$match": { "$expr": { "$eq": ["$game_id", "$$game_id"] } } },
+                { "$group": {
+                    "_id": null,
+                    "average_rating": { "$avg": "$rating" },
+                    "rating_count": { "$sum": 1 },
+                    "easy": { "$sum": count_difficulty(DifficultyVote::Easy) },
+                    "medium": { "$sum": count_difficulty(DifficultyVote::Medium) },
+                    "hard": { "$sum": count_difficulty(DifficultyVote::Hard) },
+                } },
+            ],
+            "as": "ratings",
+        } },
+        doc! { "$lookup": {
+            "from": ConnectionGameSession::collection_name(),
+            "let": { "game_id": { "$toString": "$_id" } },
+            "pipeline": [
+                { "$match": { "$expr": { "$and": [{ "$eq": ["$game_id", "$$game_id"] }, { "$eq": ["$user_id", user_id] }] } } },
+                { "$project": { "_id": 1 } },
+            ],
+            "as": "my_sessions",
+        } },
+        // An unrated game has no ratings group, and so no average_rating
+        doc! { "$set": {
+            "ratings": { "$arrayElemAt": ["$ratings", 0] },
+            "played": { "$gt": [{ "$size": "$my_sessions" }, 0] },
+        } },
+        doc! { "$set": {
+            "average_rating": "$ratings.average_rating",
+            "rating_count": { "$ifNull": ["$ratings.rating_count", 0] },
+            "difficulty_votes": {
+                "easy": { "$ifNull": ["$ratings.easy", 0] },
+                "medium": { "$ifNull": ["$ratings.medium", 0] },
+                "hard": { "$ifNull": ["$ratings.hard", 0] },
+            },
+        } },
+    ];
+    if unplayed_only {
+        pipeline.push(doc! { "$match": { "played": false } });
+    }
+    // _id comes last so that pages are stable when everything else is tied
+    let sort_doc = match sort {
+        ConnectionsGameSort::Newest => doc! { "creation_datetime": -1, "_id": -1 },
+        ConnectionsGameSort::TopRated => doc! { "average_rating": -1, "rating_count": -1, "creation_datetime": -1, "_id": -1 },
+    };
+    pipeline.extend([
+        doc! { "$sort": sort_doc },
+        doc! { "$skip": skip },
+        doc! { "$limit": limit },
+        doc! { "$project": {
+            "_id": 0,
+            "id": { "$toString": "$_id" },
+            "puzzle_name": 1,
+            "slug": 1,
+            "author_id": 1,
+            "creation_datetime": 1,
+            "tags": { "$ifNull": ["$tags", []] },
+            "average_rating": 1,
+            "rating_count": 1,
+            "difficulty_votes": 1,
+            "played": 1,
+        } },
+    ]);
+    db_handle.aggregate::<ConnectionGame, ListedConnectionsGame>(pipeline).await
+}
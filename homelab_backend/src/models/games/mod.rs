@@ -1,10 +1,22 @@
+pub mod daily;
+pub mod daily_db;
 pub mod games_db;
+pub mod rating;
+pub mod rating_db;
+pub mod session;
+pub mod session_db;
+pub mod share;
+pub mod stats;
+pub mod stats_db;
 pub mod validation;
+pub mod words;
 
 use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 
 use crate::models::deserialize_id;
+use rating::DifficultyVotes;
+use session::SessionState;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ConnectionGame {
@@ -15,12 +27,34 @@ pub struct ConnectionGame {
     pub slug: String,
     pub author_id: String,
     pub creation_datetime: i64,
+    // Drafts can only be seen and played by their author. Puzzles made before drafts existed
+    // don't have this field and are published
+    #[serde(default)]
+    pub draft: bool,
+    // Lowercase, set by the author to help players find the puzzle
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+impl ConnectionGame {
+    // From 0 for the most straightforward category up to 3 for the trickiest. Categories without a
+    // difficulty are ranked by the order they were written in
+    pub fn category_difficulty(&self, category_name: &str) -> Option<i64> {
+        self.connection_categories
+            .iter()
+            .enumerate()
+            .find(|(_, category)| category.category_name == category_name)
+            .map(|(index, category)| category.difficulty.unwrap_or(index as i64))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ConnectionCategory {
     pub category_clues: [String; 4],
     pub category_name: String,
+    // Puzzles made before difficulties existed, or made without picking them, don't have one
+    #[serde(default)]
+    pub difficulty: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -68,6 +102,8 @@ pub struct MinimalConnectionsGame {
     pub slug: String,
     pub author_id: String,
     pub creation_datetime: i64,
+    pub draft: bool,
+    pub tags: Vec<String>,
 }
 
 impl From<ConnectionGame> for MinimalConnectionsGame {
@@ -78,12 +114,38 @@ impl From<ConnectionGame> for MinimalConnectionsGame {
             slug: value.slug,
             author_id: value.author_id,
             creation_datetime: value.creation_datetime,
+            draft: value.draft,
+            tags: value.tags,
         }
     }
 }
 
+// A published puzzle in the list of other authors' puzzles, with what players thought of it
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ListedConnectionsGame {
+    pub id: String,
+    pub puzzle_name: String,
+    pub slug: String,
+    pub author_id: String,
+    pub creation_datetime: i64,
+    pub tags: Vec<String>,
+    // None until someone has rated it
+    #[serde(default)]
+    pub average_rating: Option<f64>,
+    pub rating_count: i64,
+    pub difficulty_votes: DifficultyVotes,
+    // Whether the user listing puzzles has started this one
+    pub played: bool,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct TrySolveRow {
     pub row_name: Option<String>,
     pub correct_guess: bool,
+    // Three of the four clues belong to the same category
+    pub one_away: bool,
+    // The same clues were guessed before, this guess didn't cost a mistake
+    pub already_guessed: bool,
+    pub mistakes_remaining: i64,
+    pub state: SessionState,
 }
@@ -0,0 +1,44 @@
+use crate::models::deserialize_id;
+use mongodb::bson::Bson;
+use serde::{Deserialize, Serialize};
+
+pub const MIN_RATING: i64 = 1;
+pub const MAX_RATING: i64 = 5;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DifficultyVote {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl From<DifficultyVote> for Bson {
+    fn from(value: DifficultyVote) -> Self {
+        match value {
+            DifficultyVote::Easy => Bson::String("easy".to_owned()),
+            DifficultyVote::Medium => Bson::String("medium".to_owned()),
+            DifficultyVote::Hard => Bson::String("hard".to_owned()),
+        }
+    }
+}
+
+// A player's opinion of a puzzle they have finished. Rating again replaces the old one
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConnectionGameRating {
+    #[serde(rename = "_id", deserialize_with = "deserialize_id")]
+    pub id: String,
+    pub user_id: String,
+    pub game_id: String,
+    // From MIN_RATING to MAX_RATING stars
+    pub rating: i64,
+    pub difficulty: DifficultyVote,
+    pub rated_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DifficultyVotes {
+    pub easy: i64,
+    pub medium: i64,
+    pub hard: i64,
+}
@@ -0,0 +1,53 @@
+use super::{
+    rating::{ConnectionGameRating, DifficultyVote},
+    validation::RateConnectionGameSchema,
+};
+use crate::{
+    db::{MongoModel, PatDatabase},
+    error_handler::DbError,
+    util::current_unix_time,
+};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+
+impl MongoModel for ConnectionGameRating {
+    fn collection_name() -> &'static str {
+        "game_ratings"
+    }
+    fn model_name() -> &'static str {
+        "Game Rating"
+    }
+    fn mongo_id(&self) -> Result<ObjectId, DbError> {
+        match self.id.parse::<ObjectId>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(DbError::BadId),
+        }
+    }
+}
+
+// 1 for ratings with the given difficulty vote and 0 for the rest, for counting them with a $sum
+pub fn count_difficulty(difficulty: DifficultyVote) -> Document {
+    doc! { "$cond": [{ "$eq": ["$difficulty", difficulty] }, 1, 0] }
+}
+
+// Each user has one rating per game, rating again overwrites it
+pub async fn upsert_rating(
+    db_handle: &PatDatabase,
+    user_id: &str,
+    game_id: &str,
+    data: &RateConnectionGameSchema,
+) -> Result<ConnectionGameRating, DbError> {
+    let filter_doc = doc! { "user_id": user_id, "game_id": game_id };
+    let update_doc = doc! {
+        "$set": {
+            "rating": data.rating,
+            "difficulty": data.difficulty,
+            "rated_at": current_unix_time(),
+        }
+    };
+    db_handle.upsert_one(filter_doc, update_doc).await
+}
+
+pub async fn delete_ratings_for_game(db_handle: &PatDatabase, game_id: &str) -> Result<u64, DbError> {
+    let doc = doc! { "game_id": game_id };
+    db_handle.delete_many::<ConnectionGameRating>(doc).await
+}
@@ -0,0 +1,154 @@
+use super::{ConnectionCategory, ConnectionGame};
+use crate::models::deserialize_id;
+use mongodb::bson::{doc, Bson, Document};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+// Same as the NYT game, a fourth wrong guess ends the game
+pub const STARTING_MISTAKES: i64 = 4;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    InProgress,
+    Won,
+    Lost,
+}
+
+impl From<SessionState> for Bson {
+    fn from(value: SessionState) -> Self {
+        match value {
+            SessionState::InProgress => Bson::String("in_progress".to_owned()),
+            SessionState::Won => Bson::String("won".to_owned()),
+            SessionState::Lost => Bson::String("lost".to_owned()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SessionGuess {
+    pub clues: [String; 4],
+    // The category this guess solved, None for a wrong guess
+    pub category_name: Option<String>,
+    pub guessed_at: i64,
+}
+
+impl SessionGuess {
+    pub fn to_doc(&self) -> Document {
+        doc! {
+            "clues": self.clues.to_vec(),
+            "category_name": self.category_name.clone(),
+            "guessed_at": self.guessed_at,
+        }
+    }
+}
+
+// One user's progress through one connections game. There is only ever one session per user and
+// game, so coming back to a game picks up where it was left
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConnectionGameSession {
+    #[serde(rename = "_id", deserialize_with = "deserialize_id")]
+    pub id: String,
+    pub user_id: String,
+    pub game_id: String,
+    // Category names, in the order they were solved
+    pub solved_categories: Vec<String>,
+    pub guesses: Vec<SessionGuess>,
+    pub mistakes_remaining: i64,
+    pub state: SessionState,
+    pub started_at: i64,
+    pub updated_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+impl ConnectionGameSession {
+    pub fn is_over(&self) -> bool {
+        self.state != SessionState::InProgress
+    }
+
+    // Guesses are the same no matter what order their clues were picked in
+    pub fn has_guessed(&self, clues: &[String; 4]) -> bool {
+        let mut sorted_clues = clues.clone();
+        sorted_clues.sort();
+        self.guesses.iter().any(|guess| {
+            let mut sorted_guess = guess.clues.clone();
+            sorted_guess.sort();
+            sorted_guess == sorted_clues
+        })
+    }
+
+    // Adds a guess to the session, costing a mistake if it didn't solve a category
+    pub fn apply_guess(&mut self, clues: [String; 4], category_name: Option<String>, guessed_at: i64) {
+        match &category_name {
+            Some(name) => self.solved_categories.push(name.clone()),
+            None => self.mistakes_remaining -= 1,
+        }
+        self.guesses.push(SessionGuess {
+            clues,
+            category_name,
+            guessed_at,
+        });
+        self.updated_at = guessed_at;
+        if self.solved_categories.len() == 4 {
+            self.state = SessionState::Won;
+        } else if self.mistakes_remaining <= 0 {
+            self.state = SessionState::Lost;
+        }
+        if self.is_over() {
+            self.finished_at = Some(guessed_at);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReturnConnectionGameSession {
+    pub game_id: String,
+    pub slug: String,
+    pub puzzle_name: String,
+    // Every solved category with its clues, in the order they were solved
+    pub solved_categories: Vec<ConnectionCategory>,
+    // Clues which haven't been solved yet, scrambled
+    pub remaining_clues: Vec<String>,
+    pub guesses: Vec<SessionGuess>,
+    pub mistakes_remaining: i64,
+    pub state: SessionState,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    // Only given out once the game is over
+    pub answers: Option<[ConnectionCategory; 4]>,
+}
+
+impl ReturnConnectionGameSession {
+    pub fn new(session: ConnectionGameSession, game: ConnectionGame) -> Self {
+        let solved_categories: Vec<ConnectionCategory> = session
+            .solved_categories
+            .iter()
+            .filter_map(|name| game.connection_categories.iter().find(|category| &category.category_name == name))
+            .cloned()
+            .collect();
+        let mut remaining_clues: Vec<String> = game
+            .connection_categories
+            .iter()
+            .filter(|category| !session.solved_categories.contains(&category.category_name))
+            .flat_map(|category| category.category_clues.clone())
+            .collect();
+        remaining_clues.shuffle(&mut rand::rng());
+        let answers = match session.is_over() {
+            true => Some(game.connection_categories),
+            false => None,
+        };
+        Self {
+            game_id: game.id,
+            slug: game.slug,
+            puzzle_name: game.puzzle_name,
+            solved_categories,
+            remaining_clues,
+            guesses: session.guesses,
+            mistakes_remaining: session.mistakes_remaining,
+            state: session.state,
+            started_at: session.started_at,
+            finished_at: session.finished_at,
+            answers,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use super::session::{ConnectionGameSession, SessionState, STARTING_MISTAKES};
+use crate::{
+    db::{MongoModel, PatDatabase},
+    error_handler::DbError,
+    util::current_unix_time,
+};
+use mongodb::bson::{doc, oid::ObjectId};
+
+impl MongoModel for ConnectionGameSession {
+    fn collection_name() -> &'static str {
+        "game_sessions"
+    }
+    fn model_name() -> &'static str {
+        "Game Session"
+    }
+    fn mongo_id(&self) -> Result<ObjectId, DbError> {
+        match self.id.parse::<ObjectId>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(DbError::BadId),
+        }
+    }
+}
+
+// Gets the user's session for a game, starting a new one if they haven't played it before
+pub async fn start_or_resume_session(db_handle: &PatDatabase, user_id: &str, game_id: &str) -> Result<ConnectionGameSession, DbError> {
+    let current_time = current_unix_time();
+    let filter_doc = doc! { "user_id": user_id, "game_id": game_id };
+    let update_doc = doc! {
+        "$setOnInsert": {
+            "solved_categories": [],
+            "guesses": [],
+            "mistakes_remaining": STARTING_MISTAKES,
+            "state": SessionState::InProgress,
+            "started_at": current_time,
+            "updated_at": current_time,
+            "finished_at": null,
+        }
+    };
+    db_handle.upsert_one(filter_doc, update_doc).await
+}
+
+pub async fn get_session(db_handle: &PatDatabase, user_id: &str, game_id: &str) -> Result<ConnectionGameSession, DbError> {
+    let doc = doc! { "user_id": user_id, "game_id": game_id };
+    db_handle.find_one(doc).await
+}
+
+// Saves a session after apply_guess. The update only applies if the session still has the guesses
+// it had when it was loaded, so two guesses made at the same time can't both spend the same
+// mistake. Fails with a NotFound if another guess got there first
+pub async fn save_session_guess(db_handle: &PatDatabase, session: &ConnectionGameSession) -> Result<ConnectionGameSession, DbError> {
+    let guess = match session.guesses.last() {
+        Some(guess) => guess,
+        None => return Err(DbError::UnhandledException("Tried to save a session without a new guess".to_owned())),
+    };
+    let previous_guess_count = (session.guesses.len() - 1) as i64;
+    let filter_doc = doc! {
+        "_id": session.mongo_id()?,
+        "state": SessionState::InProgress,
+        "guesses": { "$size": previous_guess_count },
+    };
+    let update_doc = doc! {
+        "$set": {
+            "solved_categories": session.solved_categories.clone(),
+            "mistakes_remaining": session.mistakes_remaining,
+            "state": session.state,
+            "updated_at": session.updated_at,
+            "finished_at": session.finished_at,
+        },
+        "$push": { "guesses": guess.to_doc() },
+    };
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
+
+// Whether anyone other than the author has played a game. The author's own sessions don't count,
+// they are thrown away when the game is edited
+pub async fn game_has_player_sessions(db_handle: &PatDatabase, game_id: &str, author_id: &str) -> Result<bool, DbError> {
+    let doc = doc! { "game_id": game_id, "user_id": { "$ne": author_id } };
+    match db_handle.find_one::<ConnectionGameSession>(doc).await {
+        Ok(_) => Ok(true),
+        Err(DbError::NotFound(_)) => Ok(false),
+        Err(db_err) => Err(db_err),
+    }
+}
+
+pub async fn delete_sessions_for_user(db_handle: &PatDatabase, game_id: &str, user_id: &str) -> Result<u64, DbError> {
+    let doc = doc! { "game_id": game_id, "user_id": user_id };
+    db_handle.delete_many::<ConnectionGameSession>(doc).await
+}
@@ -0,0 +1,46 @@
+use super::{session::ConnectionGameSession, ConnectionGame};
+use serde::{Deserialize, Serialize};
+
+// Indexed by category difficulty, the same colors as the NYT game
+const DIFFICULTY_SQUARES: [&str; 4] = ["🟨", "🟩", "🟦", "🟪"];
+// For a clue which isn't in the puzzle anymore
+const UNKNOWN_SQUARE: &str = "⬜";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShareableResult {
+    pub text: String,
+}
+
+fn clue_square(game: &ConnectionGame, clue: &str) -> &'static str {
+    game.connection_categories
+        .iter()
+        .find(|category| category.category_clues.iter().any(|category_clue| category_clue == clue))
+        .and_then(|category| game.category_difficulty(category.category_name.as_str()))
+        .and_then(|difficulty| usize::try_from(difficulty).ok())
+        .and_then(|difficulty| DIFFICULTY_SQUARES.get(difficulty).copied())
+        .unwrap_or(UNKNOWN_SQUARE)
+}
+
+fn mistakes_summary(mistakes: usize) -> String {
+    match mistakes {
+        0 => "no mistakes".to_owned(),
+        1 => "1 mistake".to_owned(),
+        _ => format!("{mistakes} mistakes"),
+    }
+}
+
+// A result which can be posted without giving away any clues or categories, one row of colored
+// squares per guess showing which categories its clues belonged to
+pub fn result_grid(session: &ConnectionGameSession, game: &ConnectionGame) -> String {
+    let mistakes = session.guesses.iter().filter(|guess| guess.category_name.is_none()).count();
+    let summary = match session.solved_categories.len() {
+        4 => format!("Solved with {}", mistakes_summary(mistakes)),
+        solved => format!("Solved {solved} of 4 categories"),
+    };
+    let rows: Vec<String> = session
+        .guesses
+        .iter()
+        .map(|guess| guess.clues.iter().map(|clue| clue_square(game, clue)).collect())
+        .collect();
+    format!("Connections: {}\n{summary}\n{}", game.puzzle_name, rows.join("\n"))
+}
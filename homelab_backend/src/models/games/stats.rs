@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+// One won session on a puzzle's leaderboard. Fewer mistakes ranks higher, then a faster solve
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    pub user_id: String,
+    // None when the player has deleted their account
+    #[serde(default)]
+    pub username: Option<String>,
+    pub mistakes: i64,
+    // Seconds between starting the session and solving the last category
+    pub solve_seconds: i64,
+    pub finished_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlayerStats {
+    // Every game started, including ones still in progress
+    pub played: i64,
+    pub won: i64,
+    pub lost: i64,
+    pub current_win_streak: i64,
+    pub max_win_streak: i64,
+    // Over finished games, None until a game has been finished
+    pub average_mistakes: Option<f64>,
+    // How many games were won with 0, 1, 2 and 3 mistakes, a won game always takes four correct
+    // guesses plus its mistakes
+    pub guess_distribution: [i64; 4],
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuthoredPuzzleStats {
+    pub game_id: String,
+    pub puzzle_name: String,
+    pub slug: String,
+    // Sessions started by players other than the author
+    pub plays: i64,
+    pub finished: i64,
+    pub won: i64,
+    // won / finished, None until someone has finished the puzzle
+    pub solve_rate: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuthorStats {
+    pub total_plays: i64,
+    pub total_finished: i64,
+    pub total_won: i64,
+    pub solve_rate: Option<f64>,
+    pub puzzles: Vec<AuthoredPuzzleStats>,
+}
+
+pub fn solve_rate(won: i64, finished: i64) -> Option<f64> {
+    match finished {
+        0 => None,
+        _ => Some(won as f64 / finished as f64),
+    }
+}
+
+// The win streak still going at the end of `results` and the longest one, results are true for a
+// win and oldest first
+pub fn win_streaks(results: &[bool]) -> (i64, i64) {
+    let mut current = 0;
+    let mut longest = 0;
+    for won in results {
+        current = match won {
+            true => current + 1,
+            false => 0,
+        };
+        longest = longest.max(current);
+    }
+    (current, longest)
+}
@@ -0,0 +1,194 @@
+use super::{
+    games_db::get_all_connections_games,
+    session::{ConnectionGameSession, SessionState, STARTING_MISTAKES},
+    stats::{solve_rate, win_streaks, AuthorStats, AuthoredPuzzleStats, LeaderboardEntry, PlayerStats},
+    ConnectionGame,
+};
+use crate::{
+    db::{MongoModel, PatDatabase},
+    error_handler::DbError,
+    models::user::User,
+};
+use mongodb::bson::{doc, Document};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+pub const LEADERBOARD_SIZE: i64 = 25;
+
+// 1 for sessions in the given state and 0 for the rest, for counting them with a $sum
+fn count_state(state: SessionState) -> Document {
+    doc! { "$cond": [{ "$eq": ["$state", state] }, 1, 0] }
+}
+
+fn mistakes_made() -> Document {
+    doc! { "$subtract": [STARTING_MISTAKES, "$mistakes_remaining"] }
+}
+
+pub async fn get_leaderboard(db_handle: &PatDatabase, game: &ConnectionGame) -> Result<Vec<LeaderboardEntry>, DbError> {
+    let pipeline = vec![
+        // The author already knows the answers, so their own wins don't count
+        doc! { "$match": { "game_id": game.id.as_str(), "state": SessionState::Won, "user_id": { "$ne": game.author_id.as_str() } } },
+        doc! { "$project": {
+            "_id": 0,
+            "user_id": 1,
+            "finished_at": 1,
+            "mistakes": mistakes_made(),
+            "solve_seconds": { "$subtract": ["$finished_at", "$started_at"] },
+        } },
+        doc! { "$sort": { "mistakes": 1, "solve_seconds": 1, "finished_at": 1 } },
+        doc! { "$limit": LEADERBOARD_SIZE },
+        // User IDs are stored as hex strings on sessions
+        doc! { "$lookup": {
+            "from": User::collection_name(),
+            "let": { "user_id": { "$toObjectId": "$user_id" } },
+            "pipeline": [
+                { "$match": { "$expr": { "$eq": ["$_id", "$$user_id"] } } },
+                { "$project": { "username": 1 } },
+            ],
+            "as": "user",
+        } },
+        doc! { "$set": { "username": { "$arrayElemAt": ["$user.username", 0] } } },
+        doc! { "$unset": "user" },
+    ];
+    db_handle.aggregate::<ConnectionGameSession, LeaderboardEntry>(pipeline).await
+}
+
+#[derive(Deserialize)]
+struct PlayerTotals {
+    played: i64,
+    won: i64,
+    lost: i64,
+    average_mistakes: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct DistributionBucket {
+    #[serde(rename = "_id")]
+    mistakes: i64,
+    count: i64,
+}
+
+#[derive(Deserialize)]
+struct FinishedResult {
+    won: bool,
+}
+
+#[derive(Deserialize)]
+struct PlayerStatsFacets {
+    totals: Vec<PlayerTotals>,
+    distribution: Vec<DistributionBucket>,
+    results: Vec<FinishedResult>,
+}
+
+pub async fn get_player_stats(db_handle: &PatDatabase, user_id: &str) -> Result<PlayerStats, DbError> {
+    let finished = doc! { "$ne": ["$state", SessionState::InProgress] };
+    let pipeline = vec![
+        doc! { "$match": { "user_id": user_id } },
+        doc! { "$facet": {
+            "totals": [
+                { "$group": {
+                    "_id": null,
+                    "played": { "$sum": 1 },
+                    "won": { "$sum": count_state(SessionState::Won) },
+                    "lost": { "$sum": count_state(SessionState::Lost) },
+                    // $avg skips the nulls left by games still in progress
+                    "average_mistakes": { "$avg": { "$cond": [finished.clone(), mistakes_made(), null] } },
+                } },
+            ],
+            "distribution": [
+                { "$match": { "state": SessionState::Won } },
+                { "$group": { "_id": mistakes_made(), "count": { "$sum": 1 } } },
+            ],
+            "results": [
+                { "$match": { "$expr": finished } },
+                { "$sort": { "finished_at": 1 } },
+                { "$project": { "_id": 0, "won": { "$eq": ["$state", SessionState::Won] } } },
+            ],
+        } },
+    ];
+    let facets = match db_handle.aggregate::<ConnectionGameSession, PlayerStatsFacets>(pipeline).await?.pop() {
+        Some(facets) => facets,
+        None => return Err(DbError::UnhandledException("A $facet aggregation returned nothing".to_owned())),
+    };
+
+    let mut guess_distribution = [0; 4];
+    for bucket in facets.distribution {
+        if let Some(count) = usize::try_from(bucket.mistakes).ok().and_then(|index| guess_distribution.get_mut(index)) {
+            *count = bucket.count;
+        }
+    }
+    let results: Vec<bool> = facets.results.into_iter().map(|result| result.won).collect();
+    let (current_win_streak, max_win_streak) = win_streaks(results.as_slice());
+    // A user who hasn't played has no totals group at all
+    let (played, won, lost, average_mistakes) = match facets.totals.into_iter().next() {
+        Some(totals) => (totals.played, totals.won, totals.lost, totals.average_mistakes),
+        None => (0, 0, 0, None),
+    };
+    Ok(PlayerStats {
+        played,
+        won,
+        lost,
+        current_win_streak,
+        max_win_streak,
+        average_mistakes,
+        guess_distribution,
+    })
+}
+
+#[derive(Deserialize)]
+struct PuzzleTotals {
+    #[serde(rename = "_id")]
+    game_id: String,
+    plays: i64,
+    finished: i64,
+    won: i64,
+}
+
+// Stats for every puzzle the user has made, ignoring their own sessions
+pub async fn get_author_stats(db_handle: &PatDatabase, user_id: &str) -> Result<AuthorStats, DbError> {
+    let games = get_all_connections_games(db_handle, user_id, true).await?;
+    let game_ids: Vec<&str> = games.iter().map(|game| game.id.as_str()).collect();
+    let pipeline = vec![
+        doc! { "$match": { "game_id": { "$in": game_ids }, "user_id": { "$ne": user_id } } },
+        doc! { "$group": {
+            "_id": "$game_id",
+            "plays": { "$sum": 1 },
+            "finished": { "$sum": { "$cond": [{ "$ne": ["$state", SessionState::InProgress] }, 1, 0] } },
+            "won": { "$sum": count_state(SessionState::Won) },
+        } },
+    ];
+    let mut totals: HashMap<String, PuzzleTotals> = db_handle
+        .aggregate::<ConnectionGameSession, PuzzleTotals>(pipeline)
+        .await?
+        .into_iter()
+        .map(|totals| (totals.game_id.clone(), totals))
+        .collect();
+
+    let puzzles: Vec<AuthoredPuzzleStats> = games
+        .into_iter()
+        .map(|game| {
+            let (plays, finished, won) = match totals.remove(&game.id) {
+                Some(totals) => (totals.plays, totals.finished, totals.won),
+                None => (0, 0, 0),
+            };
+            AuthoredPuzzleStats {
+                game_id: game.id,
+                puzzle_name: game.puzzle_name,
+                slug: game.slug,
+                plays,
+                finished,
+                won,
+                solve_rate: solve_rate(won, finished),
+            }
+        })
+        .collect();
+    let total_finished = puzzles.iter().map(|puzzle| puzzle.finished).sum();
+    let total_won = puzzles.iter().map(|puzzle| puzzle.won).sum();
+    Ok(AuthorStats {
+        total_plays: puzzles.iter().map(|puzzle| puzzle.plays).sum(),
+        total_finished,
+        total_won,
+        solve_rate: solve_rate(total_won, total_finished),
+        puzzles,
+    })
+}
@@ -1,16 +1,222 @@
+use super::rating::{DifficultyVote, MAX_RATING, MIN_RATING};
+use crate::{api::return_data::FieldError, models::name_to_slug};
 use mongodb::bson::{doc, Document};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Counted in characters rather than bytes
+pub const MAX_PUZZLE_NAME_LENGTH: usize = 100;
+pub const MAX_CATEGORY_NAME_LENGTH: usize = 100;
+pub const MAX_CLUE_LENGTH: usize = 50;
+pub const MAX_TAGS: usize = 5;
+pub const MAX_TAG_LENGTH: usize = 20;
+pub const MAX_CATEGORY_DIFFICULTY: i64 = 3;
+
+fn check_text(errors: &mut Vec<FieldError>, field: String, value: &str, max_length: usize) {
+    if value.trim().is_empty() {
+        errors.push(FieldError::new(field, "Can't be blank"));
+    } else if value.chars().count() > max_length {
+        errors.push(FieldError {
+            field,
+            msg: format!("Can't be longer than {max_length} characters"),
+        });
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CreateConnectionGameSchema {
     pub connection_categories: [CreateConnectionCategorySchema; 4],
     pub puzzle_name: String,
+    // Drafts are hidden from other users until they are published
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// Replaces a puzzle's name, categories and tags. Publishing is done separately
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateConnectionGameSchema {
+    pub connection_categories: [CreateConnectionCategorySchema; 4],
+    pub puzzle_name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl CreateConnectionGameSchema {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        validate_puzzle(self.puzzle_name.as_str(), &self.connection_categories, &self.tags)
+    }
+}
+
+impl UpdateConnectionGameSchema {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        validate_puzzle(self.puzzle_name.as_str(), &self.connection_categories, &self.tags)
+    }
+}
+
+// Tags are stored and searched for lowercase, without surrounding whitespace
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+// Every problem with a puzzle, rather than just the first one, so they can all be fixed at once
+fn validate_puzzle(puzzle_name: &str, connection_categories: &[CreateConnectionCategorySchema; 4], tags: &[String]) -> Result<(), Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    check_text(&mut errors, "puzzle_name".to_owned(), puzzle_name, MAX_PUZZLE_NAME_LENGTH);
+    if !puzzle_name.trim().is_empty() && name_to_slug(puzzle_name).is_none() {
+        errors.push(FieldError::new("puzzle_name".to_owned(), "Must contain at least one letter"));
+    }
+
+    // Clues and category names are compared ignoring case and surrounding whitespace, each one
+    // maps to the field it was first seen in
+    let mut seen_category_names: HashMap<String, String> = HashMap::new();
+    let mut seen_clues: HashMap<String, String> = HashMap::new();
+    for (category_index, category) in connection_categories.iter().enumerate() {
+        let name_field = format!("connection_categories[{category_index}].category_name");
+        check_text(&mut errors, name_field.clone(), category.category_name.as_str(), MAX_CATEGORY_NAME_LENGTH);
+        let normalized_name = category.category_name.trim().to_lowercase();
+        if !normalized_name.is_empty() {
+            match seen_category_names.get(&normalized_name) {
+                Some(first_field) => errors.push(FieldError {
+                    field: name_field.clone(),
+                    msg: format!("Has the same name as {first_field}"),
+                }),
+                None => {
+                    seen_category_names.insert(normalized_name, name_field);
+                }
+            }
+        }
+
+        for (clue_index, clue) in category.category_clues.iter().enumerate() {
+            let clue_field = format!("connection_categories[{category_index}].category_clues[{clue_index}]");
+            check_text(&mut errors, clue_field.clone(), clue.as_str(), MAX_CLUE_LENGTH);
+            let normalized_clue = clue.trim().to_lowercase();
+            if normalized_clue.is_empty() {
+                continue;
+            }
+            match seen_clues.get(&normalized_clue) {
+                Some(first_field) => errors.push(FieldError {
+                    field: clue_field,
+                    msg: format!("Is the same clue as {first_field}"),
+                }),
+                None => {
+                    seen_clues.insert(normalized_clue, clue_field);
+                }
+            }
+        }
+    }
+
+    // Difficulties are optional, but a puzzle can't have some of them
+    let difficulty_count = connection_categories.iter().filter(|category| category.difficulty.is_some()).count();
+    let mut seen_difficulties: HashMap<i64, String> = HashMap::new();
+    for (category_index, category) in connection_categories.iter().enumerate() {
+        let difficulty_field = format!("connection_categories[{category_index}].difficulty");
+        let difficulty = match category.difficulty {
+            Some(difficulty) => difficulty,
+            None if difficulty_count > 0 => {
+                errors.push(FieldError::new(difficulty_field, "Needs a difficulty, since another category has one"));
+                continue;
+            }
+            None => continue,
+        };
+        if !(0..=MAX_CATEGORY_DIFFICULTY).contains(&difficulty) {
+            errors.push(FieldError {
+                field: difficulty_field,
+                msg: format!("Must be from 0 to {MAX_CATEGORY_DIFFICULTY}"),
+            });
+            continue;
+        }
+        match seen_difficulties.get(&difficulty) {
+            Some(first_field) => errors.push(FieldError {
+                field: difficulty_field,
+                msg: format!("Is the same difficulty as {first_field}"),
+            }),
+            None => {
+                seen_difficulties.insert(difficulty, difficulty_field);
+            }
+        }
+    }
+
+    if tags.len() > MAX_TAGS {
+        errors.push(FieldError {
+            field: "tags".to_owned(),
+            msg: format!("Can't have more than {MAX_TAGS} tags"),
+        });
+    }
+    let mut seen_tags: HashMap<String, String> = HashMap::new();
+    for (tag_index, tag) in tags.iter().enumerate() {
+        let tag_field = format!("tags[{tag_index}]");
+        check_text(&mut errors, tag_field.clone(), tag.as_str(), MAX_TAG_LENGTH);
+        let normalized_tag = normalize_tag(tag);
+        if normalized_tag.is_empty() {
+            continue;
+        }
+        match seen_tags.get(&normalized_tag) {
+            Some(first_field) => errors.push(FieldError {
+                field: tag_field,
+                msg: format!("Is the same tag as {first_field}"),
+            }),
+            None => {
+                seen_tags.insert(normalized_tag, tag_field);
+            }
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RateConnectionGameSchema {
+    pub rating: i64,
+    pub difficulty: DifficultyVote,
+}
+
+impl RateConnectionGameSchema {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        match (MIN_RATING..=MAX_RATING).contains(&self.rating) {
+            true => Ok(()),
+            false => Err(vec![FieldError {
+                field: "rating".to_owned(),
+                msg: format!("Must be from {MIN_RATING} to {MAX_RATING}"),
+            }]),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionsGameSort {
+    #[default]
+    Newest,
+    // Highest average rating first, unrated puzzles last
+    TopRated,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShareResultSchema {
+    pub channel_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScheduleDailyPuzzleSchema {
+    // "YYYY-MM-DD"
+    pub date: String,
+    pub game_id: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CreateConnectionCategorySchema {
     pub category_clues: [String; 4],
     pub category_name: String,
+    // From 0 for the most straightforward category up to 3 for the trickiest, either every category
+    // has one or none do
+    #[serde(default)]
+    pub difficulty: Option<i64>,
 }
 
 impl CreateConnectionCategorySchema {
@@ -22,7 +228,8 @@ impl CreateConnectionCategorySchema {
                 self.category_clues[2].to_owned(),
                 self.category_clues[3].to_owned(),
             ],
-            "category_name": self.category_name.to_string()
+            "category_name": self.category_name.to_string(),
+            "difficulty": self.difficulty,
         }
     }
 }
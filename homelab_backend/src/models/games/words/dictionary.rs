@@ -0,0 +1,27 @@
+use rand::seq::IndexedRandom;
+use std::sync::LazyLock;
+
+// Both lists are lowercase, one word per line and sorted. Answers are drawn from the short list of
+// common words, guesses can be any word in the much longer list, which includes every answer
+const ANSWER_LIST: &str = include_str!("answers.txt");
+const GUESS_LIST: &str = include_str!("guesses.txt");
+
+static ANSWERS: LazyLock<Vec<&'static str>> = LazyLock::new(|| read_word_list(ANSWER_LIST));
+static GUESSES: LazyLock<Vec<&'static str>> = LazyLock::new(|| read_word_list(GUESS_LIST));
+
+fn read_word_list(word_list: &'static str) -> Vec<&'static str> {
+    word_list.lines().map(str::trim).filter(|word| !word.is_empty()).collect()
+}
+
+// Words are stored and compared lowercase, without surrounding whitespace
+pub fn normalize_word(word: &str) -> String {
+    word.trim().to_lowercase()
+}
+
+pub fn is_valid_word(word: &str) -> bool {
+    GUESSES.binary_search(&word).is_ok()
+}
+
+pub fn random_answer() -> &'static str {
+    ANSWERS.choose(&mut rand::rng()).expect("The bundled answer list is empty")
+}
@@ -0,0 +1,112 @@
+pub mod dictionary;
+pub mod session;
+pub mod session_db;
+pub mod stats;
+pub mod stats_db;
+pub mod validation;
+pub mod words_db;
+
+use mongodb::bson::Bson;
+use serde::{Deserialize, Serialize};
+
+use super::session::SessionState;
+use crate::models::deserialize_id;
+
+pub const WORD_LENGTH: usize = 5;
+pub const MAX_GUESSES: usize = 6;
+
+// A word guessing game. Authors either pick the answer themselves or have one drawn from the word
+// list, which they can then play like anyone else
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WordGame {
+    #[serde(rename = "_id", deserialize_with = "deserialize_id")]
+    pub id: String,
+    // Lowercase, always in the word list
+    pub answer: String,
+    pub author_id: String,
+    pub creation_datetime: i64,
+    pub from_dictionary: bool,
+}
+
+impl WordGame {
+    // An author already knows an answer they picked, so they can only play games drawn for them
+    pub fn is_playable_by(&self, user_id: &str) -> bool {
+        self.from_dictionary || self.author_id != user_id
+    }
+}
+
+// A word game without its answer, which is all anyone gets until they finish it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MinimalWordGame {
+    pub id: String,
+    pub author_id: String,
+    pub creation_datetime: i64,
+    pub from_dictionary: bool,
+}
+
+impl From<WordGame> for MinimalWordGame {
+    fn from(value: WordGame) -> Self {
+        Self {
+            id: value.id,
+            author_id: value.author_id,
+            creation_datetime: value.creation_datetime,
+            from_dictionary: value.from_dictionary,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LetterResult {
+    // Green, the right letter in the right place
+    Correct,
+    // Yellow, the letter is somewhere else in the answer
+    Present,
+    // Grey, the letter isn't in the answer, or every copy of it is already accounted for
+    Absent,
+}
+
+impl From<LetterResult> for Bson {
+    fn from(value: LetterResult) -> Self {
+        match value {
+            LetterResult::Correct => Bson::String("correct".to_owned()),
+            LetterResult::Present => Bson::String("present".to_owned()),
+            LetterResult::Absent => Bson::String("absent".to_owned()),
+        }
+    }
+}
+
+// Scores each letter of a guess. Exact matches are found first, then the answer's leftover letters
+// are handed out left to right, so a letter guessed twice is only yellow as many times as it is
+// left over in the answer
+pub fn evaluate_guess(answer: &str, guess: &str) -> Vec<LetterResult> {
+    let answer: Vec<char> = answer.chars().collect();
+    let guess: Vec<char> = guess.chars().collect();
+    let mut results = vec![LetterResult::Absent; guess.len()];
+    let mut leftover: Vec<char> = Vec::new();
+    for (index, answer_letter) in answer.iter().enumerate() {
+        match guess.get(index) {
+            Some(guess_letter) if guess_letter == answer_letter => results[index] = LetterResult::Correct,
+            _ => leftover.push(*answer_letter),
+        }
+    }
+    for (index, guess_letter) in guess.iter().enumerate() {
+        if results[index] == LetterResult::Correct {
+            continue;
+        }
+        if let Some(position) = leftover.iter().position(|letter| letter == guess_letter) {
+            leftover.swap_remove(position);
+            results[index] = LetterResult::Present;
+        }
+    }
+    results
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WordGuessResult {
+    pub letters: Vec<LetterResult>,
+    pub guesses_remaining: i64,
+    pub state: SessionState,
+    // Only given out once the game is over
+    pub answer: Option<String>,
+}
@@ -0,0 +1,91 @@
+use super::{LetterResult, WordGame, MAX_GUESSES};
+use crate::models::{deserialize_id, games::session::SessionState};
+use mongodb::bson::{doc, Document};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WordGuess {
+    pub word: String,
+    pub letters: Vec<LetterResult>,
+    pub guessed_at: i64,
+}
+
+impl WordGuess {
+    pub fn to_doc(&self) -> Document {
+        doc! {
+            "word": self.word.as_str(),
+            "letters": self.letters.clone(),
+            "guessed_at": self.guessed_at,
+        }
+    }
+}
+
+// One user's progress through one word game, there is only ever one per user and game
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WordGameSession {
+    #[serde(rename = "_id", deserialize_with = "deserialize_id")]
+    pub id: String,
+    pub user_id: String,
+    pub game_id: String,
+    pub guesses: Vec<WordGuess>,
+    pub state: SessionState,
+    pub started_at: i64,
+    pub updated_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+impl WordGameSession {
+    pub fn is_over(&self) -> bool {
+        self.state != SessionState::InProgress
+    }
+
+    pub fn guesses_remaining(&self) -> i64 {
+        MAX_GUESSES.saturating_sub(self.guesses.len()) as i64
+    }
+
+    // Adds an evaluated guess, the game is won when every letter is correct and lost when the
+    // last guess isn't
+    pub fn apply_guess(&mut self, word: String, letters: Vec<LetterResult>, guessed_at: i64) {
+        let solved = letters.iter().all(|letter| *letter == LetterResult::Correct);
+        self.guesses.push(WordGuess { word, letters, guessed_at });
+        self.updated_at = guessed_at;
+        if solved {
+            self.state = SessionState::Won;
+        } else if self.guesses.len() >= MAX_GUESSES {
+            self.state = SessionState::Lost;
+        }
+        if self.is_over() {
+            self.finished_at = Some(guessed_at);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReturnWordGameSession {
+    pub game_id: String,
+    pub guesses: Vec<WordGuess>,
+    pub guesses_remaining: i64,
+    pub state: SessionState,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    // Only given out once the game is over
+    pub answer: Option<String>,
+}
+
+impl ReturnWordGameSession {
+    pub fn new(session: WordGameSession, game: WordGame) -> Self {
+        let answer = match session.is_over() {
+            true => Some(game.answer),
+            false => None,
+        };
+        Self {
+            game_id: game.id,
+            guesses_remaining: session.guesses_remaining(),
+            guesses: session.guesses,
+            state: session.state,
+            started_at: session.started_at,
+            finished_at: session.finished_at,
+            answer,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use super::session::WordGameSession;
+use crate::{
+    db::{MongoModel, PatDatabase},
+    error_handler::DbError,
+    models::games::session::SessionState,
+    util::current_unix_time,
+};
+use mongodb::bson::{doc, oid::ObjectId};
+
+impl MongoModel for WordGameSession {
+    fn collection_name() -> &'static str {
+        "word_game_sessions"
+    }
+    fn model_name() -> &'static str {
+        "Word Game Session"
+    }
+    fn mongo_id(&self) -> Result<ObjectId, DbError> {
+        match self.id.parse::<ObjectId>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(DbError::BadId),
+        }
+    }
+}
+
+// Gets the user's session for a game, starting a new one if they haven't played it before
+pub async fn start_or_resume_word_session(db_handle: &PatDatabase, user_id: &str, game_id: &str) -> Result<WordGameSession, DbError> {
+    let current_time = current_unix_time();
+    let filter_doc = doc! { "user_id": user_id, "game_id": game_id };
+    let update_doc = doc! {
+        "$setOnInsert": {
+            "guesses": [],
+            "state": SessionState::InProgress,
+            "started_at": current_time,
+            "updated_at": current_time,
+            "finished_at": null,
+        }
+    };
+    db_handle.upsert_one(filter_doc, update_doc).await
+}
+
+// Saves a session after apply_guess, only if nobody else has guessed since it was loaded. Fails
+// with a NotFound if another guess got there first
+pub async fn save_word_session_guess(db_handle: &PatDatabase, session: &WordGameSession) -> Result<WordGameSession, DbError> {
+    let guess = match session.guesses.last() {
+        Some(guess) => guess,
+        None => return Err(DbError::UnhandledException("Tried to save a session without a new guess".to_owned())),
+    };
+    let previous_guess_count = (session.guesses.len() - 1) as i64;
+    let filter_doc = doc! {
+        "_id": session.mongo_id()?,
+        "state": SessionState::InProgress,
+        "guesses": { "$size": previous_guess_count },
+    };
+    let update_doc = doc! {
+        "$set": {
+            "state": session.state,
+            "updated_at": session.updated_at,
+            "finished_at": session.finished_at,
+        },
+        "$push": { "guesses": guess.to_doc() },
+    };
+    db_handle.find_and_update_one(filter_doc, update_doc).await
+}
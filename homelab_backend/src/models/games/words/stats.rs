@@ -0,0 +1,14 @@
+use super::MAX_GUESSES;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WordPlayerStats {
+    // Every game started, including ones still in progress
+    pub played: i64,
+    pub won: i64,
+    pub lost: i64,
+    pub current_win_streak: i64,
+    pub max_win_streak: i64,
+    // How many games were won in 1 through 6 guesses
+    pub guess_distribution: [i64; MAX_GUESSES],
+}
@@ -0,0 +1,87 @@
+use super::{session::WordGameSession, stats::WordPlayerStats, MAX_GUESSES};
+use crate::{
+    db::PatDatabase,
+    error_handler::DbError,
+    models::games::{session::SessionState, stats::win_streaks},
+};
+use mongodb::bson::doc;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct WordPlayerTotals {
+    played: i64,
+    won: i64,
+    lost: i64,
+}
+
+#[derive(Deserialize)]
+struct DistributionBucket {
+    #[serde(rename = "_id")]
+    guesses: i64,
+    count: i64,
+}
+
+#[derive(Deserialize)]
+struct FinishedResult {
+    won: bool,
+}
+
+#[derive(Deserialize)]
+struct WordPlayerStatsFacets {
+    totals: Vec<WordPlayerTotals>,
+    distribution: Vec<DistributionBucket>,
+    results: Vec<FinishedResult>,
+}
+
+pub async fn get_word_player_stats(db_handle: &PatDatabase, user_id: &str) -> Result<WordPlayerStats, DbError> {
+    let pipeline = vec![
+        doc! { "$match": { "user_id": user_id } },
+        doc! { "$facet": {
+            "totals": [
+                { "$group": {
+                    "_id": null,
+                    "played": { "$sum": 1 },
+                    "won": { "$sum": { "$cond": [{ "$eq": ["$state", SessionState::Won] }, 1, 0] } },
+                    "lost": { "$sum": { "$cond": [{ "$eq": ["$state", SessionState::Lost] }, 1, 0] } },
+                } },
+            ],
+            "distribution": [
+                { "$match": { "state": SessionState::Won } },
+                { "$group": { "_id": { "$size": "$guesses" }, "count": { "$sum": 1 } } },
+            ],
+            "results": [
+                { "$match": { "state": { "$ne": SessionState::InProgress } } },
+                { "$sort": { "finished_at": 1 } },
+                { "$project": { "_id": 0, "won": { "$eq": ["$state", SessionState::Won] } } },
+            ],
+        } },
+    ];
+    let facets = match db_handle.aggregate::<WordGameSession, WordPlayerStatsFacets>(pipeline).await?.pop() {
+        Some(facets) => facets,
+        None => return Err(DbError::UnhandledException("A $facet aggregation returned nothing".to_owned())),
+    };
+
+    // Won in one guess goes in the first slot
+    let mut guess_distribution = [0; MAX_GUESSES];
+    for bucket in facets.distribution {
+        let slot = usize::try_from(bucket.guesses - 1).ok();
+        if let Some(count) = slot.and_then(|index| guess_distribution.get_mut(index)) {
+            *count = bucket.count;
+        }
+    }
+    let results: Vec<bool> = facets.results.into_iter().map(|result| result.won).collect();
+    let (current_win_streak, max_win_streak) = win_streaks(results.as_slice());
+    // A user who hasn't played has no totals group at all
+    let (played, won, lost) = match facets.totals.into_iter().next() {
+        Some(totals) => (totals.played, totals.won, totals.lost),
+        None => (0, 0, 0),
+    };
+    Ok(WordPlayerStats {
+        played,
+        won,
+        lost,
+        current_win_streak,
+        max_win_streak,
+        guess_distribution,
+    })
+}
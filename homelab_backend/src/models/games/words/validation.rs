@@ -0,0 +1,42 @@
+use super::{
+    dictionary::{is_valid_word, normalize_word},
+    WORD_LENGTH,
+};
+use crate::api::return_data::FieldError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateWordGameSchema {
+    // Drawn from the word list when not given
+    #[serde(default)]
+    pub answer: Option<String>,
+}
+
+impl CreateWordGameSchema {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let answer = match &self.answer {
+            Some(answer) => normalize_word(answer),
+            None => return Ok(()),
+        };
+        match check_word(answer.as_str()) {
+            Ok(()) => Ok(()),
+            Err(msg) => Err(vec![FieldError::new("answer".to_owned(), msg)]),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WordGuessSchema {
+    pub guess: String,
+}
+
+// Checks a normalized word can be played
+pub fn check_word(word: &str) -> Result<(), &'static str> {
+    if word.chars().count() != WORD_LENGTH || !word.chars().all(|c| c.is_ascii_lowercase()) {
+        return Err("Must be 5 letters");
+    }
+    match is_valid_word(word) {
+        true => Ok(()),
+        false => Err("Isn't in the word list"),
+    }
+}
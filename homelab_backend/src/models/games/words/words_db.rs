@@ -0,0 +1,54 @@
+use super::{session::WordGameSession, WordGame};
+use crate::{
+    db::{str_to_object_id, MongoModel, PatDatabase},
+    error_handler::DbError,
+    util::current_unix_time,
+};
+use mongodb::bson::{doc, oid::ObjectId, Bson};
+
+impl MongoModel for WordGame {
+    fn collection_name() -> &'static str {
+        "game_words"
+    }
+    fn model_name() -> &'static str {
+        "Word Game"
+    }
+    fn mongo_id(&self) -> Result<ObjectId, DbError> {
+        match self.id.parse::<ObjectId>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(DbError::BadId),
+        }
+    }
+}
+
+// The answer has already been normalized and checked against the word list
+pub async fn insert_word_game(db_handle: &PatDatabase, answer: &str, user_id: &str, from_dictionary: bool) -> Result<WordGame, DbError> {
+    let doc = doc! {
+        "answer": answer,
+        "author_id": user_id,
+        "creation_datetime": current_unix_time(),
+        "from_dictionary": from_dictionary,
+    };
+    db_handle.insert_and_retrieve_one(doc).await
+}
+
+pub async fn get_word_game_by_id(db_handle: &PatDatabase, id: &str) -> Result<WordGame, DbError> {
+    let game_id = str_to_object_id(id)?;
+    let doc = doc! { "_id": Bson::ObjectId(game_id) };
+    db_handle.find_one(doc).await
+}
+
+pub async fn get_all_word_games(db_handle: &PatDatabase, user_id: &str, this_users_games: bool) -> Result<Vec<WordGame>, DbError> {
+    let doc = match this_users_games {
+        true => doc! { "author_id": user_id },
+        false => doc! { "author_id": { "$ne": user_id } },
+    };
+    db_handle.find(doc).await
+}
+
+// Deletes a game along with everyone's sessions for it
+pub async fn delete_word_game(db_handle: &PatDatabase, game: &WordGame) -> Result<(), DbError> {
+    db_handle.delete_one::<WordGame>(doc! { "_id": game.mongo_id()? }).await?;
+    db_handle.delete_many::<WordGameSession>(doc! { "game_id": game.id.as_str() }).await?;
+    Ok(())
+}
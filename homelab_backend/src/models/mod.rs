@@ -28,9 +28,8 @@ fn id_to_string(bson: Bson) -> Result<String, &'static str> {
     }
 }
 
-fn name_to_slug(name: &str) -> String {
-    // TODO: This should probably return an error if there are zero valid chars
-    //       e.g., "name"  is "" or "!@#$"
+// None if the name has no letters, a slug of only dashes can't identify anything
+fn name_to_slug(name: &str) -> Option<String> {
     let mut out_str = String::new();
     for c in name.chars() {
         let lowered = c.to_ascii_lowercase();
@@ -40,5 +39,8 @@ fn name_to_slug(name: &str) -> String {
             out_str.push('-')
         }
     }
-    out_str
+    match out_str.chars().any(|c| c != '-') {
+        true => Some(out_str),
+        false => None,
+    }
 }
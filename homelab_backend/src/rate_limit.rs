@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header::RETRY_AFTER, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{api::return_data::ReturnData, app::AppState, models::user::jwt::get_and_decode_auth_token};
+
+// A bucket which has refilled completely is the same as a missing one, they are dropped once this
+// many buckets pile up so keys which stopped making requests don't stick around forever
+const PRUNE_THRESHOLD: usize = 10_000;
+
+// Allows a burst of `capacity` requests, refilling at `capacity` requests per `period`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub const fn new(capacity: u32, period_secs: u64) -> Self {
+        Self {
+            capacity,
+            period: Duration::from_secs(period_secs),
+        }
+    }
+
+    // Limits are written as "<requests>/<seconds>", like "20/10" for 20 requests every 10 seconds
+    pub fn from_config(name: &str, default: RateLimit) -> Self {
+        let value = match dotenv::var(name) {
+            Ok(value) => value,
+            Err(_) => return default,
+        };
+        let parsed = value
+            .split_once('/')
+            .and_then(|(capacity, period)| Some((capacity.trim().parse::<u32>().ok()?, period.trim().parse::<u64>().ok()?)));
+        match parsed {
+            Some((capacity, period_secs)) if capacity > 0 && period_secs > 0 => Self::new(capacity, period_secs),
+            _ => panic!("{name} must look like <requests>/<seconds>, like 20/10"),
+        }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.capacity as f64 / self.period.as_secs_f64()
+    }
+}
+
+// Every configurable limit, read from the .env when the app starts
+#[derive(Debug, Clone)]
+pub struct RateLimits {
+    // Every request sent over a chat socket, per user
+    pub socket_requests: RateLimit,
+    // CreateMessage requests, per user and per channel
+    pub user_messages: RateLimit,
+    pub channel_messages: RateLimit,
+    // Every HTTP request, per user or per IP for requests without an auth token
+    pub http_requests: RateLimit,
+}
+
+impl RateLimits {
+    pub fn init() -> Self {
+        Self {
+            socket_requests: RateLimit::from_config("RATE_LIMIT_SOCKET_REQUESTS", RateLimit::new(60, 10)),
+            user_messages: RateLimit::from_config("RATE_LIMIT_USER_MESSAGES", RateLimit::new(20, 10)),
+            channel_messages: RateLimit::from_config("RATE_LIMIT_CHANNEL_MESSAGES", RateLimit::new(60, 10)),
+            http_requests: RateLimit::from_config("RATE_LIMIT_HTTP_REQUESTS", RateLimit::new(120, 10)),
+        }
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn refilled(&self, now: Instant, limit: &RateLimit) -> f64 {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        (self.tokens + elapsed * limit.refill_per_sec()).min(limit.capacity as f64)
+    }
+}
+
+// A token bucket for every key, like a user ID or a channel ID. Each request takes a token and
+// tokens refill at a steady rate, so short bursts are allowed but a flood is not
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Takes a token for `key`, or returns how long until one is available if the bucket is empty
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let capacity = self.limit.capacity as f64;
+        let mut buckets = self.buckets.lock().expect("Rate limiter mutex was poisoned");
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| bucket.refilled(now, &self.limit) < capacity);
+        }
+        let bucket = buckets.entry(key.to_owned()).or_insert(TokenBucket {
+            tokens: capacity,
+            updated_at: now,
+        });
+        bucket.tokens = bucket.refilled(now, &self.limit);
+        bucket.updated_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.limit.refill_per_sec()))
+        }
+    }
+}
+
+pub struct RateLimiters {
+    pub socket_requests: RateLimiter,
+    pub user_messages: RateLimiter,
+    pub channel_messages: RateLimiter,
+    pub http_requests: RateLimiter,
+}
+
+impl RateLimiters {
+    pub fn new(limits: &RateLimits) -> Self {
+        Self {
+            socket_requests: RateLimiter::new(limits.socket_requests),
+            user_messages: RateLimiter::new(limits.user_messages),
+            channel_messages: RateLimiter::new(limits.channel_messages),
+            http_requests: RateLimiter::new(limits.http_requests),
+        }
+    }
+}
+
+// Limits every HTTP request by the user making it, or by IP for requests without an auth token
+pub async fn limit_http_requests(State(app_state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let key = match get_and_decode_auth_token(request.headers(), &app_state.config.app_secret) {
+        Ok(user_id) => user_id,
+        Err(_) => match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => addr.ip().to_string(),
+            None => return next.run(request).await,
+        },
+    };
+    match app_state.rate_limiters.http_requests.check(key.as_str()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            let mut response = ReturnData::<()>::too_many_requests("Too many requests, slow down".to_string()).into_response();
+            // Retry-After is in whole seconds, round up so retrying right on time always works
+            let retry_after_secs = retry_after.as_secs_f64().ceil() as u64;
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(retry_after_secs));
+            response
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use tokio::sync::RwLock;
+
+struct ChannelGroup {
+    server_id: Option<String>,
+    readers: Arc<[String]>,
+}
+
+// The users who can read each channel, worked out once and reused for every broadcast to it so
+// fan-out to a channel is a single lookup instead of a trip to the db. Anything that changes who
+// can read a channel has to invalidate its group, the next broadcast will load it again
+#[derive(Default)]
+pub struct ChannelGroups {
+    groups: RwLock<HashMap<String, ChannelGroup>>,
+    // Bumped on every invalidation. A group loaded while an invalidation happened could already
+    // be out of date, so it is not cached
+    generation: AtomicU64,
+}
+
+impl ChannelGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Take this before loading the readers of a channel and pass it to `insert`
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    pub async fn get(&self, channel_id: &str) -> Option<Arc<[String]>> {
+        self.groups.read().await.get(channel_id).map(|group| group.readers.clone())
+    }
+
+    pub async fn insert(&self, channel_id: &str, server_id: Option<&str>, readers: Vec<String>, generation: u64) -> Arc<[String]> {
+        let readers: Arc<[String]> = readers.into();
+        let mut groups = self.groups.write().await;
+        if self.generation() == generation {
+            let group = ChannelGroup {
+                server_id: server_id.map(str::to_owned),
+                readers: readers.clone(),
+            };
+            groups.insert(channel_id.to_owned(), group);
+        }
+        readers
+    }
+
+    pub async fn invalidate(&self, channel_id: &str) {
+        let mut groups = self.groups.write().await;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        groups.remove(channel_id);
+    }
+
+    // Server membership and roles affect every channel in the server
+    pub async fn invalidate_server(&self, server_id: &str) {
+        let mut groups = self.groups.write().await;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        groups.retain(|_, group| group.server_id.as_deref() != Some(server_id));
+    }
+}
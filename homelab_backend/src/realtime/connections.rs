@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, RandomState},
+};
+
+use mongodb::bson::oid::ObjectId;
+use tokio::sync::{mpsc::UnboundedSender, RwLock};
+
+use crate::models::chat::packet::{WebSocketResponse, WebSocketResponseFrame};
+
+// Connects and disconnects take a write lock on a shard, so more shards means fewer connections
+// waiting on each other when many users join or leave at once
+const SHARD_COUNT: usize = 16;
+
+type UserConnections = HashMap<String, UnboundedSender<WebSocketResponseFrame>>;
+
+// Every open websocket, grouped by the user it belongs to. A user can be connected from more than
+// one device at once, so each connection gets its own ID and is cleaned up on its own.
+// Users are spread across shards by a hash of their ID, so a connect or disconnect only locks the
+// shard the user lives in and sends to users in other shards carry on
+pub struct ConnectionRegistry {
+    hasher: RandomState,
+    shards: Vec<RwLock<HashMap<String, UserConnections>>>,
+}
+
+impl Default for ConnectionRegistry {
+    fn default() -> Self {
+        Self {
+            hasher: RandomState::new(),
+            shards: (0..SHARD_COUNT).map(|_| RwLock::new(HashMap::new())).collect(),
+        }
+    }
+}
+
+impl ConnectionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn shard_index(&self, user_id: &str) -> usize {
+        (self.hasher.hash_one(user_id) % SHARD_COUNT as u64) as usize
+    }
+
+    // Returns the ID of the new connection, used to unregister it
+    pub async fn register(&self, user_id: &str, tx: UnboundedSender<WebSocketResponseFrame>) -> String {
+        let connection_id = ObjectId::new().to_hex();
+        let mut shard = self.shards[self.shard_index(user_id)].write().await;
+        shard.entry(user_id.to_owned()).or_default().insert(connection_id.clone(), tx);
+        connection_id
+    }
+
+    pub async fn unregister(&self, user_id: &str, connection_id: &str) {
+        let mut shard = self.shards[self.shard_index(user_id)].write().await;
+        if let Some(user_connections) = shard.get_mut(user_id) {
+            user_connections.remove(connection_id);
+            if user_connections.is_empty() {
+                shard.remove(user_id);
+            }
+        }
+    }
+
+    // Sends to every connection the user has open
+    pub async fn send_to_user(&self, user_id: &str, response: &WebSocketResponse) {
+        self.send_to_users([user_id], response).await;
+    }
+
+    // Keeps a user's other devices in sync with something done on one of them
+    pub async fn send_to_other_connections(&self, user_id: &str, connection_id: &str, response: &WebSocketResponse) {
+        let shard = self.shards[self.shard_index(user_id)].read().await;
+        if let Some(user_connections) = shard.get(user_id) {
+            for (_, tx) in user_connections.iter().filter(|(id, _)| id.as_str() != connection_id) {
+                let _ = tx.send(response.clone().into());
+            }
+        }
+    }
+
+    // Users are bucketed by shard first so each shard is only locked once per send, no matter
+    // how many of the users live in it
+    pub async fn send_to_users<I, S>(&self, user_ids: I, response: &WebSocketResponse)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut buckets: Vec<Vec<S>> = (0..SHARD_COUNT).map(|_| Vec::new()).collect();
+        for user_id in user_ids {
+            buckets[self.shard_index(user_id.as_ref())].push(user_id);
+        }
+        for (index, bucket) in buckets.iter().enumerate().filter(|(_, bucket)| !bucket.is_empty()) {
+            let shard = self.shards[index].read().await;
+            for user_id in bucket {
+                if let Some(user_connections) = shard.get(user_id.as_ref()) {
+                    for tx in user_connections.values() {
+                        // A failed send means the connection is closing, its own cleanup will remove it
+                        let _ = tx.send(response.clone().into());
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use futures::future::BoxFuture;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    app::AppState,
+    logger,
+    models::chat::packet::{WebSocketError, WebSocketErrorCode, WebSocketResponse, WebSocketResponseFrame},
+};
+
+// What a handler sends back to the connection which made the request. Handlers which only
+// broadcast can return Ok(None), and errors can be raised with `?` on anything that converts into
+// a WebSocketError, like a DbError
+pub type HandlerResult = Result<Option<WebSocketResponse>, WebSocketError>;
+
+type BoxedHandler = Box<dyn Fn(Arc<SocketContext>, serde_json::Value) -> BoxFuture<'static, HandlerResult> + Send + Sync>;
+
+// Everything a handler knows about the connection a request came in on
+pub struct SocketContext {
+    pub app_state: Arc<AppState>,
+    pub user_id: String,
+    pub connection_id: String,
+}
+
+// The data of a request a handler can be registered for. TYPE is the `type` the request is sent
+// with, the `data` is decoded into the implementing type
+pub trait SocketRequest: DeserializeOwned + Send + 'static {
+    const TYPE: &'static str;
+}
+
+// Every frame has a type and optionally data and a request_id, the data is decoded later by
+// whichever handler is registered for the type
+#[derive(Deserialize)]
+struct RawRequestFrame {
+    #[serde(rename = "type")]
+    request_type: String,
+    #[serde(default)]
+    data: serde_json::Value,
+    #[serde(default)]
+    request_id: Option<String>,
+}
+
+// Routes each request received on a socket to the handler registered for its type. Chat
+// registers its handlers in chat_socket_handlers, other modules can register their own request
+// types the same way and share the socket
+#[derive(Default)]
+pub struct SocketDispatcher {
+    handlers: HashMap<&'static str, BoxedHandler>,
+}
+
+impl SocketDispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T, F, Fut>(&mut self, handler: F)
+    where
+        T: SocketRequest,
+        F: Fn(Arc<SocketContext>, T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HandlerResult> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let boxed: BoxedHandler = Box::new(move |ctx, data| {
+            let handler = handler.clone();
+            Box::pin(async move {
+                match serde_json::from_value::<T>(data) {
+                    Ok(request) => handler(ctx, request).await,
+                    Err(_e) => Err(WebSocketError::new(
+                        WebSocketErrorCode::BadRequest,
+                        format!("Failed to decode the data of a {} request", T::TYPE).as_str(),
+                    )),
+                }
+            })
+        });
+        if self.handlers.insert(T::TYPE, boxed).is_some() {
+            panic!("A handler was registered twice for {} requests", T::TYPE);
+        }
+    }
+
+    // Returns what should be sent back to the connection, tagged with the request_id of the request
+    pub async fn dispatch(&self, ctx: Arc<SocketContext>, text: &str) -> Option<WebSocketResponseFrame> {
+        // Every request counts against the user's limit, including ones which can't be decoded
+        if let Err(retry_after) = ctx.app_state.rate_limiters.socket_requests.check(ctx.user_id.as_str()) {
+            return Some(WebSocketResponseFrame {
+                response: WebSocketResponse::SendError(WebSocketError::rate_limited("You are sending requests too quickly", retry_after)),
+                request_id: request_id_from_text(text),
+            });
+        }
+        let frame = match serde_json::from_str::<RawRequestFrame>(text) {
+            Ok(frame) => frame,
+            Err(_e) => {
+                logger::log_msg("Error while deserializing a websocket packet from a string");
+                // Would be nice to give more info to the user here about what failed
+                let response = WebSocketResponse::ws_error(WebSocketErrorCode::BadRequest, "Failed to decode received data");
+                return Some(WebSocketResponseFrame {
+                    response,
+                    request_id: request_id_from_text(text),
+                });
+            }
+        };
+        let result = match self.handlers.get(frame.request_type.as_str()) {
+            Some(handler) => handler(ctx, frame.data).await,
+            None => Err(WebSocketError::new(
+                WebSocketErrorCode::BadRequest,
+                format!("Unknown request type {}", frame.request_type).as_str(),
+            )),
+        };
+        let response = match result {
+            Ok(response) => response?,
+            Err(ws_err) => WebSocketResponse::SendError(ws_err),
+        };
+        Some(WebSocketResponseFrame {
+            response,
+            request_id: frame.request_id,
+        })
+    }
+}
+
+// Pulls the request_id out of a request which couldn't be decoded, so the error can still be matched up
+fn request_id_from_text(text: &str) -> Option<String> {
+    let value = serde_json::from_str::<serde_json::Value>(text).ok()?;
+    value.get("request_id")?.as_str().map(str::to_owned)
+}
@@ -0,0 +1,286 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use mongodb::{
+    bson::{doc, from_document, Document},
+    change_stream::event::{ChangeStreamEvent, OperationType, ResumeToken},
+    options::FullDocumentType,
+};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time,
+};
+
+use crate::{
+    app::AppState,
+    db::{MongoModel, PatDatabase},
+    logger,
+    models::chat::{
+        chat_channel::ChatChannel,
+        chat_channel_db::get_chat_channel_by_id,
+        message::ChatMessage,
+        packet::{MessagePinnedResponse, WebSocketResponse},
+        server::ChatServer,
+        server_db::get_channel_readers,
+    },
+};
+
+// How many events an instance can fall behind on before it starts missing them
+const EVENT_BUFFER_SIZE: usize = 1024;
+// How long to wait before re-opening a change stream which failed
+const CHANGE_STREAM_RETRY: Duration = Duration::from_secs(1);
+
+// Something which happened in a chat channel that every connected reader of the channel should
+// hear about, no matter which instance of the backend they are connected to
+#[derive(Clone, Debug)]
+pub enum ChatEvent {
+    MessageCreated(ChatMessage),
+    MessagePinned(MessagePinnedResponse),
+    // Who can read a channel, or any channel in a server, changed on some instance. Every instance
+    // drops its cached broadcast groups for it. Only the change stream backend produces these,
+    // with a single instance the request which made the change invalidates the group itself
+    ChannelReadersChanged(String),
+    ServerReadersChanged(String),
+}
+
+// Carries ChatEvents between every instance of the backend. Events are published after they have
+// been written to the db, and every instance delivers each event it receives to its own connections
+pub trait ChatEventBus: Send + Sync {
+    fn publish(&self, event: ChatEvent);
+    fn subscribe(&self) -> broadcast::Receiver<ChatEvent>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventBusBackend {
+    // Only works when a single instance of the backend is running
+    InProcess,
+    // Every instance watches chat_messages, chat_channels and chat_servers, needs the db to be a
+    // replica set
+    ChangeStream,
+}
+
+impl EventBusBackend {
+    pub fn from_config(value: &str) -> Self {
+        match value {
+            "" | "in_process" => Self::InProcess,
+            "change_stream" => Self::ChangeStream,
+            _ => panic!("CHAT_EVENT_BUS must be either in_process or change_stream"),
+        }
+    }
+}
+
+pub fn create_event_bus(backend: EventBusBackend, db: &PatDatabase) -> Box<dyn ChatEventBus> {
+    match backend {
+        EventBusBackend::InProcess => Box::new(InProcessEventBus::new()),
+        EventBusBackend::ChangeStream => Box::new(ChangeStreamEventBus::new(db.clone())),
+    }
+}
+
+pub struct InProcessEventBus {
+    sender: broadcast::Sender<ChatEvent>,
+}
+
+impl InProcessEventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+        Self { sender }
+    }
+}
+
+impl ChatEventBus for InProcessEventBus {
+    fn publish(&self, event: ChatEvent) {
+        // An error only means nothing is subscribed yet
+        let _ = self.sender.send(event);
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<ChatEvent> {
+        self.sender.subscribe()
+    }
+}
+
+pub struct ChangeStreamEventBus {
+    sender: broadcast::Sender<ChatEvent>,
+}
+
+impl ChangeStreamEventBus {
+    pub fn new(db: PatDatabase) -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+        tokio::spawn(watch_chat_collections(db, sender.clone()));
+        Self { sender }
+    }
+}
+
+impl ChatEventBus for ChangeStreamEventBus {
+    // Events are already in chat_messages by the time they are published, so the change stream
+    // picks them up on every instance including this one
+    fn publish(&self, _event: ChatEvent) {}
+
+    fn subscribe(&self) -> broadcast::Receiver<ChatEvent> {
+        self.sender.subscribe()
+    }
+}
+
+// The fields of a channel or server which decide who can read a channel. Channels are updated on
+// every message, so any other update isn't worth dropping a broadcast group over
+const CHANNEL_READER_FIELDS: [&str; 6] = [
+    "subscribers",
+    "banned_users",
+    "is_private",
+    "permission_overrides",
+    "owner_id",
+    "server_id",
+];
+const SERVER_READER_FIELDS: [&str; 4] = ["owner_id", "default_permissions", "members", "roles"];
+
+// Matches the updates which touch any of the fields, including ones to a single element of an
+// array which show up as a dotted path like "members.2.role_ids"
+fn updates_any_field(fields: &[&str]) -> Document {
+    doc! {"$expr": {"$anyElementTrue": [{"$map": {
+        "input": {"$concatArrays": [
+            {"$map": {"input": {"$objectToArray": "$updateDescription.updatedFields"}, "as": "field", "in": "$$field.k"}},
+            {"$ifNull": ["$updateDescription.removedFields", []]},
+        ]},
+        "as": "path",
+        "in": {"$in": [{"$arrayElemAt": [{"$split": ["$$path", "."]}, 0]}, fields]},
+    }}]}}
+}
+
+// Everything the change stream bus needs to hear about: new and pinned messages, and any change to
+// who can read a channel
+pub fn chat_change_pipeline() -> Vec<Document> {
+    let mut channel_updates = updates_any_field(&CHANNEL_READER_FIELDS);
+    channel_updates.insert("ns.coll", ChatChannel::collection_name());
+    channel_updates.insert("operationType", "update");
+    let mut server_updates = updates_any_field(&SERVER_READER_FIELDS);
+    server_updates.insert("ns.coll", ChatServer::collection_name());
+    server_updates.insert("operationType", "update");
+    vec![doc! {"$match": {"$or": [
+        {
+            "ns.coll": ChatMessage::collection_name(),
+            "operationType": {"$in": ["insert", "update"]},
+        },
+        {
+            "ns.coll": {"$in": [ChatChannel::collection_name(), ChatServer::collection_name()]},
+            "operationType": {"$in": ["replace", "delete"]},
+        },
+        channel_updates,
+        server_updates,
+    ]}}]
+}
+
+// Messages and the channels and servers which decide who can read them are watched through one
+// stream, so an instance always drops a stale broadcast group before delivering any message which
+// was sent after the change
+async fn watch_chat_collections(db: PatDatabase, sender: broadcast::Sender<ChatEvent>) {
+    let pipeline = chat_change_pipeline();
+    // Kept so a stream which fails can pick up where it left off without losing events
+    let mut resume_token: Option<ResumeToken> = None;
+    loop {
+        let stream = db
+            .pool_ref()
+            .watch()
+            .pipeline(pipeline.clone())
+            .full_document(FullDocumentType::UpdateLookup)
+            .resume_after(resume_token.clone())
+            .await;
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                logger::log_msg(format!("Failed to open a change stream on the chat collections: {}", e));
+                time::sleep(CHANGE_STREAM_RETRY).await;
+                continue;
+            }
+        };
+        while let Some(change) = stream.next().await {
+            match change {
+                Ok(change) => {
+                    resume_token = Some(change.id.clone());
+                    if let Some(event) = chat_event_from_change(change) {
+                        let _ = sender.send(event);
+                    }
+                }
+                Err(e) => {
+                    logger::log_msg(format!("Change stream on the chat collections failed: {}", e));
+                    break;
+                }
+            }
+        }
+        time::sleep(CHANGE_STREAM_RETRY).await;
+    }
+}
+
+pub fn chat_event_from_change(change: ChangeStreamEvent<Document>) -> Option<ChatEvent> {
+    let collection = change.ns.as_ref()?.coll.clone()?;
+    if collection == ChatMessage::collection_name() {
+        return message_event_from_change(change);
+    }
+    // Any change to a channel or server could have changed who can read it
+    let id = change.document_key?.get_object_id("_id").ok()?.to_hex();
+    match collection == ChatServer::collection_name() {
+        true => Some(ChatEvent::ServerReadersChanged(id)),
+        false => Some(ChatEvent::ChannelReadersChanged(id)),
+    }
+}
+
+// Inserts are new messages, and an update is only interesting if it changed whether the message is pinned
+fn message_event_from_change(change: ChangeStreamEvent<Document>) -> Option<ChatEvent> {
+    let message: ChatMessage = from_document(change.full_document?).ok()?;
+    match change.operation_type {
+        OperationType::Insert => Some(ChatEvent::MessageCreated(message)),
+        OperationType::Update => {
+            let update = change.update_description?;
+            match update.updated_fields.contains_key("pinned") {
+                true => Some(ChatEvent::MessagePinned(MessagePinnedResponse {
+                    channel_id: message.channel_id,
+                    message_id: message.id,
+                    pinned: message.pinned,
+                })),
+                false => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// Runs for as long as the app does, handing every event off to the connections on this instance
+pub async fn deliver_chat_events(app_state: Arc<AppState>, mut events: broadcast::Receiver<ChatEvent>) {
+    loop {
+        match events.recv().await {
+            Ok(ChatEvent::MessageCreated(message)) => {
+                let channel_id = message.channel_id.clone();
+                broadcast_to_channel(&app_state, channel_id.as_str(), &WebSocketResponse::SendChatMessage(message)).await;
+            }
+            Ok(ChatEvent::MessagePinned(pinned)) => {
+                let channel_id = pinned.channel_id.clone();
+                broadcast_to_channel(&app_state, channel_id.as_str(), &WebSocketResponse::MessagePinned(pinned)).await;
+            }
+            Ok(ChatEvent::ChannelReadersChanged(channel_id)) => app_state.channel_groups.invalidate(channel_id.as_str()).await,
+            Ok(ChatEvent::ServerReadersChanged(server_id)) => app_state.channel_groups.invalidate_server(server_id.as_str()).await,
+            Err(RecvError::Lagged(missed)) => {
+                logger::log_msg(format!("Chat event delivery fell behind and missed {} events", missed));
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+// Sends a response to every user who can read the channel, using its cached broadcast group when
+// there is one
+async fn broadcast_to_channel(app_state: &AppState, channel_id: &str, response: &WebSocketResponse) {
+    let readers = match app_state.channel_groups.get(channel_id).await {
+        Some(readers) => readers,
+        None => {
+            let generation = app_state.channel_groups.generation();
+            let channel = match get_chat_channel_by_id(&app_state.db, channel_id).await {
+                Ok(channel) => channel,
+                Err(_) => return,
+            };
+            let readers = get_channel_readers(&app_state.db, &channel).await.unwrap_or_default();
+            app_state
+                .channel_groups
+                .insert(channel_id, channel.server_id.as_deref(), readers, generation)
+                .await
+        }
+    };
+    app_state.active_connections.send_to_users(readers.iter(), response).await;
+}
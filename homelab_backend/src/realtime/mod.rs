@@ -0,0 +1,4 @@
+pub mod channel_groups;
+pub mod connections;
+pub mod dispatcher;
+pub mod event_bus;
@@ -0,0 +1,67 @@
+use crate::{
+    app::AppState,
+    error_handler::DbError,
+    logger::log_msg,
+    models::{
+        chat::{
+            chat_channel_db::get_chat_channel_by_id, message_db::insert_chat_message, server::permissions, server_db::has_channel_permission,
+            validation::CreateMessageSchema,
+        },
+        games::{
+            daily::DailyPuzzle,
+            daily_db::{claim_daily_puzzle_announcement, release_daily_puzzle_announcement},
+            games_db::get_connection_game_by_id,
+        },
+    },
+    realtime::event_bus::ChatEvent,
+    util::{current_unix_time, unix_time_to_date},
+};
+
+// Posts today's puzzle to the announcement channel, if one is configured and today's puzzle hasn't
+// been announced yet
+pub async fn announce_daily_puzzle(app_state: &AppState) {
+    let channel_id = match &app_state.config.daily_puzzle_channel_id {
+        Some(channel_id) => channel_id.clone(),
+        None => return,
+    };
+    let today = unix_time_to_date(current_unix_time(), app_state.config.daily_puzzle_utc_offset);
+    let daily_puzzle = match claim_daily_puzzle_announcement(&app_state.db, today.as_str()).await {
+        Ok(daily_puzzle) => daily_puzzle,
+        // Nothing is scheduled, or it was already announced
+        Err(DbError::NotFound(_)) => return,
+        Err(db_err) => return log_msg(format!("Failed to check the daily puzzle for {today}: {db_err:?}")),
+    };
+
+    // The claim is only kept once the announcement is posted, anything else leaves it for the next run
+    if let Err(reason) = post_daily_puzzle(app_state, channel_id, &daily_puzzle).await {
+        log_msg(format!("Failed to announce the daily puzzle for {today}: {reason}"));
+        if let Err(db_err) = release_daily_puzzle_announcement(&app_state.db, today.as_str()).await {
+            log_msg(format!("Failed to release the daily puzzle announcement for {today}: {db_err:?}"));
+        }
+    }
+}
+
+async fn post_daily_puzzle(app_state: &AppState, channel_id: String, daily_puzzle: &DailyPuzzle) -> Result<(), String> {
+    let game = get_connection_game_by_id(&app_state.db, daily_puzzle.game_id.as_str())
+        .await
+        .map_err(|db_err| format!("{db_err:?}"))?;
+    let channel = get_chat_channel_by_id(&app_state.db, channel_id.as_str())
+        .await
+        .map_err(|db_err| format!("{db_err:?}"))?;
+    // Posted as the admin who scheduled it, so they need to be able to post in the channel
+    let author_id = daily_puzzle.scheduled_by.as_str();
+    if !has_channel_permission(&app_state.db, &channel, author_id, permissions::READ | permissions::SEND).await {
+        return Err(format!("{author_id} can't send messages in the announcement channel"));
+    }
+
+    let message = CreateMessageSchema {
+        channel_id: channel.id,
+        contents: format!("Today's puzzle is {}! Play it at /games/connections/play/{}", game.puzzle_name, game.slug),
+        reply_to: None,
+    };
+    let chat_message = insert_chat_message(&app_state.db, message, author_id)
+        .await
+        .map_err(|db_err| format!("{db_err:?}"))?;
+    app_state.event_bus.publish(ChatEvent::MessageCreated(chat_message));
+    Ok(())
+}
@@ -3,6 +3,9 @@ use crate::models::log::Log;
 use mongodb::bson::doc;
 use std::fmt::{Display, Formatter};
 
+// Query params which can carry credentials, their values are never logged
+const SENSITIVE_QUERY_PARAMS: [&str; 4] = ["auth_token", "token", "ticket", "password"];
+
 pub struct LogCreationTask {
     method: String,
     uri: String,
@@ -20,24 +23,39 @@ impl LogCreationTask {
     pub fn new(method: String, uri: String, user_id: String, date_time: i64) -> Self {
         Self {
             method,
-            uri,
+            uri: redact_uri(uri.as_str()),
             user_id,
             date_time,
         }
     }
     pub async fn write_log(&self, db_handle: &PatDatabase) {
-        let mut uri = self.uri.clone();
-        if uri.starts_with("/api/chat/ws?auth_token=") {
-            // This is a bit hacky and should be made to support a general-purpose solution to the problem
-            // Websocket authentication includes the token as a query param in the uri, so redact it
-            uri = "/api/chat/ws?auth_token=<redacted>".to_string()
-        }
         let doc = doc! {
             "method": self.method.clone(),
-            "uri": uri,
+            "uri": self.uri.clone(),
             "user_id": self.user_id.clone(),
             "date_time": self.date_time
         };
         let _res = db_handle.insert_one::<Log>(doc).await;
     }
 }
+
+// Replaces the value of every sensitive query param in a uri, so credentials never end up in the
+// console or the logs collection
+fn redact_uri(uri: &str) -> String {
+    let (path, query) = match uri.split_once('?') {
+        Some(split) => split,
+        None => return uri.to_owned(),
+    };
+    let query = query
+        .split('&')
+        .map(|param| {
+            let key = param.split_once('=').map_or(param, |(key, _)| key);
+            match SENSITIVE_QUERY_PARAMS.contains(&key.to_ascii_lowercase().as_str()) {
+                true => format!("{key}=<redacted>"),
+                false => param.to_owned(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("&");
+    format!("{path}?{query}")
+}
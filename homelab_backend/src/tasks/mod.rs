@@ -1,2 +1,3 @@
+pub mod daily_puzzle_task;
 pub mod log_creation_task;
 pub mod task_manager;
@@ -6,6 +6,8 @@ pub struct TaskManager {
     db_handle: PatDatabase,
     log_creation_send_channel: Sender<&'static str>,
     log_creation_receive_channel: Receiver<&'static str>, // This should return a result with some data?
+    daily_puzzle_send_channel: Sender<&'static str>,
+    daily_puzzle_receive_channel: Receiver<&'static str>,
 }
 
 impl TaskManager {
@@ -13,11 +15,15 @@ impl TaskManager {
         db_handle: PatDatabase,
         log_creation_send_channel: Sender<&'static str>,
         log_creation_receive_channel: Receiver<&'static str>,
+        daily_puzzle_send_channel: Sender<&'static str>,
+        daily_puzzle_receive_channel: Receiver<&'static str>,
     ) -> Self {
         Self {
             db_handle,
             log_creation_send_channel,
             log_creation_receive_channel,
+            daily_puzzle_send_channel,
+            daily_puzzle_receive_channel,
         }
     }
 }
@@ -29,4 +35,10 @@ impl TaskManager {
         let _ = self.log_creation_send_channel.send("run task");
         let _ = self.log_creation_receive_channel.changed().await;
     }
+
+    pub async fn run_daily_puzzle_task(&mut self) {
+        // Manually run the recurring task to announce the daily puzzle and wait for it to finish
+        let _ = self.daily_puzzle_send_channel.send("run task");
+        let _ = self.daily_puzzle_receive_channel.changed().await;
+    }
 }
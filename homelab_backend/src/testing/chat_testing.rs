@@ -1,20 +1,44 @@
 #[cfg(test)]
 mod chat_testing {
+    use crate::app::Config;
+    use crate::rate_limit::{RateLimit, RateLimits};
+    use crate::realtime::event_bus::{chat_change_pipeline, chat_event_from_change, ChatEvent, EventBusBackend};
     use crate::testing::{
         helpers::user_helpers::{create_user, get_user_me},
         TestHelper, FAKE_MONGO_ID,
     };
+    use crate::util::format_unix_time;
+    use futures::StreamExt;
     use hyper::StatusCode;
+    use mongodb::{
+        bson::Document,
+        change_stream::{event::ChangeStreamEvent, ChangeStream},
+        options::FullDocumentType,
+    };
+    use std::time::Instant;
+    use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest, http::HeaderValue};
 
     use crate::models::chat::{
         chat_channel::{ChannelType, ReturnChannel},
-        packet::{RequestMessagesSchema, WebSocketRequest},
-        validation::{CreateChannelSchema, CreateMessageSchema},
+        export::ExportedMessage,
+        packet::{
+            AuthenticateSchema, ChannelEvent, GetThreadSchema, MarkReadSchema, PinMessageSchema, RequestMessagesSchema, ResumeChannelSchema,
+            ResumeSchema, WebSocketErrorCode, WebSocketRequest, WebSocketResponse,
+        },
+        server::{permissions, PermissionOverride},
+        validation::{
+            CreateChannelSchema, CreateInviteSchema, CreateMessageSchema, CreateServerCategorySchema, CreateServerChannelSchema,
+            CreateServerRoleSchema, CreateServerSchema, UpdateChannelSchema, MAX_INVITE_LIFETIME, MAX_MESSAGE_LENGTH,
+        },
     };
     use crate::models::user::ReturnUser;
     use crate::testing::helpers::chat_helpers::{
-        create_chat_channel, get_channel_by_id, list_channels, receive_chat_message, receive_chat_state, send_arbitrary_data, send_websocket_request,
-        subscribe_to_channel, unsubscribe_from_channel,
+        create_chat_channel, create_invite, create_server, create_server_category, create_server_channel, create_server_invite, create_server_role,
+        delete_channel, export_channel, get_channel_by_id, get_server, get_socket_ticket, join_server, join_with_invite, list_channels,
+        list_direct_messages, list_invites, list_servers, moderate_channel_member, open_direct_message, receive_channel_event, receive_chat_message,
+        receive_chat_state, receive_message_pinned, receive_read_receipt, receive_response_frame, receive_response_to, receive_resumed,
+        receive_thread, remove_server_member, revoke_invite, search_messages, send_arbitrary_data, send_websocket_request,
+        send_websocket_request_with_id, set_member_roles, set_permission_override, subscribe_to_channel, unsubscribe_from_channel, update_channel,
     };
 
     struct ChatHelper {
@@ -41,8 +65,9 @@ mod chat_testing {
                 // Create a channel for the user
                 let data = CreateChannelSchema {
                     name: Some(format!("channel-{}", n)),
-                    channel_type: 0,
+                    channel_type: 1,
                     slug: format!("channel-{}", n),
+                    is_private: false,
                 };
                 let channel = create_chat_channel(test_helper, token.as_str(), &data)
                     .await
@@ -74,18 +99,35 @@ mod chat_testing {
         let user = get_user_me(&helper, token.as_str()).await.unwrap();
         let user_two = get_user_me(&helper, second_token.as_str()).await.unwrap();
 
+        // Try to create a direct message channel, which must be opened with a specific user instead
+        let dm_data = CreateChannelSchema {
+            name: None,
+            channel_type: 0,
+            slug: "dm_channel".to_string(),
+            is_private: false,
+        };
+        match create_chat_channel(&helper, token.as_str(), &dm_data).await {
+            Ok(_) => panic!("Creating a direct message channel directly should fail"),
+            Err((status_code, _msg)) => assert_eq!(
+                status_code,
+                StatusCode::BAD_REQUEST,
+                "Creating a direct message channel directly should 400"
+            ),
+        };
+
         // Create a channel without a name
         let data = CreateChannelSchema {
             name: None,
-            channel_type: 0,
+            channel_type: 1,
             slug: "test_channel".to_string(),
+            is_private: false,
         };
         let first_channel = create_chat_channel(&helper, token.as_str(), &data)
             .await
             .expect("Failed to create a chat channel");
         assert_eq!(first_channel.name, None);
         assert_eq!(first_channel.slug.as_str(), "test_channel");
-        assert_eq!(first_channel.channel_type, ChannelType::DirectMessage);
+        assert_eq!(first_channel.channel_type, ChannelType::Group);
         assert_eq!(first_channel.pinned_messages.len(), 0);
         assert_eq!(first_channel.subscribers, vec![user.clone().into()]);
         assert_eq!(first_channel.owner_id, user.id.as_str());
@@ -96,6 +138,7 @@ mod chat_testing {
             name: Some("My Channel".to_string()),
             channel_type: 1,
             slug: "second_channel".to_string(),
+            is_private: false,
         };
         let second_channel = create_chat_channel(&helper, token.as_str(), &data_two)
             .await
@@ -353,7 +396,7 @@ mod chat_testing {
         send_websocket_request(&mut first_socket, &message_data_bad_channel).await;
         match receive_chat_message(&mut first_socket).await {
             Ok(_) => panic!("Should receive a WebSocketError after sending a chat message to a channel that doesn't exist"),
-            Err(e) => assert_eq!(e.status_code, 404),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::NotFound),
         }
 
         // Try to send a message to a channel the user is not a subscriber of
@@ -366,14 +409,14 @@ mod chat_testing {
         send_websocket_request(&mut first_socket, &unauthorized_message).await;
         match receive_chat_message(&mut first_socket).await {
             Ok(_) => panic!("Should receive a WebSocketError after sending a chat message to a channel that the user is not subscribed to"),
-            Err(e) => assert_eq!(e.status_code, 400),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
         }
 
         // Send garbage data, assert that we get a 400 back
         send_arbitrary_data(&mut first_socket, "This cannot be deserialized".to_owned()).await;
         match receive_chat_message(&mut first_socket).await {
             Ok(_) => panic!("Should receive a WebSocketError after sending arbitrary text that cannot be deserialized"),
-            Err(e) => assert_eq!(e.status_code, 400),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
         }
     }
 
@@ -432,7 +475,7 @@ mod chat_testing {
         send_websocket_request(&mut first_socket, &request_invalid_channel).await;
         match receive_chat_state(&mut first_socket).await {
             Ok(_) => panic!("Should receive a WebSocketError when requesting chat state for a channel that doesn't exist"),
-            Err(e) => assert_eq!(e.status_code, 404),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::NotFound),
         }
 
         // Request messages from a channel the user is not subscribed to
@@ -445,7 +488,7 @@ mod chat_testing {
         send_websocket_request(&mut first_socket, &request_invalid_channel).await;
         match receive_chat_state(&mut first_socket).await {
             Ok(_) => panic!("Should receive a WebSocketError when requesting chat state for a channel that the user is not subscribed to"),
-            Err(e) => assert_eq!(e.status_code, 400),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
         }
 
         // Try to request too many messages at once
@@ -458,7 +501,7 @@ mod chat_testing {
         send_websocket_request(&mut first_socket, &request_too_many_messages).await;
         match receive_chat_state(&mut first_socket).await {
             Ok(_) => panic!("Should receive a WebSocketError when requesting too many chat messages at once"),
-            Err(e) => assert_eq!(e.status_code, 400),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
         }
 
         // Request 5 chat messages starting at some arbitrary message, assert we get the right ones back
@@ -550,4 +593,1790 @@ mod chat_testing {
             assert_eq!(chat_message.author_id.as_str(), user_two_id);
         }
     }
+
+    #[tokio::test]
+    async fn chat_read_receipts() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 2).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let user_two_id = chat_helper.users[1].id.as_str();
+        let channel_two_id = chat_helper.channels[1]._id.as_str();
+
+        // Read receipts are shared in direct messages
+        let direct_message = open_direct_message(&helper, token, user_two_id)
+            .await
+            .expect("Failed to open a direct message");
+        let channel_one_id = direct_message.channel._id.as_str();
+
+        let (mut first_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+        let (mut second_socket, _second_response) =
+            tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, second_token))
+                .await
+                .expect("Failed to open a ws connection with second user");
+
+        // Send three messages as the first user and clear both sockets
+        for n in 0..3 {
+            let message_data: WebSocketRequest = CreateMessageSchema {
+                channel_id: channel_one_id.to_string(),
+                contents: format!("Chat message {}", n),
+                reply_to: None,
+            }
+            .into();
+            send_websocket_request(&mut first_socket, &message_data).await;
+        }
+        for _ in 0..3 {
+            receive_chat_message(&mut first_socket)
+                .await
+                .expect("Failed to receive a chat message when one was expected");
+            receive_chat_message(&mut second_socket)
+                .await
+                .expect("Failed to receive a chat message when one was expected");
+        }
+
+        // The second user has not read anything, the author has read their own messages
+        let second_user_channels = list_channels(&helper, second_token, "?subscribed=true")
+            .await
+            .expect("Failed to list channels the requester subscribes to");
+        let shared_channel = second_user_channels.iter().find(|c| c._id == channel_one_id).unwrap();
+        assert_eq!(shared_channel.unread_count, 3);
+        assert_eq!(shared_channel.first_unread_id, Some(1));
+        let first_user_channels = list_channels(&helper, token, "?subscribed=true")
+            .await
+            .expect("Failed to list channels the requester subscribes to");
+        let shared_channel = first_user_channels.iter().find(|c| c._id == channel_one_id).unwrap();
+        assert_eq!(shared_channel.unread_count, 0);
+        assert_eq!(shared_channel.first_unread_id, None);
+
+        // Mark the first two messages as read, the reader gets a confirmation and the other participant gets a receipt
+        let mark_read: WebSocketRequest = MarkReadSchema {
+            channel_id: channel_one_id.to_string(),
+            atomic_message_id: 2,
+            send_read_receipt: None,
+        }
+        .into();
+        send_websocket_request(&mut second_socket, &mark_read).await;
+        let marked_read = receive_read_receipt(&mut second_socket).await.expect("Failed to mark a channel as read");
+        assert_eq!(marked_read.last_read_atomic_id, 2);
+        let read_receipt = receive_read_receipt(&mut first_socket)
+            .await
+            .expect("Failed to receive a read receipt in a direct message");
+        assert_eq!(read_receipt, marked_read);
+        assert_eq!(read_receipt.user_id.as_str(), user_two_id);
+
+        let second_user_channels = list_channels(&helper, second_token, "?subscribed=true")
+            .await
+            .expect("Failed to list channels the requester subscribes to");
+        let shared_channel = second_user_channels.iter().find(|c| c._id == channel_one_id).unwrap();
+        assert_eq!(shared_channel.unread_count, 1);
+        assert_eq!(shared_channel.first_unread_id, Some(3));
+
+        // Marking an older message as read should not move the marker backwards
+        let stale_mark_read: WebSocketRequest = MarkReadSchema {
+            channel_id: channel_one_id.to_string(),
+            atomic_message_id: 1,
+            send_read_receipt: Some(false),
+        }
+        .into();
+        send_websocket_request(&mut second_socket, &stale_mark_read).await;
+        let marked_read = receive_read_receipt(&mut second_socket).await.expect("Failed to mark a channel as read");
+        assert_eq!(marked_read.last_read_atomic_id, 2);
+
+        // Marking past the most recent message is clamped to the most recent message
+        let future_mark_read: WebSocketRequest = MarkReadSchema {
+            channel_id: channel_one_id.to_string(),
+            atomic_message_id: 1000,
+            send_read_receipt: Some(false),
+        }
+        .into();
+        send_websocket_request(&mut second_socket, &future_mark_read).await;
+        let marked_read = receive_read_receipt(&mut second_socket).await.expect("Failed to mark a channel as read");
+        assert_eq!(marked_read.last_read_atomic_id, 3);
+
+        // Receipts were suppressed, so the next thing the first user receives is their own message
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "After the receipts".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &message_data).await;
+        let chat_message = receive_chat_message(&mut first_socket)
+            .await
+            .expect("Failed to receive a chat message when one was expected");
+        assert_eq!(chat_message.atomic_id, 4);
+
+        // Try to mark a channel the user is not in as read
+        let unauthorized_mark_read: WebSocketRequest = MarkReadSchema {
+            channel_id: channel_two_id.to_string(),
+            atomic_message_id: 1,
+            send_read_receipt: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &unauthorized_mark_read).await;
+        match receive_read_receipt(&mut first_socket).await {
+            Ok(_) => panic!("Should receive a WebSocketError when marking a channel the user is not subscribed to as read"),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_threads() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 2).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let channel_one_id = chat_helper.channels[0]._id.as_str();
+        let channel_two_id = chat_helper.channels[1]._id.as_str();
+
+        let (mut first_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+        let (mut second_socket, _second_response) =
+            tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, second_token))
+                .await
+                .expect("Failed to open a ws connection with second user");
+
+        // Create a message to reply to
+        let parent_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "Parent message".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &parent_data).await;
+        let parent = receive_chat_message(&mut first_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+        assert_eq!(parent.reply_count, 0);
+
+        // Reply to it a few times, with an unrelated message in the middle
+        for n in 0..5 {
+            let reply_data: WebSocketRequest = CreateMessageSchema {
+                channel_id: channel_one_id.to_string(),
+                contents: format!("Reply {}", n),
+                reply_to: Some(parent.id.clone()),
+            }
+            .into();
+            send_websocket_request(&mut first_socket, &reply_data).await;
+            let reply = receive_chat_message(&mut first_socket)
+                .await
+                .expect("Failed to receive a chat message after sending one");
+            assert_eq!(reply.reply_to, Some(parent.id.clone()));
+
+            if n == 2 {
+                let unrelated_data: WebSocketRequest = CreateMessageSchema {
+                    channel_id: channel_one_id.to_string(),
+                    contents: "Not in the thread".to_owned(),
+                    reply_to: None,
+                }
+                .into();
+                send_websocket_request(&mut first_socket, &unrelated_data).await;
+                receive_chat_message(&mut first_socket)
+                    .await
+                    .expect("Failed to receive a chat message after sending one");
+            }
+        }
+
+        // Get the first page of the thread
+        let get_thread: WebSocketRequest = GetThreadSchema {
+            channel_id: channel_one_id.to_string(),
+            message_id: parent.id.clone(),
+            after_atomic_id: 0,
+            message_count: 3,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &get_thread).await;
+        let first_page = receive_thread(&mut first_socket).await.expect("Failed to get a thread");
+        assert_eq!(first_page.parent.id, parent.id);
+        assert_eq!(first_page.parent.reply_count, 5);
+        assert_eq!(first_page.replies.len(), 3);
+        assert!(first_page.has_more);
+        assert_eq!(first_page.replies[0].contents.as_str(), "Reply 0");
+        assert_eq!(first_page.replies[2].contents.as_str(), "Reply 2");
+
+        // Get the rest of the thread, skipping over the unrelated message
+        let get_thread: WebSocketRequest = GetThreadSchema {
+            channel_id: channel_one_id.to_string(),
+            message_id: parent.id.clone(),
+            after_atomic_id: first_page.replies[2].atomic_id,
+            message_count: 3,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &get_thread).await;
+        let second_page = receive_thread(&mut first_socket).await.expect("Failed to get a thread");
+        assert_eq!(second_page.replies.len(), 2);
+        assert!(!second_page.has_more);
+        assert_eq!(second_page.replies[0].contents.as_str(), "Reply 3");
+        assert_eq!(second_page.replies[1].contents.as_str(), "Reply 4");
+
+        // Try to reply to a message which does not exist
+        let bad_reply: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "Bad reply".to_owned(),
+            reply_to: Some(FAKE_MONGO_ID.to_string()),
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &bad_reply).await;
+        match receive_chat_message(&mut first_socket).await {
+            Ok(_) => panic!("Should receive a WebSocketError after replying to a message that doesn't exist"),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
+        }
+
+        // Try to reply to a message in a different channel
+        let other_channel_message: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_two_id.to_string(),
+            contents: "Other channel".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut second_socket, &other_channel_message).await;
+        let other_channel_message = receive_chat_message(&mut second_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+        let cross_channel_reply: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "Cross channel reply".to_owned(),
+            reply_to: Some(other_channel_message.id),
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &cross_channel_reply).await;
+        match receive_chat_message(&mut first_socket).await {
+            Ok(_) => panic!("Should receive a WebSocketError after replying to a message in another channel"),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
+        }
+
+        // Try to get a thread for a message that does not exist
+        let missing_thread: WebSocketRequest = GetThreadSchema {
+            channel_id: channel_one_id.to_string(),
+            message_id: FAKE_MONGO_ID.to_string(),
+            after_atomic_id: 0,
+            message_count: 3,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &missing_thread).await;
+        match receive_thread(&mut first_socket).await {
+            Ok(_) => panic!("Should receive a WebSocketError when getting a thread for a message that doesn't exist"),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::NotFound),
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_search() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 2).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let user_two_id = chat_helper.users[1].id.as_str();
+        let channel_one_id = chat_helper.channels[0]._id.as_str();
+        let channel_two_id = chat_helper.channels[1]._id.as_str();
+
+        // Subscribe to the first channel with the second user
+        subscribe_to_channel(&helper, second_token, channel_one_id)
+            .await
+            .expect("Failed to subscribe to another users chat channel");
+
+        let (mut first_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+        let (mut second_socket, _second_response) =
+            tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, second_token))
+                .await
+                .expect("Failed to open a ws connection with second user");
+
+        // Fill the shared channel as the first user
+        for contents in ["hello there", "the weather is nice", "pineapple pizza", "what about lunch"] {
+            let message_data: WebSocketRequest = CreateMessageSchema {
+                channel_id: channel_one_id.to_string(),
+                contents: contents.to_owned(),
+                reply_to: None,
+            }
+            .into();
+            send_websocket_request(&mut first_socket, &message_data).await;
+            receive_chat_message(&mut first_socket)
+                .await
+                .expect("Failed to receive a chat message after sending one");
+            receive_chat_message(&mut second_socket)
+                .await
+                .expect("Failed to receive a chat message when one was expected");
+        }
+
+        // Send a message in each channel as the second user
+        for (channel_id, contents) in [(channel_one_id, "pineapple juice"), (channel_two_id, "secret pineapple")] {
+            let message_data: WebSocketRequest = CreateMessageSchema {
+                channel_id: channel_id.to_string(),
+                contents: contents.to_owned(),
+                reply_to: None,
+            }
+            .into();
+            send_websocket_request(&mut second_socket, &message_data).await;
+            receive_chat_message(&mut second_socket)
+                .await
+                .expect("Failed to receive a chat message after sending one");
+        }
+
+        // Search as the first user, the message in the channel they are not in should not be found
+        let results = search_messages(&helper, token, "?query=pineapple&context=1")
+            .await
+            .expect("Failed to search chat messages");
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.message.channel_id.as_str() == channel_one_id));
+        let pizza = results.iter().find(|r| r.message.contents.as_str() == "pineapple pizza").unwrap();
+        assert_eq!(pizza.context_before.len(), 1);
+        assert_eq!(pizza.context_before[0].contents.as_str(), "the weather is nice");
+        assert_eq!(pizza.context_after.len(), 1);
+        assert_eq!(pizza.context_after[0].contents.as_str(), "what about lunch");
+
+        // Filter by author
+        let results = search_messages(&helper, token, format!("?query=pineapple&author_id={}", user_two_id).as_str())
+            .await
+            .expect("Failed to search chat messages by author");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message.contents.as_str(), "pineapple juice");
+
+        // The second user is in both channels
+        let results = search_messages(&helper, second_token, "?query=pineapple")
+            .await
+            .expect("Failed to search chat messages");
+        assert_eq!(results.len(), 3);
+
+        // Filter by channel
+        let results = search_messages(&helper, second_token, format!("?query=pineapple&channel_id={}", channel_two_id).as_str())
+            .await
+            .expect("Failed to search chat messages by channel");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message.contents.as_str(), "secret pineapple");
+
+        // Filter by date, nothing was sent in the future
+        let results = search_messages(&helper, token, "?query=pineapple&from=99999999999")
+            .await
+            .expect("Failed to search chat messages by date");
+        assert_eq!(results.len(), 0);
+
+        // Try to search a channel the user is not in
+        match search_messages(&helper, token, format!("?query=pineapple&channel_id={}", channel_two_id).as_str()).await {
+            Ok(_) => panic!("Searching a channel the user is not in should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "Searching a channel the user is not in should 400"),
+        }
+
+        // Try to search with an empty query
+        match search_messages(&helper, token, "?query=").await {
+            Ok(_) => panic!("Searching with an empty query should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "Searching with an empty query should 400"),
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_direct_messages() {
+        let helper = TestHelper::init().await;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 3).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let third_token = chat_helper.tokens[2].as_str();
+        let user_one = chat_helper.users[0].clone();
+        let user_two = chat_helper.users[1].clone();
+
+        // Open a direct message with the second user
+        let direct_message = open_direct_message(&helper, token, user_two.id.as_str())
+            .await
+            .expect("Failed to open a direct message");
+        assert_eq!(direct_message.channel.channel_type, ChannelType::DirectMessage);
+        assert_eq!(direct_message.channel.subscribers.len(), 2);
+        assert_eq!(direct_message.other_user, user_two);
+
+        // Opening it again from either side finds the same channel
+        let same_direct_message = open_direct_message(&helper, token, user_two.id.as_str())
+            .await
+            .expect("Failed to open an existing direct message");
+        assert_eq!(same_direct_message.channel._id, direct_message.channel._id);
+        let other_side = open_direct_message(&helper, second_token, user_one.id.as_str())
+            .await
+            .expect("Failed to open an existing direct message as the other participant");
+        assert_eq!(other_side.channel._id, direct_message.channel._id);
+        assert_eq!(other_side.other_user, user_one);
+
+        // A second direct message with another user is a different channel, even though it was opened by the same user
+        let third_user_dm = open_direct_message(&helper, token, chat_helper.users[2].id.as_str())
+            .await
+            .expect("Failed to open a second direct message");
+        assert_ne!(third_user_dm.channel._id, direct_message.channel._id);
+
+        // Try to open a direct message with yourself
+        match open_direct_message(&helper, token, user_one.id.as_str()).await {
+            Ok(_) => panic!("Opening a direct message with yourself should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "Opening a direct message with yourself should 400"),
+        }
+
+        // Try to open a direct message with a user that does not exist
+        match open_direct_message(&helper, token, FAKE_MONGO_ID).await {
+            Ok(_) => panic!("Opening a direct message with a user that doesn't exist should fail"),
+            Err((status_code, _msg)) => assert_eq!(
+                status_code,
+                StatusCode::NOT_FOUND,
+                "Opening a direct message with a user that doesn't exist should 404"
+            ),
+        }
+
+        // A third user cannot subscribe to or view a direct message
+        match subscribe_to_channel(&helper, third_token, direct_message.channel._id.as_str()).await {
+            Ok(_) => panic!("Subscribing to someone else's direct message should fail"),
+            Err((status_code, _msg)) => assert_eq!(
+                status_code,
+                StatusCode::FORBIDDEN,
+                "Subscribing to someone else's direct message should 403"
+            ),
+        }
+        match get_channel_by_id(&helper, third_token, direct_message.channel._id.as_str()).await {
+            Ok(_) => panic!("Getting someone else's direct message should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Getting someone else's direct message should 403"),
+        }
+        let third_user_channels = list_channels(&helper, third_token, "?subscribed=false")
+            .await
+            .expect("Failed to list channels the requester is not subscribed to");
+        assert!(third_user_channels.iter().all(|c| c.channel_type != ChannelType::DirectMessage));
+
+        // List direct messages for each user
+        let first_user_dms = list_direct_messages(&helper, token).await.expect("Failed to list direct messages");
+        assert_eq!(first_user_dms.len(), 2);
+        let second_user_dms = list_direct_messages(&helper, second_token).await.expect("Failed to list direct messages");
+        assert_eq!(second_user_dms.len(), 1);
+        assert_eq!(second_user_dms[0].other_user, user_one);
+
+        // A participant who left gets messages again once they reopen the DM
+        let dm_channel_id = direct_message.channel._id.as_str();
+        let (mut first_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", helper.address, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+        let (mut second_socket, _response) =
+            tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", helper.address, second_token))
+                .await
+                .expect("Failed to open a ws connection with second user");
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: dm_channel_id.to_string(),
+            contents: "Before leaving".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &message_data).await;
+        receive_chat_message(&mut first_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+        receive_chat_message(&mut second_socket)
+            .await
+            .expect("Failed to receive a direct message");
+
+        unsubscribe_from_channel(&helper, second_token, dm_channel_id)
+            .await
+            .expect("Failed to leave a direct message");
+        // Sent while the second user is gone, so the DM's readers are cached without them
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: dm_channel_id.to_string(),
+            contents: "While away".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &message_data).await;
+        receive_chat_message(&mut first_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+
+        // The other participant opening it again doesn't pull them back in
+        let reopened = open_direct_message(&helper, token, user_two.id.as_str())
+            .await
+            .expect("Failed to reopen a direct message");
+        assert_eq!(reopened.channel.subscribers, vec![user_one.clone()]);
+        let reopened = open_direct_message(&helper, second_token, user_one.id.as_str())
+            .await
+            .expect("Failed to reopen a direct message after leaving it");
+        assert_eq!(reopened.channel._id.as_str(), dm_channel_id);
+        assert_eq!(reopened.channel.subscribers.len(), 2);
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: dm_channel_id.to_string(),
+            contents: "Welcome back".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &message_data).await;
+        let returned_message = receive_chat_message(&mut second_socket)
+            .await
+            .expect("Failed to receive a direct message after it was reopened");
+        assert_eq!(returned_message.contents.as_str(), "Welcome back");
+
+        // Whoever opened the DM can leave it too
+        let left = unsubscribe_from_channel(&helper, token, dm_channel_id)
+            .await
+            .expect("Failed to leave a direct message you opened");
+        assert_eq!(left.subscribers, vec![user_two.clone()]);
+    }
+
+    #[tokio::test]
+    async fn chat_private_channels_and_moderation() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 3).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let third_token = chat_helper.tokens[2].as_str();
+        let user_one_id = chat_helper.users[0].id.as_str();
+        let user_two_id = chat_helper.users[1].id.as_str();
+        let user_three_id = chat_helper.users[2].id.as_str();
+
+        let private_data = CreateChannelSchema {
+            name: Some("Private".to_string()),
+            channel_type: 1,
+            slug: "private_channel".to_string(),
+            is_private: true,
+        };
+        let private_channel = create_chat_channel(&helper, token, &private_data)
+            .await
+            .expect("Failed to create a private channel");
+        assert!(private_channel.is_private);
+        let channel_id = private_channel._id.as_str();
+
+        // A private channel can't be found, viewed or subscribed to without an invite
+        let unsubscribed = list_channels(&helper, second_token, "?subscribed=false")
+            .await
+            .expect("Failed to list channels the requester is not subscribed to");
+        assert!(unsubscribed.iter().all(|c| c._id.as_str() != channel_id));
+        match get_channel_by_id(&helper, second_token, channel_id).await {
+            Ok(_) => panic!("Getting a private channel without being in it should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Getting a private channel should 403"),
+        }
+        match subscribe_to_channel(&helper, second_token, channel_id).await {
+            Ok(_) => panic!("Subscribing to a private channel should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Subscribing to a private channel should 403"),
+        }
+
+        // Only the owner can create invites
+        let invite_data = CreateInviteSchema {
+            expires_in: Some(3600),
+            max_uses: Some(1),
+        };
+        match create_invite(&helper, second_token, channel_id, &invite_data).await {
+            Ok(_) => panic!("Creating an invite for a channel you don't own should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Creating an invite as a non-owner should 403"),
+        }
+        let bad_invite = CreateInviteSchema {
+            expires_in: None,
+            max_uses: Some(0),
+        };
+        match create_invite(&helper, token, channel_id, &bad_invite).await {
+            Ok(_) => panic!("Creating an invite with no uses should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "Creating an invite with no uses should 400"),
+        }
+        for expires_in in [MAX_INVITE_LIFETIME + 1, i64::MAX] {
+            let forever_invite = CreateInviteSchema {
+                expires_in: Some(expires_in),
+                max_uses: None,
+            };
+            match create_invite(&helper, token, channel_id, &forever_invite).await {
+                Ok(_) => panic!("Creating an invite which expires in {expires_in} seconds should fail"),
+                Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "Creating an invite which lasts too long should 400"),
+            }
+        }
+        let invite = create_invite(&helper, token, channel_id, &invite_data)
+            .await
+            .expect("Failed to create an invite");
+        assert_eq!(invite.uses, 0);
+
+        let (mut first_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+
+        // Join with the invite, the owner is notified
+        let joined = join_with_invite(&helper, second_token, invite.code.as_str())
+            .await
+            .expect("Failed to join a channel with an invite");
+        assert!(joined.subscribers.iter().any(|u| u.id.as_str() == user_two_id));
+        let event = receive_channel_event(&mut first_socket).await;
+        assert_eq!(event.channel_id.as_str(), channel_id);
+        assert_eq!(event.event, ChannelEvent::MemberJoined(user_two_id.to_string()));
+
+        // The invite only had one use
+        match join_with_invite(&helper, third_token, invite.code.as_str()).await {
+            Ok(_) => panic!("Joining with a used up invite should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "Joining with a used up invite should 400"),
+        }
+
+        // A revoked invite can't be used
+        let second_invite = create_invite(
+            &helper,
+            token,
+            channel_id,
+            &CreateInviteSchema {
+                expires_in: None,
+                max_uses: None,
+            },
+        )
+        .await
+        .expect("Failed to create an invite");
+        let invites = list_invites(&helper, token, channel_id).await.expect("Failed to list invites");
+        assert_eq!(invites.len(), 2);
+        revoke_invite(&helper, token, second_invite.code.as_str())
+            .await
+            .expect("Failed to revoke an invite");
+        match join_with_invite(&helper, third_token, second_invite.code.as_str()).await {
+            Ok(_) => panic!("Joining with a revoked invite should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::NOT_FOUND, "Joining with a revoked invite should 404"),
+        }
+
+        // Only the owner can moderate
+        match moderate_channel_member(&helper, second_token, channel_id, "kick", user_one_id).await {
+            Ok(_) => panic!("Kicking as a non-owner should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Kicking as a non-owner should 403"),
+        }
+        match moderate_channel_member(&helper, token, channel_id, "kick", user_one_id).await {
+            Ok(_) => panic!("The owner kicking themselves should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "The owner kicking themselves should 400"),
+        }
+
+        // Kick the second user
+        let kicked = moderate_channel_member(&helper, token, channel_id, "kick", user_two_id)
+            .await
+            .expect("Failed to kick a member");
+        assert!(kicked.subscribers.iter().all(|u| u.id.as_str() != user_two_id));
+        let event = receive_channel_event(&mut first_socket).await;
+        assert_eq!(event.event, ChannelEvent::MemberKicked(user_two_id.to_string()));
+
+        // Ban the third user, who then can't join with a fresh invite
+        let banned = moderate_channel_member(&helper, token, channel_id, "ban", user_three_id)
+            .await
+            .expect("Failed to ban a user");
+        assert!(banned.subscribers.iter().all(|u| u.id.as_str() != user_three_id));
+        let event = receive_channel_event(&mut first_socket).await;
+        assert_eq!(event.event, ChannelEvent::MemberBanned(user_three_id.to_string()));
+        let open_invite = create_invite(
+            &helper,
+            token,
+            channel_id,
+            &CreateInviteSchema {
+                expires_in: None,
+                max_uses: None,
+            },
+        )
+        .await
+        .expect("Failed to create an invite");
+        match join_with_invite(&helper, third_token, open_invite.code.as_str()).await {
+            Ok(_) => panic!("A banned user joining should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "A banned user joining should 403"),
+        }
+        moderate_channel_member(&helper, token, channel_id, "unban", user_three_id)
+            .await
+            .expect("Failed to unban a user");
+        join_with_invite(&helper, third_token, open_invite.code.as_str())
+            .await
+            .expect("Failed to join a channel after being unbanned");
+        let event = receive_channel_event(&mut first_socket).await;
+        assert_eq!(event.event, ChannelEvent::MemberJoined(user_three_id.to_string()));
+
+        // Rename the channel and make it public
+        let update_data = UpdateChannelSchema {
+            name: Some("Not Private".to_string()),
+            is_private: Some(false),
+        };
+        let updated = update_channel(&helper, token, channel_id, &update_data)
+            .await
+            .expect("Failed to update a channel");
+        assert_eq!(updated.name, Some("Not Private".to_string()));
+        assert!(!updated.is_private);
+        let event = receive_channel_event(&mut first_socket).await;
+        assert_eq!(
+            event.event,
+            ChannelEvent::ChannelUpdated {
+                name: Some("Not Private".to_string()),
+                is_private: false
+            }
+        );
+        subscribe_to_channel(&helper, second_token, channel_id)
+            .await
+            .expect("Failed to subscribe to a channel after it was made public");
+        receive_channel_event(&mut first_socket).await;
+
+        // Transfer ownership to the third user, the old owner can no longer moderate
+        match moderate_channel_member(&helper, token, channel_id, "transfer", FAKE_MONGO_ID).await {
+            Ok(_) => panic!("Transferring ownership to a user not in the channel should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "Transferring to a non-member should 400"),
+        }
+        // Slugs are unique per owner, so the new owner can't already have a channel with the same one
+        let same_slug_data = CreateChannelSchema {
+            name: Some("Same Slug".to_string()),
+            channel_type: 1,
+            slug: "private_channel".to_string(),
+            is_private: false,
+        };
+        let same_slug_channel = create_chat_channel(&helper, third_token, &same_slug_data)
+            .await
+            .expect("Failed to create a channel");
+        match moderate_channel_member(&helper, token, channel_id, "transfer", user_three_id).await {
+            Ok(_) => panic!("Transferring ownership to a user with a channel of the same slug should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "A slug collision on transfer should 400"),
+        }
+        delete_channel(&helper, third_token, same_slug_channel._id.as_str())
+            .await
+            .expect("Failed to delete a channel");
+
+        let transferred = moderate_channel_member(&helper, token, channel_id, "transfer", user_three_id)
+            .await
+            .expect("Failed to transfer ownership");
+        assert_eq!(transferred.owner_id.as_str(), user_three_id);
+        let event = receive_channel_event(&mut first_socket).await;
+        assert_eq!(event.event, ChannelEvent::OwnershipTransferred(user_three_id.to_string()));
+        match delete_channel(&helper, token, channel_id).await {
+            Ok(_) => panic!("Deleting a channel you no longer own should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Deleting a channel as a non-owner should 403"),
+        }
+
+        // Delete the channel as the new owner
+        delete_channel(&helper, third_token, channel_id)
+            .await
+            .expect("Failed to delete a channel");
+        let event = receive_channel_event(&mut first_socket).await;
+        assert_eq!(event.event, ChannelEvent::ChannelDeleted);
+        match get_channel_by_id(&helper, token, channel_id).await {
+            Ok(_) => panic!("Getting a deleted channel should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::NOT_FOUND, "Getting a deleted channel should 404"),
+        }
+        match join_with_invite(&helper, second_token, open_invite.code.as_str()).await {
+            Ok(_) => panic!("Using an invite for a deleted channel should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::NOT_FOUND, "Using an invite for a deleted channel should 404"),
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_servers() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 3).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let third_token = chat_helper.tokens[2].as_str();
+        let user_one_id = chat_helper.users[0].id.as_str();
+        let user_two_id = chat_helper.users[1].id.as_str();
+
+        // Creating a server also creates a general channel
+        let server_data = CreateServerSchema {
+            name: "My Server".to_string(),
+        };
+        let created = create_server(&helper, token, &server_data).await.expect("Failed to create a server");
+        let server_id = created.server.id.as_str();
+        assert_eq!(created.server.owner_id.as_str(), user_one_id);
+        assert_eq!(created.server.members.len(), 1);
+        assert_eq!(created.channels.len(), 1);
+        assert_eq!(created.channels[0].channel_type, ChannelType::Server);
+        assert_eq!(created.channels[0].server_id.as_deref(), Some(server_id));
+        let general_id = created.channels[0]._id.as_str();
+
+        // Server channels can only be made and joined through the server
+        let server_channel_data = CreateChannelSchema {
+            name: None,
+            channel_type: 2,
+            slug: "server_channel".to_string(),
+            is_private: false,
+        };
+        match create_chat_channel(&helper, token, &server_channel_data).await {
+            Ok(_) => panic!("Creating a server channel outside of a server should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "Creating a server channel directly should 400"),
+        }
+        match subscribe_to_channel(&helper, second_token, general_id).await {
+            Ok(_) => panic!("Subscribing to a server channel should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Subscribing to a server channel should 403"),
+        }
+        match get_server(&helper, second_token, server_id).await {
+            Ok(_) => panic!("Getting a server you are not a member of should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Getting a server as a non-member should 403"),
+        }
+
+        // Join with an invite, a server invite can't be used as a channel invite
+        let invite = create_server_invite(
+            &helper,
+            token,
+            server_id,
+            &CreateInviteSchema {
+                expires_in: None,
+                max_uses: None,
+            },
+        )
+        .await
+        .expect("Failed to create a server invite");
+        assert_eq!(invite.server_id.as_deref(), Some(server_id));
+        let forever_invite = CreateInviteSchema {
+            expires_in: Some(i64::MAX),
+            max_uses: None,
+        };
+        match create_server_invite(&helper, token, server_id, &forever_invite).await {
+            Ok(_) => panic!("Creating a server invite which never really expires should fail"),
+            Err((status_code, _msg)) => assert_eq!(
+                status_code,
+                StatusCode::BAD_REQUEST,
+                "Creating a server invite which lasts too long should 400"
+            ),
+        }
+        match join_with_invite(&helper, second_token, invite.code.as_str()).await {
+            Ok(_) => panic!("Joining a channel with a server invite should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "Using a server invite for a channel should 400"),
+        }
+        let joined = join_server(&helper, second_token, invite.code.as_str())
+            .await
+            .expect("Failed to join a server");
+        assert_eq!(joined.server.members.len(), 2);
+        assert!(joined.channels[0].subscribers.iter().any(|u| u.id.as_str() == user_two_id));
+        match join_server(&helper, second_token, invite.code.as_str()).await {
+            Ok(_) => panic!("Joining a server twice should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "Joining a server twice should 400"),
+        }
+        let servers = list_servers(&helper, second_token).await.expect("Failed to list servers");
+        assert_eq!(servers.len(), 1);
+        let servers = list_servers(&helper, third_token).await.expect("Failed to list servers");
+        assert_eq!(servers.len(), 0);
+
+        // Members can't manage the server by default
+        let announcements_data = CreateServerChannelSchema {
+            name: "announcements".to_string(),
+            slug: "announcements".to_string(),
+            category_id: None,
+        };
+        match create_server_channel(&helper, second_token, server_id, &announcements_data).await {
+            Ok(_) => panic!("Creating a channel without the manage permission should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Creating a channel as a non-manager should 403"),
+        }
+
+        // Set up a category, a role and channels with overrides
+        let with_category = create_server_category(&helper, token, server_id, &CreateServerCategorySchema { name: "Info".to_string() })
+            .await
+            .expect("Failed to create a category");
+        let category_id = with_category.categories[0].id.clone();
+        let bad_role = CreateServerRoleSchema {
+            name: "Bad".to_string(),
+            permissions: 1 << 10,
+        };
+        match create_server_role(&helper, token, server_id, &bad_role).await {
+            Ok(_) => panic!("Creating a role with invalid permissions should fail"),
+            Err((status_code, _msg)) => assert_eq!(
+                status_code,
+                StatusCode::BAD_REQUEST,
+                "Creating a role with invalid permissions should 400"
+            ),
+        }
+        let role_data = CreateServerRoleSchema {
+            name: "Moderator".to_string(),
+            permissions: permissions::PIN,
+        };
+        let with_role = create_server_role(&helper, token, server_id, &role_data)
+            .await
+            .expect("Failed to create a role");
+        let role_id = with_role.roles[0].id.clone();
+
+        let announcements_data = CreateServerChannelSchema {
+            name: "announcements".to_string(),
+            slug: "announcements".to_string(),
+            category_id: Some(category_id.clone()),
+        };
+        let announcements = create_server_channel(&helper, token, server_id, &announcements_data)
+            .await
+            .expect("Failed to create a server channel");
+        assert_eq!(announcements.category_id, Some(category_id));
+        let announcements_id = announcements._id.as_str();
+        let everyone_cant_send = PermissionOverride {
+            role_id: server_id.to_string(),
+            allow: 0,
+            deny: permissions::SEND,
+        };
+        set_permission_override(&helper, token, server_id, announcements_id, &everyone_cant_send)
+            .await
+            .expect("Failed to set a permission override");
+        let moderators_can_send = PermissionOverride {
+            role_id: role_id.clone(),
+            allow: permissions::SEND,
+            deny: 0,
+        };
+        set_permission_override(&helper, token, server_id, announcements_id, &moderators_can_send)
+            .await
+            .expect("Failed to set a permission override");
+
+        let secret_data = CreateServerChannelSchema {
+            name: "secret".to_string(),
+            slug: "secret".to_string(),
+            category_id: None,
+        };
+        let secret = create_server_channel(&helper, token, server_id, &secret_data)
+            .await
+            .expect("Failed to create a server channel");
+        let secret_id = secret._id.as_str();
+        let everyone_cant_read = PermissionOverride {
+            role_id: server_id.to_string(),
+            allow: 0,
+            deny: permissions::READ,
+        };
+        set_permission_override(&helper, token, server_id, secret_id, &everyone_cant_read)
+            .await
+            .expect("Failed to set a permission override");
+
+        // The member can only see the channels they can read
+        let member_view = get_server(&helper, second_token, server_id).await.expect("Failed to get a server");
+        assert_eq!(member_view.channels.len(), 2);
+        assert!(member_view.channels.iter().all(|c| c._id.as_str() != secret_id));
+        let owner_view = get_server(&helper, token, server_id).await.expect("Failed to get a server");
+        assert_eq!(owner_view.channels.len(), 3);
+        match get_channel_by_id(&helper, second_token, secret_id).await {
+            Ok(_) => panic!("Getting a channel you can't read should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Getting a channel you can't read should 403"),
+        }
+
+        let (mut first_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+        let (mut second_socket, _second_response) =
+            tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, second_token))
+                .await
+                .expect("Failed to open a ws connection with second user");
+
+        // Everyone was denied sending in announcements
+        let announcement: WebSocketRequest = CreateMessageSchema {
+            channel_id: announcements_id.to_string(),
+            contents: "Hello everyone".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut second_socket, &announcement).await;
+        match receive_chat_message(&mut second_socket).await {
+            Ok(_) => panic!("Sending a message without the send permission should fail"),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::Forbidden),
+        }
+
+        // A message in general reaches both members
+        let welcome: WebSocketRequest = CreateMessageSchema {
+            channel_id: general_id.to_string(),
+            contents: "Welcome".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &welcome).await;
+        receive_chat_message(&mut first_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+        let welcome_message = receive_chat_message(&mut second_socket)
+            .await
+            .expect("Failed to receive a chat message when one was expected");
+
+        // Pinning needs the pin permission
+        let pin: WebSocketRequest = PinMessageSchema {
+            channel_id: general_id.to_string(),
+            message_id: welcome_message.id.clone(),
+            pinned: true,
+        }
+        .into();
+        send_websocket_request(&mut second_socket, &pin).await;
+        match receive_message_pinned(&mut second_socket).await {
+            Ok(_) => panic!("Pinning a message without the pin permission should fail"),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::Forbidden),
+        }
+
+        // Give the member the moderator role, they can now pin and send in announcements
+        set_member_roles(&helper, token, server_id, user_two_id, &[FAKE_MONGO_ID.to_string()])
+            .await
+            .expect_err("Setting a role which doesn't exist should fail");
+        set_member_roles(&helper, token, server_id, user_two_id, &[role_id])
+            .await
+            .expect("Failed to set a members roles");
+        send_websocket_request(&mut second_socket, &pin).await;
+        let pinned = receive_message_pinned(&mut second_socket).await.expect("Failed to pin a message");
+        assert_eq!(pinned.message_id, welcome_message.id);
+        assert!(pinned.pinned);
+        let pinned = receive_message_pinned(&mut first_socket)
+            .await
+            .expect("Failed to receive a pinned message");
+        assert_eq!(pinned.message_id, welcome_message.id);
+        let general = get_channel_by_id(&helper, token, general_id)
+            .await
+            .expect("Failed to get a server channel");
+        assert_eq!(general.pinned_messages, vec![welcome_message.id.clone()]);
+
+        // The member can't read the secret channel
+        let secret_state: WebSocketRequest = RequestMessagesSchema {
+            message_count: 10,
+            atomic_message_id: 10,
+            channel_id: secret_id.to_string(),
+        }
+        .into();
+        send_websocket_request(&mut second_socket, &secret_state).await;
+        match receive_chat_state(&mut second_socket).await {
+            Ok(_) => panic!("Getting messages in a channel you can't read should fail"),
+            Err(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
+        }
+
+        send_websocket_request(&mut second_socket, &announcement).await;
+        receive_chat_message(&mut second_socket)
+            .await
+            .expect("Failed to send a message after being given the send permission");
+        receive_chat_message(&mut first_socket)
+            .await
+            .expect("Failed to receive a chat message when one was expected");
+
+        // Remove the member, they lose access to the server and its channels
+        match remove_server_member(&helper, token, server_id, user_one_id).await {
+            Ok(_) => panic!("The owner leaving their server should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST, "The owner leaving their server should 400"),
+        }
+        remove_server_member(&helper, token, server_id, user_two_id)
+            .await
+            .expect("Failed to remove a server member");
+        match get_server(&helper, second_token, server_id).await {
+            Ok(_) => panic!("Getting a server after being removed should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN, "Getting a server after being removed should 403"),
+        }
+        let general = get_channel_by_id(&helper, token, general_id)
+            .await
+            .expect("Failed to get a server channel");
+        assert!(general.subscribers.iter().all(|u| u.id.as_str() != user_two_id));
+    }
+
+    #[tokio::test]
+    async fn chat_multiple_connections() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 2).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let user_two_id = chat_helper.users[1].id.as_str();
+        let channel_one_id = chat_helper.channels[0]._id.as_str();
+
+        subscribe_to_channel(&helper, second_token, channel_one_id)
+            .await
+            .expect("Failed to subscribe to another users chat channel");
+
+        // The first user is connected from two devices at once
+        let (mut first_device, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with the first device");
+        let (mut second_device, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with the second device");
+        let (mut second_user_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, second_token))
+            .await
+            .expect("Failed to open a ws connection with second user");
+
+        // A message from another user reaches both devices
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "Hello to every device".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut second_user_socket, &message_data).await;
+        let sent_message = receive_chat_message(&mut second_user_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+        let first_device_message = receive_chat_message(&mut first_device)
+            .await
+            .expect("First device failed to receive a chat message");
+        let second_device_message = receive_chat_message(&mut second_device)
+            .await
+            .expect("Second device failed to receive a chat message");
+        assert_eq!(first_device_message, sent_message);
+        assert_eq!(second_device_message, sent_message);
+        assert_eq!(sent_message.author_id.as_str(), user_two_id);
+
+        // Marking the channel read on one device tells the other device too
+        let mark_read: WebSocketRequest = MarkReadSchema {
+            channel_id: channel_one_id.to_string(),
+            atomic_message_id: sent_message.atomic_id,
+            send_read_receipt: None,
+        }
+        .into();
+        send_websocket_request(&mut first_device, &mark_read).await;
+        let marked_read = receive_read_receipt(&mut first_device).await.expect("Failed to mark a channel as read");
+        let synced_read = receive_read_receipt(&mut second_device)
+            .await
+            .expect("Second device failed to receive the read marker");
+        assert_eq!(marked_read, synced_read);
+
+        // Errors only go back to the device which made the request
+        send_arbitrary_data(&mut first_device, "not a real packet".to_owned()).await;
+        let websocket_error = receive_chat_message(&mut first_device).await;
+        assert!(websocket_error.is_err());
+
+        // Closing one device leaves the other connected
+        first_device.close(None).await.expect("Failed to close the first device");
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "Still here?".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut second_user_socket, &message_data).await;
+        let second_device_message = receive_chat_message(&mut second_device)
+            .await
+            .expect("Second device stopped receiving messages after the first closed");
+        assert_eq!(second_device_message.contents.as_str(), "Still here?");
+    }
+
+    #[tokio::test]
+    async fn chat_resume() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 2).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let channel_one_id = chat_helper.channels[0]._id.as_str();
+        let channel_two_id = chat_helper.channels[1]._id.as_str();
+
+        subscribe_to_channel(&helper, second_token, channel_one_id)
+            .await
+            .expect("Failed to subscribe to another users chat channel");
+
+        let (mut first_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+        let (mut second_socket, _second_response) =
+            tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, second_token))
+                .await
+                .expect("Failed to open a ws connection with second user");
+
+        // The second user sees the first message and then drops their connection
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "Before the drop".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &message_data).await;
+        let last_seen = receive_chat_message(&mut second_socket)
+            .await
+            .expect("Failed to receive a chat message when one was expected");
+        receive_chat_message(&mut first_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+        second_socket.close(None).await.expect("Failed to close the second socket");
+
+        // Messages sent while the second user is gone
+        for n in 0..2 {
+            let message_data: WebSocketRequest = CreateMessageSchema {
+                channel_id: channel_one_id.to_string(),
+                contents: format!("Missed message {}", n),
+                reply_to: None,
+            }
+            .into();
+            send_websocket_request(&mut first_socket, &message_data).await;
+            receive_chat_message(&mut first_socket)
+                .await
+                .expect("Failed to receive a chat message after sending one");
+        }
+
+        // Reconnect and catch up, a channel that doesn't exist and one the user was never in are reported as lost
+        let (mut second_socket, _second_response) =
+            tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, second_token))
+                .await
+                .expect("Failed to reconnect with second user");
+        let resume: WebSocketRequest = ResumeSchema {
+            channels: vec![
+                ResumeChannelSchema {
+                    channel_id: channel_one_id.to_string(),
+                    last_atomic_id: last_seen.atomic_id,
+                },
+                ResumeChannelSchema {
+                    channel_id: FAKE_MONGO_ID.to_string(),
+                    last_atomic_id: 0,
+                },
+            ],
+        }
+        .into();
+        send_websocket_request(&mut second_socket, &resume).await;
+        let resumed = receive_resumed(&mut second_socket).await.expect("Failed to resume after reconnecting");
+        assert_eq!(resumed.channels.len(), 1);
+        let resumed_channel = &resumed.channels[0];
+        assert_eq!(resumed_channel.channel_id.as_str(), channel_one_id);
+        assert!(!resumed_channel.has_more);
+        let contents: Vec<&str> = resumed_channel.messages.iter().map(|m| m.contents.as_str()).collect();
+        assert_eq!(contents, vec!["Missed message 0", "Missed message 1"]);
+        assert_eq!(resumed.lost_channels, vec![FAKE_MONGO_ID.to_string()]);
+
+        // Channels the user can't read are lost rather than replayed
+        let resume: WebSocketRequest = ResumeSchema {
+            channels: vec![ResumeChannelSchema {
+                channel_id: channel_two_id.to_string(),
+                last_atomic_id: 0,
+            }],
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &resume).await;
+        let resumed = receive_resumed(&mut first_socket).await.expect("Failed to resume");
+        assert!(resumed.channels.is_empty());
+        assert_eq!(resumed.lost_channels, vec![channel_two_id.to_string()]);
+
+        // Too many channels at once
+        let resume: WebSocketRequest = ResumeSchema {
+            channels: (0..51)
+                .map(|_| ResumeChannelSchema {
+                    channel_id: channel_one_id.to_string(),
+                    last_atomic_id: 0,
+                })
+                .collect(),
+        }
+        .into();
+        send_websocket_request(&mut second_socket, &resume).await;
+        let resume_error = receive_resumed(&mut second_socket).await;
+        assert!(resume_error.is_err());
+        assert_eq!(resume_error.unwrap_err().status_code, WebSocketErrorCode::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn chat_request_ids() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 1).await;
+        let token = chat_helper.tokens[0].as_str();
+        let channel_id = chat_helper.channels[0]._id.as_str();
+
+        let (mut socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection");
+
+        // The MessageCreated carries the request_id, the broadcast of the message does not belong to a request
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_id.to_string(),
+            contents: "Correlated".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request_with_id(&mut socket, &message_data, "create-1").await;
+        for _ in 0..2 {
+            let frame = receive_response_frame(&mut socket).await;
+            match frame.response {
+                WebSocketResponse::MessageCreated(_) => assert_eq!(frame.request_id.as_deref(), Some("create-1")),
+                WebSocketResponse::SendChatMessage(_) => assert_eq!(frame.request_id, None),
+                _ => panic!("Should only receive MessageCreated or SendChatMessage after creating a message"),
+            }
+        }
+
+        // Errors are echoed with the request_id and a typed code
+        let bad_request: WebSocketRequest = RequestMessagesSchema {
+            message_count: 500,
+            atomic_message_id: 1,
+            channel_id: channel_id.to_string(),
+        }
+        .into();
+        send_websocket_request_with_id(&mut socket, &bad_request, "state-1").await;
+        let frame = receive_response_frame(&mut socket).await;
+        assert_eq!(frame.request_id.as_deref(), Some("state-1"));
+        match frame.response {
+            WebSocketResponse::SendError(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
+            _ => panic!("Requesting too many messages should error"),
+        }
+
+        // Database errors are converted into a matching code
+        let missing_channel: WebSocketRequest = RequestMessagesSchema {
+            message_count: 5,
+            atomic_message_id: 1,
+            channel_id: FAKE_MONGO_ID.to_string(),
+        }
+        .into();
+        send_websocket_request_with_id(&mut socket, &missing_channel, "state-2").await;
+        let frame = receive_response_frame(&mut socket).await;
+        assert_eq!(frame.request_id.as_deref(), Some("state-2"));
+        match frame.response {
+            WebSocketResponse::SendError(e) => assert_eq!(e.status_code, WebSocketErrorCode::NotFound),
+            _ => panic!("Requesting messages from a channel which doesn't exist should error"),
+        }
+
+        // A request which can't be decoded still gets its request_id back
+        send_arbitrary_data(&mut socket, r#"{"type":"NotARequest","request_id":"bad-1"}"#.to_owned()).await;
+        let frame = receive_response_frame(&mut socket).await;
+        assert_eq!(frame.request_id.as_deref(), Some("bad-1"));
+        match frame.response {
+            WebSocketResponse::SendError(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
+            _ => panic!("An unknown request type should error"),
+        }
+
+        // A known request type with data that doesn't fit it is rejected before reaching its handler
+        send_arbitrary_data(
+            &mut socket,
+            r#"{"type":"GetChatState","data":{"channel_id":5},"request_id":"bad-2"}"#.to_owned(),
+        )
+        .await;
+        let frame = receive_response_frame(&mut socket).await;
+        assert_eq!(frame.request_id.as_deref(), Some("bad-2"));
+        match frame.response {
+            WebSocketResponse::SendError(e) => {
+                assert_eq!(e.status_code, WebSocketErrorCode::BadRequest);
+                assert_eq!(e.msg, "Failed to decode the data of a GetChatState request");
+            }
+            _ => panic!("A request with malformed data should error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_socket_tickets() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 1).await;
+        let token = chat_helper.tokens[0].as_str();
+        let user_id = chat_helper.users[0].id.as_str();
+        let channel_id = chat_helper.channels[0]._id.as_str();
+        let get_state: WebSocketRequest = RequestMessagesSchema {
+            message_count: 10,
+            atomic_message_id: 10,
+            channel_id: channel_id.to_owned(),
+        }
+        .into();
+
+        // Tickets need an authenticated user
+        let res = get_socket_ticket(&helper, "not a token").await;
+        assert_eq!(res.unwrap_err().0, StatusCode::UNAUTHORIZED);
+
+        // A ticket can be passed with Sec-WebSocket-Protocol, alongside the protocol the server picks
+        let ticket = get_socket_ticket(&helper, token).await.expect("Failed to get a socket ticket");
+        let ticket_request = |ticket: &str| {
+            let mut request = format!("ws://{}/api/chat/ws", addr).into_client_request().unwrap();
+            let protocols = format!("pat-chat, pat-ticket.{}", ticket);
+            request
+                .headers_mut()
+                .insert("Sec-WebSocket-Protocol", HeaderValue::from_str(protocols.as_str()).unwrap());
+            request
+        };
+        let (mut socket, response) = tokio_tungstenite::connect_async(ticket_request(ticket.ticket.as_str()))
+            .await
+            .expect("Failed to open a ws connection with a ticket");
+        assert_eq!(response.headers().get("Sec-WebSocket-Protocol").unwrap(), "pat-chat");
+        send_websocket_request(&mut socket, &get_state).await;
+        receive_chat_state(&mut socket).await.expect("A socket opened with a ticket should work");
+
+        // Tickets can only be used once
+        match tokio_tungstenite::connect_async(ticket_request(ticket.ticket.as_str())).await {
+            Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), StatusCode::UNAUTHORIZED),
+            _ => panic!("A ticket should not be usable twice"),
+        }
+
+        // Without credentials, the first request has to Authenticate
+        let ticket = get_socket_ticket(&helper, token).await.expect("Failed to get a socket ticket");
+        let (mut socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws", addr))
+            .await
+            .expect("Failed to open a ws connection without credentials");
+        let authenticate: WebSocketRequest = AuthenticateSchema { ticket: ticket.ticket }.into();
+        send_websocket_request_with_id(&mut socket, &authenticate, "auth-1").await;
+        let frame = receive_response_frame(&mut socket).await;
+        assert_eq!(frame.request_id.as_deref(), Some("auth-1"));
+        match frame.response {
+            WebSocketResponse::Authenticated(authenticated) => assert_eq!(authenticated.user_id, user_id),
+            _ => panic!("Authenticating with a valid ticket should succeed"),
+        }
+        send_websocket_request(&mut socket, &get_state).await;
+        receive_chat_state(&mut socket).await.expect("An authenticated socket should work");
+
+        // A socket which doesn't Authenticate first is told why and closed
+        let (mut socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws", addr))
+            .await
+            .expect("Failed to open a ws connection without credentials");
+        send_websocket_request(&mut socket, &get_state).await;
+        match receive_response_frame(&mut socket).await.response {
+            WebSocketResponse::SendError(e) => assert_eq!(e.status_code, WebSocketErrorCode::Unauthorized),
+            _ => panic!("Making a request before authenticating should error"),
+        }
+        match socket.next().await {
+            Some(Ok(tungstenite::Message::Close(_))) | None => (),
+            _ => panic!("The socket should be closed after failing to authenticate"),
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_rate_limits() {
+        let mut config = Config::init();
+        config.rate_limits = RateLimits {
+            socket_requests: RateLimit::new(10, 60),
+            user_messages: RateLimit::new(3, 60),
+            channel_messages: RateLimit::new(5, 60),
+            http_requests: RateLimit::new(20, 60),
+        };
+        let helper = TestHelper::init_with_config(config).await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 2).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let channel_id = chat_helper.channels[0]._id.as_str();
+        subscribe_to_channel(&helper, second_token, channel_id)
+            .await
+            .expect("Failed to subscribe to another users chat channel");
+
+        let (mut first_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+        let (mut second_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, second_token))
+            .await
+            .expect("Failed to open a ws connection with second user");
+        let message = |contents: String| -> WebSocketRequest {
+            CreateMessageSchema {
+                channel_id: channel_id.to_owned(),
+                contents,
+                reply_to: None,
+            }
+            .into()
+        };
+
+        // Messages have a maximum length
+        send_websocket_request_with_id(&mut first_socket, &message("a".repeat(MAX_MESSAGE_LENGTH + 1)), "too-long").await;
+        match receive_response_to(&mut first_socket, "too-long").await {
+            WebSocketResponse::SendError(e) => assert_eq!(e.status_code, WebSocketErrorCode::BadRequest),
+            _ => panic!("A message over the maximum length should error"),
+        }
+
+        // A user can only send so many messages before being told to wait
+        for n in 0..3 {
+            let request_id = format!("message-{n}");
+            send_websocket_request_with_id(&mut first_socket, &message(format!("Message {n}")), request_id.as_str()).await;
+            match receive_response_to(&mut first_socket, request_id.as_str()).await {
+                WebSocketResponse::MessageCreated(_) => (),
+                _ => panic!("Messages under the limit should be sent"),
+            }
+        }
+        send_websocket_request_with_id(&mut first_socket, &message("One too many".to_owned()), "limited").await;
+        match receive_response_to(&mut first_socket, "limited").await {
+            WebSocketResponse::SendError(e) => {
+                assert_eq!(e.status_code, WebSocketErrorCode::RateLimited);
+                assert!(e.retry_after_ms.unwrap() > 0);
+            }
+            _ => panic!("A user over their message limit should be rate limited"),
+        }
+
+        // The channel has its own limit, shared by everyone sending to it
+        for n in 0..2 {
+            let request_id = format!("second-message-{n}");
+            send_websocket_request_with_id(&mut second_socket, &message(format!("Second message {n}")), request_id.as_str()).await;
+            match receive_response_to(&mut second_socket, request_id.as_str()).await {
+                WebSocketResponse::MessageCreated(_) => (),
+                _ => panic!("Messages under the channel limit should be sent"),
+            }
+        }
+        send_websocket_request_with_id(&mut second_socket, &message("Channel is full".to_owned()), "channel-limited").await;
+        match receive_response_to(&mut second_socket, "channel-limited").await {
+            WebSocketResponse::SendError(e) => {
+                assert_eq!(e.status_code, WebSocketErrorCode::RateLimited);
+                assert_eq!(e.msg, "This channel is receiving too many messages");
+            }
+            _ => panic!("A channel over its message limit should be rate limited"),
+        }
+
+        // Every request counts against the socket limit, the first user has made 5 so far
+        let get_state: WebSocketRequest = RequestMessagesSchema {
+            message_count: 10,
+            atomic_message_id: 10,
+            channel_id: channel_id.to_owned(),
+        }
+        .into();
+        for n in 0..5 {
+            let request_id = format!("state-{n}");
+            send_websocket_request_with_id(&mut first_socket, &get_state, request_id.as_str()).await;
+            match receive_response_to(&mut first_socket, request_id.as_str()).await {
+                WebSocketResponse::SendChatState(_) => (),
+                _ => panic!("Requests under the socket limit should succeed"),
+            }
+        }
+        send_websocket_request_with_id(&mut first_socket, &get_state, "state-limited").await;
+        match receive_response_to(&mut first_socket, "state-limited").await {
+            WebSocketResponse::SendError(e) => assert_eq!(e.status_code, WebSocketErrorCode::RateLimited),
+            _ => panic!("A user over their socket limit should be rate limited"),
+        }
+
+        // HTTP requests are limited too
+        let mut limited = false;
+        for _ in 0..20 {
+            if let Err((status, _)) = get_channel_by_id(&helper, token, channel_id).await {
+                assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+                limited = true;
+                break;
+            }
+        }
+        assert!(limited, "A user over their HTTP limit should be rate limited");
+    }
+
+    #[tokio::test]
+    async fn chat_export() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 2).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let user_one = &chat_helper.users[0];
+        let channel_id = chat_helper.channels[0]._id.as_str();
+
+        let (mut socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_id.to_owned(),
+            contents: "<b>Hello</b> & welcome".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut socket, &message_data).await;
+        let first_message = receive_chat_message(&mut socket).await.expect("Failed to receive a chat message");
+        let reply_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_id.to_owned(),
+            contents: "A reply".to_owned(),
+            reply_to: Some(first_message.id.clone()),
+        }
+        .into();
+        send_websocket_request(&mut socket, &reply_data).await;
+        receive_chat_message(&mut socket).await.expect("Failed to receive a chat message");
+
+        // NDJSON has one message per line, oldest first, with the author's username
+        let (content_type, ndjson) = export_channel(&helper, token, channel_id, "ndjson")
+            .await
+            .expect("Failed to export a channel");
+        assert_eq!(content_type, "application/x-ndjson");
+        let exported: Vec<ExportedMessage> = ndjson.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].contents, "<b>Hello</b> & welcome");
+        assert_eq!(exported[0].author_username.as_deref(), Some(user_one.username.as_str()));
+        assert_eq!(exported[0].reply_count, 1);
+        assert_eq!(exported[1].reply_to.as_deref(), Some(first_message.id.as_str()));
+
+        // HTML is a standalone page with the contents escaped
+        let (content_type, html) = export_channel(&helper, token, channel_id, "html")
+            .await
+            .expect("Failed to export a channel");
+        assert_eq!(content_type, "text/html; charset=utf-8");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("&lt;b&gt;Hello&lt;/b&gt; &amp; welcome"));
+        assert!(html.contains(format!("href=\"#message-{}\"", first_message.id).as_str()));
+        assert!(html.trim_end().ends_with("</html>"));
+
+        // Only someone in the channel can export it
+        let res = export_channel(&helper, second_token, channel_id, "ndjson").await;
+        assert_eq!(res.unwrap_err().0, StatusCode::FORBIDDEN);
+        let res = export_channel(&helper, token, FAKE_MONGO_ID, "ndjson").await;
+        assert_eq!(res.unwrap_err().0, StatusCode::NOT_FOUND);
+
+        assert_eq!(format_unix_time(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_unix_time(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_unix_time(1_709_993_100), "2024-03-09 14:05:00 UTC");
+    }
+
+    // Both instances share the db and watch it with a change stream, so this needs MongoDB to be running as a replica set
+    #[tokio::test]
+    async fn chat_change_stream_across_instances() {
+        let change_stream_config = || {
+            let mut config = TestHelper::config();
+            config.chat_event_bus = EventBusBackend::ChangeStream;
+            config
+        };
+        let helper = TestHelper::init_with_config(change_stream_config()).await;
+        let other_instance = helper.init_second_instance(change_stream_config()).await;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 2).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let channel_one_id = chat_helper.channels[0]._id.as_str();
+        let channel_two_id = chat_helper.channels[1]._id.as_str();
+
+        subscribe_to_channel(&helper, second_token, channel_one_id)
+            .await
+            .expect("Failed to subscribe to another users chat channel");
+
+        // Each user is connected to a different instance
+        let (mut first_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", helper.address, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+        let (mut second_socket, _response) =
+            tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", other_instance.address, second_token))
+                .await
+                .expect("Failed to open a ws connection with second user on the other instance");
+
+        // A message sent on one instance reaches a reader on the other, which caches the channel's readers
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "Across instances".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &message_data).await;
+        let sent_message = receive_chat_message(&mut first_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+        let other_instance_message = receive_chat_message(&mut second_socket)
+            .await
+            .expect("Failed to receive a chat message sent on the other instance");
+        assert_eq!(other_instance_message, sent_message);
+
+        // Leaving the channel through the first instance drops the cached readers on the other instance too
+        unsubscribe_from_channel(&helper, second_token, channel_one_id)
+            .await
+            .expect("Failed to unsubscribe from a chat channel");
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "Not for the second user".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &message_data).await;
+        receive_chat_message(&mut first_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+
+        // The next message the second user receives is their own, not the one sent after they left
+        let own_message: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_two_id.to_string(),
+            contents: "Only mine".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut second_socket, &own_message).await;
+        let next_message = receive_chat_message(&mut second_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+        assert_eq!(next_message.contents.as_str(), "Only mine");
+
+        // Coming back through the first instance is picked up by the other instance as well
+        subscribe_to_channel(&helper, second_token, channel_one_id)
+            .await
+            .expect("Failed to subscribe to a chat channel again");
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "Welcome back".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &message_data).await;
+        let welcome_back = receive_chat_message(&mut second_socket)
+            .await
+            .expect("Failed to receive a chat message after subscribing again");
+        assert_eq!(welcome_back.contents.as_str(), "Welcome back");
+    }
+
+    async fn next_chat_event(changes: &mut ChangeStream<ChangeStreamEvent<Document>>) -> ChatEvent {
+        loop {
+            let change = changes.next().await.expect("The change stream ended").expect("The change stream failed");
+            if let Some(event) = chat_event_from_change(change) {
+                return event;
+            }
+        }
+    }
+
+    // Watches the db with the change stream bus's pipeline, so this needs MongoDB to be running as a replica set
+    #[tokio::test]
+    async fn chat_change_stream_events() {
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, 2).await;
+        let token = chat_helper.tokens[0].as_str();
+        let second_token = chat_helper.tokens[1].as_str();
+        let channel_one_id = chat_helper.channels[0]._id.as_str();
+
+        subscribe_to_channel(&helper, second_token, channel_one_id)
+            .await
+            .expect("Failed to subscribe to another users chat channel");
+        let mut changes = helper
+            .database
+            .watch()
+            .pipeline(chat_change_pipeline())
+            .full_document(FullDocumentType::UpdateLookup)
+            .await
+            .expect("Failed to open a change stream");
+
+        let (mut first_socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+            .await
+            .expect("Failed to open a ws connection with first user");
+        let message_data: WebSocketRequest = CreateMessageSchema {
+            channel_id: channel_one_id.to_string(),
+            contents: "Watched".to_owned(),
+            reply_to: None,
+        }
+        .into();
+        send_websocket_request(&mut first_socket, &message_data).await;
+        receive_chat_message(&mut first_socket)
+            .await
+            .expect("Failed to receive a chat message after sending one");
+        unsubscribe_from_channel(&helper, second_token, channel_one_id)
+            .await
+            .expect("Failed to unsubscribe from a chat channel");
+
+        // Sending the message also bumped the channel's most recent message ID, which doesn't change
+        // who can read it, so the next event is the unsubscribe
+        match next_chat_event(&mut changes).await {
+            ChatEvent::MessageCreated(message) => assert_eq!(message.contents.as_str(), "Watched"),
+            event => panic!("Expected the new message, got {:?}", event),
+        }
+        match next_chat_event(&mut changes).await {
+            ChatEvent::ChannelReadersChanged(channel_id) => assert_eq!(channel_id.as_str(), channel_one_id),
+            event => panic!("Expected the channel's readers to change, got {:?}", event),
+        }
+    }
+
+    // Not a benchmark, but gives a rough idea of how fan-out holds up with a lot of open sockets.
+    // Run with --nocapture to see the throughput
+    #[tokio::test]
+    async fn chat_load_many_connections() {
+        const USER_COUNT: usize = 10;
+        const CONNECTIONS_PER_USER: usize = 30;
+        const MESSAGE_COUNT: usize = 20;
+
+        let helper = TestHelper::init().await;
+        let addr = &helper.address;
+
+        let chat_helper = ChatHelper::setup_chat(&helper, USER_COUNT).await;
+        let channel_id = chat_helper.channels[0]._id.as_str();
+        for token in chat_helper.tokens.iter().skip(1) {
+            subscribe_to_channel(&helper, token.as_str(), channel_id)
+                .await
+                .expect("Failed to subscribe to another users chat channel");
+        }
+
+        // Every user is connected from a lot of devices at once
+        let mut sockets = Vec::new();
+        for token in chat_helper.tokens.iter() {
+            for _ in 0..CONNECTIONS_PER_USER {
+                let (socket, _response) = tokio_tungstenite::connect_async(format!("ws://{}/api/chat/ws?auth_token={}", addr, token))
+                    .await
+                    .expect("Failed to open a ws connection");
+                sockets.push(socket);
+            }
+        }
+
+        let start = Instant::now();
+        for n in 0..MESSAGE_COUNT {
+            let message_data: WebSocketRequest = CreateMessageSchema {
+                channel_id: channel_id.to_string(),
+                contents: format!("Load message {}", n),
+                reply_to: None,
+            }
+            .into();
+            send_websocket_request(&mut sockets[0], &message_data).await;
+        }
+
+        // Every socket should get every message, in order
+        for socket in sockets.iter_mut() {
+            for n in 0..MESSAGE_COUNT {
+                let chat_message = receive_chat_message(socket)
+                    .await
+                    .expect("Failed to receive a chat message when one was expected");
+                assert_eq!(chat_message.contents, format!("Load message {}", n));
+            }
+        }
+        let elapsed = start.elapsed();
+
+        let deliveries = sockets.len() * MESSAGE_COUNT;
+        println!(
+            "Delivered {} messages to {} sockets in {:?}, {:.0} deliveries per second",
+            MESSAGE_COUNT,
+            sockets.len(),
+            elapsed,
+            deliveries as f64 / elapsed.as_secs_f64()
+        );
+    }
 }
@@ -1,14 +1,36 @@
 #[cfg(test)]
 mod games_testing {
-    use crate::models::games::validation::{CreateConnectionCategorySchema, CreateConnectionGameSchema};
+    use crate::api::return_data::FieldError;
+    use crate::models::chat::validation::CreateChannelSchema;
+    use crate::models::games::{games_db::first_free_slug, stats::win_streaks};
+    use crate::models::games::{
+        rating::{DifficultyVote, DifficultyVotes},
+        session::SessionState,
+        validation::{
+            CreateConnectionCategorySchema, CreateConnectionGameSchema, RateConnectionGameSchema, ScheduleDailyPuzzleSchema, ShareResultSchema,
+            UpdateConnectionGameSchema,
+        },
+    };
     use crate::testing::{
         helpers::{
-            games_helpers::{create_connections_game, get_game_to_play, list_connections_games, try_connections_solution},
-            user_helpers::{create_user, get_user_me},
+            chat_helpers::create_chat_channel,
+            games_helpers::{
+                create_connections_game, delete_connections_game, get_connections_author_stats, get_connections_leaderboard,
+                get_connections_player_stats, get_daily_puzzle, get_daily_puzzle_queue, get_game_to_play, get_shareable_result,
+                list_connections_games, list_my_connections_games, publish_connections_game, rate_connections_game, schedule_daily_puzzle,
+                share_connections_result, start_connections_session, try_connections_solution, unschedule_daily_puzzle, update_connections_game,
+            },
+            user_helpers::{create_user, get_user_me, promote_to_admin},
         },
         TestHelper,
     };
+    use crate::util::{current_unix_time, is_valid_date, parse_utc_offset, unix_time_to_date};
+    use futures::TryStreamExt;
     use hyper::StatusCode;
+    use mongodb::{
+        bson::{doc, oid::ObjectId, Document},
+        Collection,
+    };
 
     #[tokio::test]
     async fn connections_crud() {
@@ -29,28 +51,18 @@ mod games_testing {
         let user_two = get_user_me(&helper, second_token.as_str()).await.unwrap();
 
         let connection_categories = [
-            CreateConnectionCategorySchema {
-                category_clues: ["foo".to_string(), "bar".to_string(), "baz".to_string(), "bash".to_string()],
-                category_name: "first".to_string(),
-            },
-            CreateConnectionCategorySchema {
-                category_clues: ["foo".to_string(), "bar".to_string(), "baz".to_string(), "bash".to_string()],
-                category_name: "second".to_string(),
-            },
-            CreateConnectionCategorySchema {
-                category_clues: ["foo".to_string(), "bar".to_string(), "baz".to_string(), "bash".to_string()],
-                category_name: "third".to_string(),
-            },
-            CreateConnectionCategorySchema {
-                category_clues: ["foo".to_string(), "bar".to_string(), "baz".to_string(), "bash".to_string()],
-                category_name: "fourth".to_string(),
-            },
+            category("first", ["foo", "bar", "baz", "bash"]),
+            category("second", ["one", "two", "three", "four"]),
+            category("third", ["red", "green", "blue", "teal"]),
+            category("fourth", ["oak", "ash", "elm", "fir"]),
         ];
 
         // Create a connections game
         let data = CreateConnectionGameSchema {
             connection_categories: connection_categories.clone(),
             puzzle_name: "Test Puzzle".to_string(),
+            draft: false,
+            tags: Vec::new(),
         };
         let connection_game = create_connections_game(&helper, token.as_str(), &data)
             .await
@@ -58,20 +70,19 @@ mod games_testing {
         assert_eq!(connection_game.author_id, user.id);
         assert_eq!(connection_game.puzzle_name, "Test Puzzle");
 
-        // Try to create a connections game with a duplicate slug
-        match create_connections_game(&helper, token.as_str(), &data).await {
-            Ok(_) => panic!("Creating a connections game with a duplicate slug should fail"),
-            Err((status_code, _msg)) => assert_eq!(
-                status_code,
-                StatusCode::BAD_REQUEST,
-                "Creating a connection game with a duplicate slug should 400"
-            ),
-        }
+        // A game with the same name gets a suffixed slug rather than failing
+        let same_name_game = create_connections_game(&helper, token.as_str(), &data)
+            .await
+            .expect("Failed to create a connections game with a duplicate name");
+        assert_eq!(connection_game.slug, "test-puzzle");
+        assert_eq!(same_name_game.slug, "test-puzzle-2");
 
         // Create a second connections game
         let second_data = CreateConnectionGameSchema {
             connection_categories: connection_categories.clone(),
             puzzle_name: "Second Test Puzzle".to_string(),
+            draft: false,
+            tags: Vec::new(),
         };
         let _second_connections_game = create_connections_game(&helper, token.as_str(), &second_data)
             .await
@@ -81,21 +92,22 @@ mod games_testing {
         let other_user_data = CreateConnectionGameSchema {
             connection_categories: connection_categories.clone(),
             puzzle_name: "Other User Test Puzzle".to_string(),
+            draft: false,
+            tags: Vec::new(),
         };
         let other_user_connections_game = create_connections_game(&helper, second_token.as_str(), &other_user_data)
             .await
             .expect("Failed to create a connections game");
 
-        // List "my" connections games for the first user, there should be two
-        let my_connections_games = list_connections_games(&helper, token.as_str(), true)
+        // List "my" connections games for the first user, there should be three
+        let my_connections_games = list_my_connections_games(&helper, token.as_str())
             .await
             .expect("Failed to get connections games for 'me'");
-        assert_eq!(my_connections_games.len(), 2);
-        assert_eq!(my_connections_games[0].author_id, user.id);
-        assert_eq!(my_connections_games[1].author_id, user.id);
+        assert_eq!(my_connections_games.len(), 3);
+        assert!(my_connections_games.iter().all(|game| game.author_id == user.id));
 
         // List all connections games for other users as the first user, there should be one
-        let other_connections_games = list_connections_games(&helper, token.as_str(), false)
+        let other_connections_games = list_connections_games(&helper, token.as_str(), "")
             .await
             .expect("Failed to get connections games for other users");
         assert_eq!(other_connections_games.len(), 1);
@@ -117,13 +129,12 @@ mod games_testing {
             .expect("Failed to get a connections game to play");
         assert_eq!(play_game.scrambled_clues.len(), 16);
 
-        // Try to solve a row with an incorrect solution
+        // Guesses with words which aren't in the puzzle are rejected without being checked
         let bad_guess = ["wrong".to_string(), "wrong".to_string(), "wrong".to_string(), "wrong".to_string()];
-        let bad_guess_response = try_connections_solution(&helper, token.as_str(), other_user_connections_game.slug.as_str(), bad_guess)
-            .await
-            .expect("Failed to attempt an incorrect guess for a connections game row");
-        assert_eq!(bad_guess_response.row_name, None);
-        assert_eq!(bad_guess_response.correct_guess, false);
+        match try_connections_solution(&helper, token.as_str(), other_user_connections_game.slug.as_str(), bad_guess).await {
+            Ok(_) => panic!("Guessing words which aren't in the puzzle should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
 
         // Solve a row with a correct solution
         let good_guess = connection_categories[0].category_clues.clone();
@@ -134,4 +145,922 @@ mod games_testing {
         assert_eq!(good_guess_response.row_name, Some(good_guess_category_name));
         assert_eq!(good_guess_response.correct_guess, true);
     }
+
+    fn rating(rating: i64, difficulty: DifficultyVote) -> RateConnectionGameSchema {
+        RateConnectionGameSchema { rating, difficulty }
+    }
+
+    fn category(name: &str, clues: [&str; 4]) -> CreateConnectionCategorySchema {
+        CreateConnectionCategorySchema {
+            category_clues: clues.map(|clue| clue.to_string()),
+            category_name: name.to_string(),
+            difficulty: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn connections_sessions() {
+        let helper = TestHelper::init().await;
+
+        let author_token = create_user(&helper, "author", "author").await.unwrap();
+        let player_token = create_user(&helper, "player", "player").await.unwrap();
+        let loser_token = create_user(&helper, "loser", "loser").await.unwrap();
+
+        let connection_categories = [
+            category("fish", ["bass", "pike", "carp", "sole"]),
+            category("trees", ["oak", "ash", "elm", "fir"]),
+            category("colors", ["red", "blue", "green", "teal"]),
+            category("planets", ["mars", "venus", "earth", "saturn"]),
+        ];
+        let data = CreateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: "Session Puzzle".to_string(),
+            draft: false,
+            tags: Vec::new(),
+        };
+        let game = create_connections_game(&helper, author_token.as_str(), &data).await.unwrap();
+        let slug = game.slug.as_str();
+
+        // A new session starts with every clue left and four mistakes
+        let session = start_connections_session(&helper, player_token.as_str(), slug)
+            .await
+            .expect("Failed to start a connections session");
+        assert_eq!(session.state, SessionState::InProgress);
+        assert_eq!(session.mistakes_remaining, 4);
+        assert_eq!(session.remaining_clues.len(), 16);
+        assert!(session.guesses.is_empty());
+        assert!(session.answers.is_none());
+
+        // Solve a row and make a mistake
+        let first = try_connections_solution(&helper, player_token.as_str(), slug, connection_categories[0].category_clues.clone())
+            .await
+            .unwrap();
+        assert!(first.correct_guess);
+        assert_eq!(first.mistakes_remaining, 4);
+        let wrong = ["oak".to_string(), "red".to_string(), "mars".to_string(), "ash".to_string()];
+        let second = try_connections_solution(&helper, player_token.as_str(), slug, wrong).await.unwrap();
+        assert!(!second.correct_guess);
+        assert_eq!(second.mistakes_remaining, 3);
+        assert_eq!(second.state, SessionState::InProgress);
+
+        // Solving the same row twice is rejected
+        match try_connections_solution(&helper, player_token.as_str(), slug, connection_categories[0].category_clues.clone()).await {
+            Ok(_) => panic!("Solving an already solved category should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+
+        // Resuming the session keeps the progress
+        let session = start_connections_session(&helper, player_token.as_str(), slug).await.unwrap();
+        assert_eq!(session.solved_categories.len(), 1);
+        assert_eq!(session.solved_categories[0].category_name, "fish");
+        assert_eq!(session.remaining_clues.len(), 12);
+        assert_eq!(session.guesses.len(), 2);
+        assert_eq!(session.mistakes_remaining, 3);
+
+        // Solving every row wins the game
+        for category in &connection_categories[1..] {
+            try_connections_solution(&helper, player_token.as_str(), slug, category.category_clues.clone())
+                .await
+                .unwrap();
+        }
+        let session = start_connections_session(&helper, player_token.as_str(), slug).await.unwrap();
+        assert_eq!(session.state, SessionState::Won);
+        assert!(session.finished_at.is_some());
+        assert!(session.remaining_clues.is_empty());
+        assert!(session.answers.is_some());
+
+        // Three clues from one category is one away
+        let one_away = ["bass".to_string(), "pike".to_string(), "carp".to_string(), "oak".to_string()];
+        let response = try_connections_solution(&helper, loser_token.as_str(), slug, one_away.clone())
+            .await
+            .unwrap();
+        assert!(!response.correct_guess);
+        assert!(response.one_away);
+        assert!(!response.already_guessed);
+        assert_eq!(response.mistakes_remaining, 3);
+
+        // Making the same guess again, in any order, doesn't cost a mistake
+        let reordered = ["oak".to_string(), "carp".to_string(), "pike".to_string(), "bass".to_string()];
+        let response = try_connections_solution(&helper, loser_token.as_str(), slug, reordered).await.unwrap();
+        assert!(response.already_guessed);
+        assert_eq!(response.mistakes_remaining, 3);
+
+        // Guesses with a repeated word are rejected
+        let repeated = ["bass".to_string(), "bass".to_string(), "carp".to_string(), "oak".to_string()];
+        match try_connections_solution(&helper, loser_token.as_str(), slug, repeated).await {
+            Ok(_) => panic!("Guessing the same word twice should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+
+        // Four mistakes loses the game
+        let wrong_guesses = [
+            ["bass", "oak", "red", "mars"],
+            ["pike", "ash", "blue", "venus"],
+            ["carp", "elm", "green", "earth"],
+        ];
+        for (index, wrong) in wrong_guesses.iter().enumerate() {
+            let wrong = wrong.map(|clue| clue.to_string());
+            let response = try_connections_solution(&helper, loser_token.as_str(), slug, wrong).await.unwrap();
+            assert!(!response.one_away);
+            assert_eq!(response.mistakes_remaining, 2 - index as i64);
+        }
+        let session = start_connections_session(&helper, loser_token.as_str(), slug).await.unwrap();
+        assert_eq!(session.state, SessionState::Lost);
+        assert_eq!(session.remaining_clues.len(), 16);
+        assert!(session.answers.is_some());
+
+        // Guesses are rejected once the game is over
+        match try_connections_solution(&helper, loser_token.as_str(), slug, connection_categories[0].category_clues.clone()).await {
+            Ok(_) => panic!("Guessing after a game is over should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+    }
+
+    #[test]
+    fn connections_validation() {
+        let valid = CreateConnectionGameSchema {
+            connection_categories: [
+                category("fish", ["bass", "pike", "carp", "sole"]),
+                category("trees", ["oak", "ash", "elm", "fir"]),
+                category("colors", ["red", "blue", "green", "teal"]),
+                category("planets", ["mars", "venus", "earth", "saturn"]),
+            ],
+            puzzle_name: "Valid Puzzle".to_string(),
+            draft: false,
+            tags: Vec::new(),
+        };
+        assert!(valid.validate().is_ok());
+
+        let invalid = CreateConnectionGameSchema {
+            connection_categories: [
+                category("fish", ["bass", "pike", "carp", "sole"]),
+                category(" ", ["oak", "ash", "elm", "fir"]),
+                category("Fish", ["red", " BASS ", "green", ""]),
+                category("planets", ["mars", "venus", "earth", &"a".repeat(51)]),
+            ],
+            puzzle_name: "!!!".to_string(),
+            draft: false,
+            tags: Vec::new(),
+        };
+        let errors = invalid.validate().expect_err("An invalid puzzle should fail validation");
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "puzzle_name",
+                "connection_categories[1].category_name",
+                "connection_categories[2].category_name",
+                "connection_categories[2].category_clues[1]",
+                "connection_categories[2].category_clues[3]",
+                "connection_categories[3].category_clues[3]",
+            ]
+        );
+        assert_eq!(
+            errors[3],
+            FieldError::new(
+                "connection_categories[2].category_clues[1]".to_owned(),
+                "Is the same clue as connection_categories[0].category_clues[0]"
+            )
+        );
+
+        // Tags are compared the same way as clues, and there can only be a few of them
+        let mut tagged = valid;
+        tagged.tags = vec!["Animals".to_string(), " animals ".to_string(), "".to_string(), "a".repeat(21)];
+        let errors = tagged.validate().expect_err("Invalid tags should fail validation");
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, vec!["tags[1]", "tags[2]", "tags[3]"]);
+        tagged.tags = (0..6).map(|n| format!("tag {n}")).collect();
+        let errors = tagged.validate().expect_err("Too many tags should fail validation");
+        assert_eq!(errors, vec![FieldError::new("tags".to_owned(), "Can't have more than 5 tags")]);
+
+        // Either every category has a different difficulty from 0 to 3, or none of them have one
+        tagged.tags = Vec::new();
+        for (category, difficulty) in tagged.connection_categories.iter_mut().zip([Some(0), Some(0), Some(4), None]) {
+            category.difficulty = difficulty;
+        }
+        let errors = tagged.validate().expect_err("Invalid difficulties should fail validation");
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "connection_categories[1].difficulty",
+                "connection_categories[2].difficulty",
+                "connection_categories[3].difficulty",
+            ]
+        );
+        for (category, difficulty) in tagged.connection_categories.iter_mut().zip([3, 1, 0, 2]) {
+            category.difficulty = Some(difficulty);
+        }
+        assert!(tagged.validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn connections_invalid_puzzle() {
+        let helper = TestHelper::init().await;
+        let token = create_user(&helper, "foo", "foo").await.unwrap();
+
+        // Every category is the same, so every clue after the first four is a duplicate. Which fields
+        // fail is covered by connections_validation
+        let data = CreateConnectionGameSchema {
+            connection_categories: [
+                category("first", ["foo", "bar", "baz", "bash"]),
+                category("second", ["foo", "bar", "baz", "bash"]),
+                category("third", ["foo", "bar", "baz", "bash"]),
+                category("fourth", ["foo", "bar", "baz", "bash"]),
+            ],
+            puzzle_name: "Duplicate Puzzle".to_string(),
+            draft: false,
+            tags: Vec::new(),
+        };
+        match create_connections_game(&helper, token.as_str(), &data).await {
+            Ok(_) => panic!("Creating a connections game with duplicate clues should fail"),
+            Err((status_code, msg)) => {
+                assert_eq!(status_code, StatusCode::BAD_REQUEST);
+                assert_eq!(msg, "The request failed validation");
+            }
+        }
+
+        // Nothing was created
+        let games = list_my_connections_games(&helper, token.as_str()).await.unwrap();
+        assert!(games.is_empty());
+    }
+
+    #[tokio::test]
+    async fn connections_drafts_and_editing() {
+        let helper = TestHelper::init().await;
+
+        let author_token = create_user(&helper, "author", "author").await.unwrap();
+        let player_token = create_user(&helper, "player", "player").await.unwrap();
+
+        let connection_categories = [
+            category("fish", ["bass", "pike", "carp", "sole"]),
+            category("trees", ["oak", "ash", "elm", "fir"]),
+            category("colors", ["red", "blue", "green", "teal"]),
+            category("planets", ["mars", "venus", "earth", "saturn"]),
+        ];
+        let data = CreateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: "Draft Puzzle".to_string(),
+            draft: true,
+            tags: Vec::new(),
+        };
+        let game = create_connections_game(&helper, author_token.as_str(), &data).await.unwrap();
+        assert!(game.draft);
+
+        // A draft is only visible to its author
+        let mine = list_my_connections_games(&helper, author_token.as_str()).await.unwrap();
+        assert_eq!(mine.len(), 1);
+        assert!(mine[0].draft);
+        let others = list_connections_games(&helper, player_token.as_str(), "").await.unwrap();
+        assert!(others.is_empty());
+        match get_game_to_play(&helper, player_token.as_str(), game.slug.as_str()).await {
+            Ok(_) => panic!("Playing someone else's draft should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::NOT_FOUND),
+        }
+        start_connections_session(&helper, author_token.as_str(), game.slug.as_str())
+            .await
+            .expect("An author should be able to play their own draft");
+
+        // Only the author can change a game
+        let update = UpdateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: "Edited Puzzle".to_string(),
+            tags: Vec::new(),
+        };
+        match update_connections_game(&helper, player_token.as_str(), game.id.as_str(), &update).await {
+            Ok(_) => panic!("Editing someone else's game should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN),
+        }
+        match publish_connections_game(&helper, player_token.as_str(), game.id.as_str()).await {
+            Ok(_) => panic!("Publishing someone else's game should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN),
+        }
+        match delete_connections_game(&helper, player_token.as_str(), game.id.as_str()).await {
+            Ok(_) => panic!("Deleting someone else's game should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN),
+        }
+
+        // The author's own session doesn't stop them from editing
+        let edited = update_connections_game(&helper, author_token.as_str(), game.id.as_str(), &update)
+            .await
+            .expect("Failed to edit a connections game");
+        assert_eq!(edited.puzzle_name, "Edited Puzzle");
+        assert_eq!(edited.slug, "edited-puzzle");
+
+        // Edits are validated the same way as new games
+        let invalid_update = UpdateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: " ".to_string(),
+            tags: Vec::new(),
+        };
+        match update_connections_game(&helper, author_token.as_str(), game.id.as_str(), &invalid_update).await {
+            Ok(_) => panic!("Editing a game to be invalid should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+
+        // Once published other users can find and play it
+        let published = publish_connections_game(&helper, author_token.as_str(), game.id.as_str()).await.unwrap();
+        assert!(!published.draft);
+        let others = list_connections_games(&helper, player_token.as_str(), "").await.unwrap();
+        assert_eq!(others.len(), 1);
+        start_connections_session(&helper, player_token.as_str(), edited.slug.as_str())
+            .await
+            .unwrap();
+
+        // A game someone else has played can't be edited
+        match update_connections_game(&helper, author_token.as_str(), game.id.as_str(), &update).await {
+            Ok(_) => panic!("Editing a game which has been played should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+
+        // But it can be deleted
+        delete_connections_game(&helper, author_token.as_str(), game.id.as_str()).await.unwrap();
+        let mine = list_my_connections_games(&helper, author_token.as_str()).await.unwrap();
+        assert!(mine.is_empty());
+        match start_connections_session(&helper, player_token.as_str(), edited.slug.as_str()).await {
+            Ok(_) => panic!("Playing a deleted game should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::NOT_FOUND),
+        }
+    }
+
+    #[test]
+    fn connections_slug_suffixes() {
+        let taken = |slugs: &[&str]| slugs.iter().map(|slug| slug.to_string()).collect::<Vec<String>>();
+        assert_eq!(first_free_slug("puzzle", &taken(&[])), "puzzle");
+        assert_eq!(first_free_slug("puzzle", &taken(&["other"])), "puzzle");
+        assert_eq!(first_free_slug("puzzle", &taken(&["puzzle"])), "puzzle-2");
+        assert_eq!(first_free_slug("puzzle", &taken(&["puzzle", "puzzle-2", "puzzle-4"])), "puzzle-3");
+    }
+
+    #[tokio::test]
+    async fn connections_slugs_are_unique() {
+        let helper = TestHelper::init().await;
+
+        let first_token = create_user(&helper, "first", "first").await.unwrap();
+        let second_token = create_user(&helper, "second", "second").await.unwrap();
+
+        let connection_categories = [
+            category("fish", ["bass", "pike", "carp", "sole"]),
+            category("trees", ["oak", "ash", "elm", "fir"]),
+            category("colors", ["red", "blue", "green", "teal"]),
+            category("planets", ["mars", "venus", "earth", "saturn"]),
+        ];
+        let data = CreateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: "Shared Name".to_string(),
+            draft: false,
+            tags: Vec::new(),
+        };
+
+        // Two authors using the same name get different slugs
+        let first_game = create_connections_game(&helper, first_token.as_str(), &data).await.unwrap();
+        let second_game = create_connections_game(&helper, second_token.as_str(), &data).await.unwrap();
+        assert_eq!(first_game.slug, "shared-name");
+        assert_eq!(second_game.slug, "shared-name-2");
+
+        // Each slug plays its own game
+        let played = start_connections_session(&helper, first_token.as_str(), second_game.slug.as_str())
+            .await
+            .unwrap();
+        assert_eq!(played.game_id, second_game.id);
+        let played = start_connections_session(&helper, second_token.as_str(), first_game.slug.as_str())
+            .await
+            .unwrap();
+        assert_eq!(played.game_id, first_game.id);
+
+        // Editing a game without changing its name keeps its slug
+        let update = UpdateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: "shared name".to_string(),
+            tags: Vec::new(),
+        };
+        let edited = update_connections_game(&helper, second_token.as_str(), second_game.id.as_str(), &update)
+            .await
+            .unwrap();
+        assert_eq!(edited.slug, "shared-name-2");
+
+        // Renaming a game to a name which is taken also gets a suffix
+        let renamed_data = CreateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: "Another Name".to_string(),
+            draft: false,
+            tags: Vec::new(),
+        };
+        let third_game = create_connections_game(&helper, second_token.as_str(), &renamed_data).await.unwrap();
+        let update = UpdateConnectionGameSchema {
+            connection_categories,
+            puzzle_name: "Shared Name".to_string(),
+            tags: Vec::new(),
+        };
+        let renamed = update_connections_game(&helper, second_token.as_str(), third_game.id.as_str(), &update)
+            .await
+            .unwrap();
+        assert_eq!(renamed.slug, "shared-name-3");
+    }
+
+    #[test]
+    fn connections_win_streaks() {
+        assert_eq!(win_streaks(&[]), (0, 0));
+        assert_eq!(win_streaks(&[true, true, false, true]), (1, 2));
+        assert_eq!(win_streaks(&[false, true, true, true]), (3, 3));
+        assert_eq!(win_streaks(&[true, false]), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn connections_stats() {
+        let helper = TestHelper::init().await;
+
+        let author_token = create_user(&helper, "author", "author").await.unwrap();
+        let winner_token = create_user(&helper, "winner", "winner").await.unwrap();
+        let loser_token = create_user(&helper, "loser", "loser").await.unwrap();
+
+        let connection_categories = [
+            category("fish", ["bass", "pike", "carp", "sole"]),
+            category("trees", ["oak", "ash", "elm", "fir"]),
+            category("colors", ["red", "blue", "green", "teal"]),
+            category("planets", ["mars", "venus", "earth", "saturn"]),
+        ];
+        let data = CreateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: "Stats Puzzle".to_string(),
+            draft: false,
+            tags: Vec::new(),
+        };
+        let game = create_connections_game(&helper, author_token.as_str(), &data).await.unwrap();
+        let slug = game.slug.as_str();
+        let wrong_guesses = [
+            ["bass", "oak", "red", "mars"],
+            ["pike", "ash", "blue", "venus"],
+            ["carp", "elm", "green", "earth"],
+            ["sole", "fir", "teal", "saturn"],
+        ];
+
+        // Nobody has played yet
+        let stats = get_connections_player_stats(&helper, winner_token.as_str()).await.unwrap();
+        assert_eq!(stats.played, 0);
+        assert_eq!(stats.average_mistakes, None);
+        assert!(get_connections_leaderboard(&helper, winner_token.as_str(), slug)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // The winner makes one mistake, the loser makes four
+        try_connections_solution(&helper, winner_token.as_str(), slug, wrong_guesses[0].map(|clue| clue.to_string()))
+            .await
+            .unwrap();
+        for category in &connection_categories {
+            try_connections_solution(&helper, winner_token.as_str(), slug, category.category_clues.clone())
+                .await
+                .unwrap();
+        }
+        for wrong in &wrong_guesses {
+            try_connections_solution(&helper, loser_token.as_str(), slug, wrong.map(|clue| clue.to_string()))
+                .await
+                .unwrap();
+        }
+
+        // Only wins go on the leaderboard
+        let leaderboard = get_connections_leaderboard(&helper, author_token.as_str(), slug).await.unwrap();
+        assert_eq!(leaderboard.len(), 1);
+        assert_eq!(leaderboard[0].username, Some("winner".to_string()));
+        assert_eq!(leaderboard[0].mistakes, 1);
+
+        let stats = get_connections_player_stats(&helper, winner_token.as_str()).await.unwrap();
+        assert_eq!(stats.played, 1);
+        assert_eq!(stats.won, 1);
+        assert_eq!(stats.current_win_streak, 1);
+        assert_eq!(stats.max_win_streak, 1);
+        assert_eq!(stats.average_mistakes, Some(1.0));
+        assert_eq!(stats.guess_distribution, [0, 1, 0, 0]);
+
+        let stats = get_connections_player_stats(&helper, loser_token.as_str()).await.unwrap();
+        assert_eq!(stats.lost, 1);
+        assert_eq!(stats.current_win_streak, 0);
+        assert_eq!(stats.average_mistakes, Some(4.0));
+        assert_eq!(stats.guess_distribution, [0, 0, 0, 0]);
+
+        // The author knows the answers, so their own perfect win doesn't go on the leaderboard and
+        // isn't counted as a play
+        for category in &connection_categories {
+            try_connections_solution(&helper, author_token.as_str(), slug, category.category_clues.clone())
+                .await
+                .unwrap();
+        }
+        let leaderboard = get_connections_leaderboard(&helper, author_token.as_str(), slug).await.unwrap();
+        assert_eq!(leaderboard.len(), 1);
+        assert_eq!(leaderboard[0].username, Some("winner".to_string()));
+        let author_stats = get_connections_author_stats(&helper, author_token.as_str()).await.unwrap();
+        assert_eq!(author_stats.total_plays, 2);
+        assert_eq!(author_stats.total_won, 1);
+        assert_eq!(author_stats.solve_rate, Some(0.5));
+        assert_eq!(author_stats.puzzles.len(), 1);
+        assert_eq!(author_stats.puzzles[0].slug, game.slug);
+    }
+
+    #[test]
+    fn daily_puzzle_dates() {
+        // 2024-03-09 23:30:00 UTC
+        let unix_time = 1_710_027_000;
+        assert_eq!(unix_time_to_date(unix_time, 0), "2024-03-09");
+        assert_eq!(unix_time_to_date(unix_time, 60), "2024-03-10");
+        assert_eq!(unix_time_to_date(unix_time, -24 * 60), "2024-03-08");
+
+        assert!(is_valid_date("2024-02-29"));
+        assert!(!is_valid_date("2023-02-29"));
+        assert!(!is_valid_date("2024-13-01"));
+        assert!(!is_valid_date("2024-1-01"));
+        assert!(!is_valid_date("2024-+1-01"));
+        assert!(!is_valid_date("tomorrow"));
+
+        assert_eq!(parse_utc_offset("0"), Some(0));
+        assert_eq!(parse_utc_offset("UTC"), Some(0));
+        assert_eq!(parse_utc_offset("+02:00"), Some(120));
+        assert_eq!(parse_utc_offset("-05:30"), Some(-330));
+        assert_eq!(parse_utc_offset("02:00"), None);
+        assert_eq!(parse_utc_offset("+15:00"), None);
+        assert_eq!(parse_utc_offset("+01:60"), None);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn daily_puzzles() {
+        // The announcement channel has to be known before the app starts, so it gets a fixed ID
+        let announcement_channel_id = ObjectId::new();
+        let mut config = TestHelper::config();
+        config.daily_puzzle_utc_offset = 0;
+        config.daily_puzzle_channel_id = Some(announcement_channel_id.to_hex());
+        let helper = TestHelper::init_with_config(config).await;
+
+        let author_token = create_user(&helper, "author", "author").await.unwrap();
+        let admin_token = create_user(&helper, "admin", "admin").await.unwrap();
+        promote_to_admin(&helper, "admin").await;
+        let today = unix_time_to_date(current_unix_time(), 0);
+        let tomorrow = unix_time_to_date(current_unix_time() + 86_400, 0);
+
+        let connection_categories = [
+            category("fish", ["bass", "pike", "carp", "sole"]),
+            category("trees", ["oak", "ash", "elm", "fir"]),
+            category("colors", ["red", "blue", "green", "teal"]),
+            category("planets", ["mars", "venus", "earth", "saturn"]),
+        ];
+        let mut data = CreateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: "Daily Puzzle".to_string(),
+            draft: false,
+            tags: Vec::new(),
+        };
+        let game = create_connections_game(&helper, author_token.as_str(), &data).await.unwrap();
+        data.puzzle_name = "Draft Puzzle".to_string();
+        data.draft = true;
+        let draft_game = create_connections_game(&helper, author_token.as_str(), &data).await.unwrap();
+
+        // Nothing is scheduled yet
+        let err = get_daily_puzzle(&helper, author_token.as_str()).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::NOT_FOUND);
+
+        // Only admins can schedule puzzles, and only published ones on real days which haven't passed
+        let mut schedule_data = ScheduleDailyPuzzleSchema {
+            date: today.clone(),
+            game_id: game.id.clone(),
+        };
+        let err = schedule_daily_puzzle(&helper, author_token.as_str(), &schedule_data).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::FORBIDDEN);
+        for bad_date in ["2100-02-30", "2000-01-01"] {
+            schedule_data.date = bad_date.to_string();
+            let err = schedule_daily_puzzle(&helper, admin_token.as_str(), &schedule_data).await.unwrap_err();
+            assert_eq!(err.0, StatusCode::BAD_REQUEST);
+        }
+        schedule_data.date = today.clone();
+        schedule_data.game_id = draft_game.id.clone();
+        let err = schedule_daily_puzzle(&helper, admin_token.as_str(), &schedule_data).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
+
+        // One puzzle per day
+        schedule_data.game_id = game.id.clone();
+        schedule_daily_puzzle(&helper, admin_token.as_str(), &schedule_data).await.unwrap();
+        let err = schedule_daily_puzzle(&helper, admin_token.as_str(), &schedule_data).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
+        schedule_data.date = tomorrow.clone();
+        schedule_daily_puzzle(&helper, admin_token.as_str(), &schedule_data).await.unwrap();
+
+        let daily_puzzle = get_daily_puzzle(&helper, author_token.as_str()).await.unwrap();
+        assert_eq!(daily_puzzle.date, today);
+        assert_eq!(daily_puzzle.game.slug, game.slug);
+
+        // The queue is admin only, soonest first
+        let err = get_daily_puzzle_queue(&helper, author_token.as_str()).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::FORBIDDEN);
+        let queue = get_daily_puzzle_queue(&helper, admin_token.as_str()).await.unwrap();
+        assert_eq!(
+            queue.iter().map(|daily| daily.date.as_str()).collect::<Vec<&str>>(),
+            [today.as_str(), tomorrow.as_str()]
+        );
+        unschedule_daily_puzzle(&helper, admin_token.as_str(), tomorrow.as_str()).await.unwrap();
+        assert_eq!(get_daily_puzzle_queue(&helper, admin_token.as_str()).await.unwrap().len(), 1);
+
+        // Announcing fails while the channel doesn't exist, which leaves today's puzzle unannounced
+        helper
+            .task_manager
+            .lock()
+            .expect("Failed to get task manager mutex lock")
+            .run_daily_puzzle_task()
+            .await;
+        let daily_puzzles: Collection<Document> = helper.database.collection("game_daily_puzzles");
+        let todays_puzzle = daily_puzzles.find_one(doc! {"date": today.as_str()}).await.unwrap().unwrap();
+        assert!(!todays_puzzle.get_bool("announced").unwrap());
+
+        // Copy a real channel into the ID the app was configured with
+        let channel_data = CreateChannelSchema {
+            name: Some("puzzles".to_string()),
+            channel_type: 1,
+            slug: "puzzles".to_string(),
+            is_private: false,
+        };
+        let channel = create_chat_channel(&helper, admin_token.as_str(), &channel_data).await.unwrap();
+        let channels: Collection<Document> = helper.database.collection("chat_channels");
+        let mut channel_doc = channels
+            .find_one(doc! {"_id": channel._id.parse::<ObjectId>().unwrap()})
+            .await
+            .unwrap()
+            .unwrap();
+        channel_doc.insert("_id", announcement_channel_id);
+        channel_doc.insert("slug", "daily-puzzles");
+        channel_doc.insert("subscribers", Vec::<String>::new());
+        channels.insert_one(channel_doc).await.unwrap();
+
+        // The admin who scheduled it isn't in the channel, so it can't be posted yet
+        helper
+            .task_manager
+            .lock()
+            .expect("Failed to get task manager mutex lock")
+            .run_daily_puzzle_task()
+            .await;
+        let todays_puzzle = daily_puzzles.find_one(doc! {"date": today.as_str()}).await.unwrap().unwrap();
+        assert!(!todays_puzzle.get_bool("announced").unwrap());
+        let admin = get_user_me(&helper, admin_token.as_str()).await.unwrap();
+        channels
+            .update_one(doc! {"_id": announcement_channel_id}, doc! {"$push": {"subscribers": admin.id.as_str()}})
+            .await
+            .unwrap();
+
+        // Today's puzzle is only announced once, no matter how often the task runs
+        for _ in 0..2 {
+            helper
+                .task_manager
+                .lock()
+                .expect("Failed to get task manager mutex lock")
+                .run_daily_puzzle_task()
+                .await;
+        }
+        let messages: Collection<Document> = helper.database.collection("chat_messages");
+        let announcements: Vec<Document> = messages
+            .find(doc! {"channel_id": announcement_channel_id.to_hex()})
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(announcements.len(), 1);
+        assert!(announcements[0].get_str("contents").unwrap().contains(game.slug.as_str()));
+
+        // Deleting a game takes it off the schedule
+        delete_connections_game(&helper, author_token.as_str(), game.id.as_str()).await.unwrap();
+        let err = get_daily_puzzle(&helper, author_token.as_str()).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn connections_ratings_and_discovery() {
+        let helper = TestHelper::init().await;
+
+        let author_token = create_user(&helper, "author", "author").await.unwrap();
+        let second_author_token = create_user(&helper, "second_author", "second_author").await.unwrap();
+        let player_token = create_user(&helper, "player", "player").await.unwrap();
+        let loser_token = create_user(&helper, "loser", "loser").await.unwrap();
+        let newcomer_token = create_user(&helper, "newcomer", "newcomer").await.unwrap();
+        let second_author = get_user_me(&helper, second_author_token.as_str()).await.unwrap();
+
+        let connection_categories = [
+            category("fish", ["bass", "pike", "carp", "sole"]),
+            category("trees", ["oak", "ash", "elm", "fir"]),
+            category("colors", ["red", "blue", "green", "teal"]),
+            category("planets", ["mars", "venus", "earth", "saturn"]),
+        ];
+        let mut data = CreateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: "Alpha".to_string(),
+            draft: false,
+            tags: vec!["Animals".to_string(), " Nature ".to_string()],
+        };
+        let alpha = create_connections_game(&helper, author_token.as_str(), &data).await.unwrap();
+        assert_eq!(alpha.tags, vec!["animals", "nature"]);
+        data.puzzle_name = "Beta".to_string();
+        data.tags = vec!["science".to_string()];
+        let beta = create_connections_game(&helper, second_author_token.as_str(), &data).await.unwrap();
+        data.puzzle_name = "Gamma".to_string();
+        data.draft = true;
+        create_connections_game(&helper, author_token.as_str(), &data).await.unwrap();
+
+        // Newest first by default, drafts are never listed
+        let games = list_connections_games(&helper, player_token.as_str(), "").await.unwrap();
+        assert_eq!(games.iter().map(|game| game.slug.as_str()).collect::<Vec<&str>>(), ["beta", "alpha"]);
+        assert!(games
+            .iter()
+            .all(|game| game.average_rating.is_none() && game.rating_count == 0 && !game.played));
+
+        // Only finished games can be rated, and never by their author
+        let five_stars = rating(5, DifficultyVote::Hard);
+        match rate_connections_game(&helper, player_token.as_str(), "alpha", &five_stars).await {
+            Ok(_) => panic!("Rating a game before playing it should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+        start_connections_session(&helper, player_token.as_str(), "alpha").await.unwrap();
+        match rate_connections_game(&helper, player_token.as_str(), "alpha", &five_stars).await {
+            Ok(_) => panic!("Rating a game before finishing it should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+        match rate_connections_game(&helper, author_token.as_str(), "alpha", &five_stars).await {
+            Ok(_) => panic!("Rating your own game should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+
+        // The player wins both games, the loser loses alpha
+        for slug in ["alpha", "beta"] {
+            for category in &connection_categories {
+                try_connections_solution(&helper, player_token.as_str(), slug, category.category_clues.clone())
+                    .await
+                    .unwrap();
+            }
+        }
+        let wrong_guesses = [
+            ["bass", "oak", "red", "mars"],
+            ["pike", "ash", "blue", "venus"],
+            ["carp", "elm", "green", "earth"],
+            ["sole", "fir", "teal", "saturn"],
+        ];
+        for wrong in &wrong_guesses {
+            try_connections_solution(&helper, loser_token.as_str(), "alpha", wrong.map(|clue| clue.to_string()))
+                .await
+                .unwrap();
+        }
+
+        match rate_connections_game(&helper, player_token.as_str(), "alpha", &rating(6, DifficultyVote::Hard)).await {
+            Ok(_) => panic!("A rating above 5 should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+        let saved = rate_connections_game(&helper, player_token.as_str(), "alpha", &five_stars).await.unwrap();
+        assert_eq!(saved.rating, 5);
+        rate_connections_game(&helper, loser_token.as_str(), "alpha", &rating(3, DifficultyVote::Easy))
+            .await
+            .unwrap();
+        rate_connections_game(&helper, player_token.as_str(), "beta", &rating(2, DifficultyVote::Medium))
+            .await
+            .unwrap();
+
+        let games = list_connections_games(&helper, newcomer_token.as_str(), "?sort=top_rated").await.unwrap();
+        assert_eq!(games[0].slug, "alpha");
+        assert_eq!(games[0].average_rating, Some(4.0));
+        assert_eq!(games[0].rating_count, 2);
+        assert_eq!(games[0].difficulty_votes, DifficultyVotes { easy: 1, medium: 0, hard: 1 });
+
+        // Rating again replaces the old rating
+        rate_connections_game(&helper, player_token.as_str(), "alpha", &rating(1, DifficultyVote::Hard))
+            .await
+            .unwrap();
+        let games = list_connections_games(&helper, newcomer_token.as_str(), "?sort=top_rated").await.unwrap();
+        assert_eq!(games.iter().map(|game| game.slug.as_str()).collect::<Vec<&str>>(), ["beta", "alpha"]);
+        assert_eq!(games[1].average_rating, Some(2.0));
+        assert_eq!(games[1].rating_count, 2);
+
+        // Filters
+        let games = list_connections_games(&helper, newcomer_token.as_str(), "?tag=ANIMALS").await.unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].id, alpha.id);
+        let games = list_connections_games(&helper, newcomer_token.as_str(), format!("?author_id={}", second_author.id).as_str())
+            .await
+            .unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].id, beta.id);
+        let games = list_connections_games(&helper, loser_token.as_str(), "?unplayed=true").await.unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].id, beta.id);
+        assert_eq!(
+            list_connections_games(&helper, newcomer_token.as_str(), "?unplayed=true")
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // Pages
+        let games = list_connections_games(&helper, newcomer_token.as_str(), "?per_page=1&page=2")
+            .await
+            .unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].id, alpha.id);
+        assert!(list_connections_games(&helper, newcomer_token.as_str(), "?per_page=1&page=3")
+            .await
+            .unwrap()
+            .is_empty());
+        // The last two are pages so far in that skipping to them overflows
+        for bad_page in [
+            "?page=0",
+            "?per_page=0",
+            "?per_page=51",
+            "?per_page=50&page=18446744073709551615",
+            "?per_page=2&page=9223372036854775807",
+        ] {
+            match list_connections_games(&helper, newcomer_token.as_str(), bad_page).await {
+                Ok(_) => panic!("Listing connections games with {bad_page} should fail"),
+                Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+            }
+        }
+
+        // Deleting a game deletes its ratings
+        delete_connections_game(&helper, author_token.as_str(), alpha.id.as_str()).await.unwrap();
+        let ratings: Collection<Document> = helper.database.collection("game_ratings");
+        assert_eq!(ratings.count_documents(doc! {}).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn connections_share_results() {
+        let helper = TestHelper::init().await;
+
+        let author_token = create_user(&helper, "author", "author").await.unwrap();
+        let player_token = create_user(&helper, "player", "player").await.unwrap();
+        let outsider_token = create_user(&helper, "outsider", "outsider").await.unwrap();
+
+        // Written from trickiest to most straightforward
+        let mut connection_categories = [
+            category("fish", ["bass", "pike", "carp", "sole"]),
+            category("trees", ["oak", "ash", "elm", "fir"]),
+            category("colors", ["red", "blue", "green", "teal"]),
+            category("planets", ["mars", "venus", "earth", "saturn"]),
+        ];
+        for (category, difficulty) in connection_categories.iter_mut().zip([3, 2, 1, 0]) {
+            category.difficulty = Some(difficulty);
+        }
+        let data = CreateConnectionGameSchema {
+            connection_categories: connection_categories.clone(),
+            puzzle_name: "Share Puzzle".to_string(),
+            draft: false,
+            tags: Vec::new(),
+        };
+        let game = create_connections_game(&helper, author_token.as_str(), &data).await.unwrap();
+        let slug = game.slug.as_str();
+
+        // Nothing to share before the game is over
+        match get_shareable_result(&helper, player_token.as_str(), slug).await {
+            Ok(_) => panic!("Sharing an unfinished game should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+
+        let guesses = [
+            ["bass", "pike", "carp", "oak"],
+            ["mars", "venus", "earth", "saturn"],
+            ["bass", "pike", "carp", "sole"],
+            ["oak", "ash", "elm", "fir"],
+            ["red", "blue", "green", "teal"],
+        ];
+        for guess in &guesses {
+            try_connections_solution(&helper, player_token.as_str(), slug, guess.map(|clue| clue.to_string()))
+                .await
+                .unwrap();
+        }
+        let shareable = get_shareable_result(&helper, player_token.as_str(), slug).await.unwrap();
+        assert_eq!(
+            shareable.text,
+            "Connections: Share Puzzle\nSolved with 1 mistake\n🟪🟪🟪🟦\n🟨🟨🟨🟨\n🟪🟪🟪🟪\n🟦🟦🟦🟦\n🟩🟩🟩🟩"
+        );
+
+        // Posting it needs to be allowed to send messages in the channel
+        let channel_data = CreateChannelSchema {
+            name: Some("games".to_string()),
+            channel_type: 1,
+            slug: "games".to_string(),
+            is_private: false,
+        };
+        let channel = create_chat_channel(&helper, player_token.as_str(), &channel_data).await.unwrap();
+        let share_data = ShareResultSchema {
+            channel_id: channel._id.clone(),
+        };
+        let message = share_connections_result(&helper, player_token.as_str(), slug, &share_data).await.unwrap();
+        assert_eq!(message.contents, shareable.text);
+        assert_eq!(message.channel_id, channel._id);
+
+        let channel_data = CreateChannelSchema {
+            name: Some("elsewhere".to_string()),
+            channel_type: 1,
+            slug: "elsewhere".to_string(),
+            is_private: false,
+        };
+        let other_channel = create_chat_channel(&helper, outsider_token.as_str(), &channel_data).await.unwrap();
+        let share_data = ShareResultSchema {
+            channel_id: other_channel._id.clone(),
+        };
+        match share_connections_result(&helper, player_token.as_str(), slug, &share_data).await {
+            Ok(_) => panic!("Sharing to a channel the player isn't in should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+    }
 }
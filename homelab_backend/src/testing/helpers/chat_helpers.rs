@@ -1,15 +1,25 @@
 use crate::models::chat::{
-    chat_channel::ReturnChannel,
-    message::ChatMessage,
-    packet::{WebSocketError, WebSocketRequest, WebSocketResponse},
-    validation::CreateChannelSchema,
+    chat_channel::{ReturnChannel, ReturnDirectMessageChannel},
+    invite::ChatInvite,
+    message::{ChatMessage, MessageSearchResult},
+    packet::{
+        ChannelEventResponse, MessagePinnedResponse, ReadReceiptResponse, ResumeResponse, ThreadResponse, WebSocketError, WebSocketRequest,
+        WebSocketRequestFrame, WebSocketResponse, WebSocketResponseFrame,
+    },
+    server::{ChatServer, PermissionOverride, ReturnServer},
+    socket_ticket::ReturnSocketTicket,
+    validation::{
+        CreateChannelSchema, CreateInviteSchema, CreateServerCategorySchema, CreateServerChannelSchema, CreateServerRoleSchema, CreateServerSchema,
+        UpdateChannelSchema,
+    },
 };
 use crate::testing::{
-    helpers::{get_request, post_request, put_request},
+    helpers::{delete_request, get_request, get_text_request, post_request, put_request},
     TestHelper,
 };
 use axum::http::StatusCode;
 use futures::{SinkExt, StreamExt};
+use serde::Serialize;
 use serde_json::json;
 use std::time::Duration;
 use tokio::{net::TcpStream, time::timeout};
@@ -44,20 +54,177 @@ pub async fn get_channel_by_id(test_helper: &TestHelper, token: &str, channel_id
     get_request(test_helper, path.as_str(), token).await
 }
 
+pub async fn open_direct_message(test_helper: &TestHelper, token: &str, user_id: &str) -> Result<ReturnDirectMessageChannel, (StatusCode, String)> {
+    let data = json!({"user_id": user_id});
+    put_request(test_helper, "/chat/direct_messages", data, token).await
+}
+
+pub async fn list_direct_messages(test_helper: &TestHelper, token: &str) -> Result<Vec<ReturnDirectMessageChannel>, (StatusCode, String)> {
+    get_request(test_helper, "/chat/direct_messages", token).await
+}
+
+pub async fn search_messages(test_helper: &TestHelper, token: &str, query_params: &str) -> Result<Vec<MessageSearchResult>, (StatusCode, String)> {
+    let path = format!("/chat/search{query_params}");
+    get_request(test_helper, path.as_str(), token).await
+}
+
+pub async fn update_channel(
+    test_helper: &TestHelper,
+    token: &str,
+    channel_id: &str,
+    update_data: &UpdateChannelSchema,
+) -> Result<ReturnChannel, (StatusCode, String)> {
+    let path = format!("/chat/channels/{channel_id}");
+    put_request(test_helper, path.as_str(), json!(update_data), token).await
+}
+
+pub async fn delete_channel(test_helper: &TestHelper, token: &str, channel_id: &str) -> Result<(), (StatusCode, String)> {
+    let path = format!("/chat/channels/{channel_id}");
+    delete_request(test_helper, path.as_str(), token).await
+}
+
+// Used for the kick, ban, unban and transfer endpoints which all take a user ID
+pub async fn moderate_channel_member(
+    test_helper: &TestHelper,
+    token: &str,
+    channel_id: &str,
+    action: &str,
+    user_id: &str,
+) -> Result<ReturnChannel, (StatusCode, String)> {
+    let path = format!("/chat/channels/{channel_id}/{action}");
+    let data = json!({"user_id": user_id});
+    put_request(test_helper, path.as_str(), data, token).await
+}
+
+pub async fn create_invite(
+    test_helper: &TestHelper,
+    token: &str,
+    channel_id: &str,
+    invite_data: &CreateInviteSchema,
+) -> Result<ChatInvite, (StatusCode, String)> {
+    let path = format!("/chat/channels/{channel_id}/invites");
+    post_request(test_helper, path.as_str(), json!(invite_data), Some(token)).await
+}
+
+pub async fn list_invites(test_helper: &TestHelper, token: &str, channel_id: &str) -> Result<Vec<ChatInvite>, (StatusCode, String)> {
+    let path = format!("/chat/channels/{channel_id}/invites");
+    get_request(test_helper, path.as_str(), token).await
+}
+
+pub async fn revoke_invite(test_helper: &TestHelper, token: &str, code: &str) -> Result<(), (StatusCode, String)> {
+    let path = format!("/chat/invites/{code}");
+    delete_request(test_helper, path.as_str(), token).await
+}
+
+pub async fn join_with_invite(test_helper: &TestHelper, token: &str, code: &str) -> Result<ReturnChannel, (StatusCode, String)> {
+    let path = format!("/chat/invites/{code}/join");
+    put_request(test_helper, path.as_str(), json!({}), token).await
+}
+
+pub async fn create_server(test_helper: &TestHelper, token: &str, server_data: &CreateServerSchema) -> Result<ReturnServer, (StatusCode, String)> {
+    post_request(test_helper, "/chat/servers", json!(server_data), Some(token)).await
+}
+
+pub async fn get_server(test_helper: &TestHelper, token: &str, server_id: &str) -> Result<ReturnServer, (StatusCode, String)> {
+    let path = format!("/chat/servers/{server_id}");
+    get_request(test_helper, path.as_str(), token).await
+}
+
+pub async fn list_servers(test_helper: &TestHelper, token: &str) -> Result<Vec<ChatServer>, (StatusCode, String)> {
+    get_request(test_helper, "/chat/servers", token).await
+}
+
+pub async fn create_server_channel(
+    test_helper: &TestHelper,
+    token: &str,
+    server_id: &str,
+    channel_data: &CreateServerChannelSchema,
+) -> Result<ReturnChannel, (StatusCode, String)> {
+    let path = format!("/chat/servers/{server_id}/channels");
+    post_request(test_helper, path.as_str(), json!(channel_data), Some(token)).await
+}
+
+pub async fn create_server_category(
+    test_helper: &TestHelper,
+    token: &str,
+    server_id: &str,
+    category_data: &CreateServerCategorySchema,
+) -> Result<ChatServer, (StatusCode, String)> {
+    let path = format!("/chat/servers/{server_id}/categories");
+    post_request(test_helper, path.as_str(), json!(category_data), Some(token)).await
+}
+
+pub async fn create_server_role(
+    test_helper: &TestHelper,
+    token: &str,
+    server_id: &str,
+    role_data: &CreateServerRoleSchema,
+) -> Result<ChatServer, (StatusCode, String)> {
+    let path = format!("/chat/servers/{server_id}/roles");
+    post_request(test_helper, path.as_str(), json!(role_data), Some(token)).await
+}
+
+pub async fn set_member_roles(
+    test_helper: &TestHelper,
+    token: &str,
+    server_id: &str,
+    user_id: &str,
+    role_ids: &[String],
+) -> Result<ChatServer, (StatusCode, String)> {
+    let path = format!("/chat/servers/{server_id}/members/{user_id}/roles");
+    put_request(test_helper, path.as_str(), json!({"role_ids": role_ids}), token).await
+}
+
+pub async fn set_permission_override(
+    test_helper: &TestHelper,
+    token: &str,
+    server_id: &str,
+    channel_id: &str,
+    permission_override: &PermissionOverride,
+) -> Result<ReturnChannel, (StatusCode, String)> {
+    let path = format!("/chat/servers/{server_id}/channels/{channel_id}/overrides");
+    put_request(test_helper, path.as_str(), json!(permission_override), token).await
+}
+
+pub async fn remove_server_member(test_helper: &TestHelper, token: &str, server_id: &str, user_id: &str) -> Result<(), (StatusCode, String)> {
+    let path = format!("/chat/servers/{server_id}/members/{user_id}");
+    delete_request(test_helper, path.as_str(), token).await
+}
+
+pub async fn create_server_invite(
+    test_helper: &TestHelper,
+    token: &str,
+    server_id: &str,
+    invite_data: &CreateInviteSchema,
+) -> Result<ChatInvite, (StatusCode, String)> {
+    let path = format!("/chat/servers/{server_id}/invites");
+    post_request(test_helper, path.as_str(), json!(invite_data), Some(token)).await
+}
+
+pub async fn join_server(test_helper: &TestHelper, token: &str, code: &str) -> Result<ReturnServer, (StatusCode, String)> {
+    let path = format!("/chat/servers/join/{code}");
+    put_request(test_helper, path.as_str(), json!({}), token).await
+}
+
+pub async fn export_channel(test_helper: &TestHelper, token: &str, channel_id: &str, format: &str) -> Result<(String, String), (StatusCode, String)> {
+    let path = format!("/chat/channels/{channel_id}/export?format={format}");
+    get_text_request(test_helper, path.as_str(), token).await
+}
+
+pub async fn get_socket_ticket(test_helper: &TestHelper, token: &str) -> Result<ReturnSocketTicket, (StatusCode, String)> {
+    post_request(test_helper, "/chat/ws/ticket", json!({}), Some(token)).await
+}
+
 pub async fn receive_chat_message(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> Result<ChatMessage, WebSocketError> {
-    match guarded_receive_data_from_socket(socket).await {
-        WebSocketResponse::SendChatMessage(chat_message) => Ok(chat_message),
-        WebSocketResponse::SendError(ws_err) => Err(ws_err),
-        WebSocketResponse::MessageCreated(_message_created) => {
-            // When a user creates a message they are sent a MessageCreated, but it is not guaranteed which order
-            // the two responses will come in. Try to poll again
-            match guarded_receive_data_from_socket(socket).await {
-                WebSocketResponse::SendChatMessage(chat_message) => Ok(chat_message),
-                WebSocketResponse::SendError(ws_err) => Err(ws_err),
-                _ => panic!("Should only SendChatMessage or SendError when getting a chat message"),
-            }
+    loop {
+        match guarded_receive_data_from_socket(socket).await {
+            WebSocketResponse::SendChatMessage(chat_message) => return Ok(chat_message),
+            WebSocketResponse::SendError(ws_err) => return Err(ws_err),
+            // When a user creates a message they are sent a MessageCreated, but messages are delivered separately so
+            // it is not guaranteed which order the two responses will come in. Try to poll again
+            WebSocketResponse::MessageCreated(_message_created) => continue,
+            _ => panic!("Should only receive SendChatMessage or SendError when getting a chat message"),
         }
-        _ => panic!("Should only receive SendChatMessage or SendError when getting a chat message"),
     }
 }
 
@@ -69,26 +236,103 @@ pub async fn receive_chat_state(socket: &mut WebSocketStream<MaybeTlsStream<TcpS
     }
 }
 
+pub async fn receive_read_receipt(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> Result<ReadReceiptResponse, WebSocketError> {
+    loop {
+        match guarded_receive_data_from_socket(socket).await {
+            WebSocketResponse::MarkedRead(read_receipt) => return Ok(read_receipt),
+            WebSocketResponse::ReadReceipt(read_receipt) => return Ok(read_receipt),
+            WebSocketResponse::SendError(ws_err) => return Err(ws_err),
+            // A MessageCreated can be left over in the socket from a message this user sent
+            WebSocketResponse::MessageCreated(_message_created) => continue,
+            _ => panic!("Should only receive MarkedRead, ReadReceipt or SendError when getting a read receipt"),
+        }
+    }
+}
+
+pub async fn receive_thread(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> Result<ThreadResponse, WebSocketError> {
+    loop {
+        match guarded_receive_data_from_socket(socket).await {
+            WebSocketResponse::SendThread(thread) => return Ok(thread),
+            WebSocketResponse::SendError(ws_err) => return Err(ws_err),
+            // A MessageCreated can be left over in the socket from a message this user sent
+            WebSocketResponse::MessageCreated(_message_created) => continue,
+            _ => panic!("Should only receive SendThread or SendError when getting a thread"),
+        }
+    }
+}
+
+pub async fn receive_message_pinned(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> Result<MessagePinnedResponse, WebSocketError> {
+    loop {
+        match guarded_receive_data_from_socket(socket).await {
+            WebSocketResponse::MessagePinned(message_pinned) => return Ok(message_pinned),
+            WebSocketResponse::SendError(ws_err) => return Err(ws_err),
+            // A MessageCreated can be left over in the socket from a message this user sent
+            WebSocketResponse::MessageCreated(_message_created) => continue,
+            _ => panic!("Should only receive MessagePinned or SendError when pinning a message"),
+        }
+    }
+}
+
+pub async fn receive_resumed(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> Result<ResumeResponse, WebSocketError> {
+    loop {
+        match guarded_receive_data_from_socket(socket).await {
+            WebSocketResponse::Resumed(resumed) => return Ok(resumed),
+            WebSocketResponse::SendError(ws_err) => return Err(ws_err),
+            // A MessageCreated can be left over in the socket from a message this user sent
+            WebSocketResponse::MessageCreated(_message_created) => continue,
+            _ => panic!("Should only receive Resumed or SendError when resuming"),
+        }
+    }
+}
+
+pub async fn receive_channel_event(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> ChannelEventResponse {
+    match guarded_receive_data_from_socket(socket).await {
+        WebSocketResponse::SendChannelEvent(channel_event) => channel_event,
+        _ => panic!("Should only receive SendChannelEvent when getting a channel event"),
+    }
+}
+
 // Wrap the function which actually gets the message in a timeout so we panic if there is no data
 // in the socket, rather than hang endlessly
 async fn guarded_receive_data_from_socket(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> WebSocketResponse {
+    receive_response_frame(socket).await.response
+}
+
+// Like the other receive helpers, but keeps the request_id the response was sent with
+pub async fn receive_response_frame(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> WebSocketResponseFrame {
     match timeout(Duration::from_secs(10), receive_data_from_socket(socket)).await {
         Ok(ws_response_data) => ws_response_data,
         Err(_) => panic!("Failed to receive a chat message on a websocket client"),
     }
 }
 
-async fn receive_data_from_socket(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> WebSocketResponse {
-    match socket.next().await {
-        Some(server_res) => match server_res {
-            Ok(server_message) => match server_message {
-                tungstenite::Message::Text(msg) => serde_json::from_str::<WebSocketResponse>(msg.as_str())
-                    .expect("Failed to deserialize chat creation response into a WebSocketResponse"),
-                _ => panic!("Server should respond with a Message::Text variant when receiving data"),
+// Skips past broadcasts and responses to other requests
+pub async fn receive_response_to(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>, request_id: &str) -> WebSocketResponse {
+    loop {
+        let frame = receive_response_frame(socket).await;
+        if frame.request_id.as_deref() == Some(request_id) {
+            return frame.response;
+        }
+    }
+}
+
+async fn receive_data_from_socket(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> WebSocketResponseFrame {
+    loop {
+        match socket.next().await {
+            Some(server_res) => match server_res {
+                Ok(server_message) => match server_message {
+                    tungstenite::Message::Text(msg) => {
+                        return serde_json::from_str::<WebSocketResponseFrame>(msg.as_str())
+                            .expect("Failed to deserialize chat creation response into a WebSocketResponse")
+                    }
+                    // The server's heartbeat, tungstenite answers these on its own
+                    tungstenite::Message::Ping(_) | tungstenite::Message::Pong(_) => continue,
+                    _ => panic!("Server should respond with a Message::Text variant when receiving data"),
+                },
+                Err(e) => panic!("Server responded with error: {e}"),
             },
-            Err(e) => panic!("Server responded with error: {e}"),
-        },
-        None => panic!("Failed to get a response from the server when one was expected"),
+            None => panic!("Failed to get a response from the server when one was expected"),
+        }
     }
 }
 
@@ -101,7 +345,18 @@ pub async fn send_websocket_request(socket: &mut WebSocketStream<MaybeTlsStream<
     }
 }
 
-async fn send_data_over_socket(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>, data: &WebSocketRequest) {
+pub async fn send_websocket_request_with_id(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>, data: &WebSocketRequest, request_id: &str) {
+    let frame = WebSocketRequestFrame {
+        request: data.clone(),
+        request_id: Some(request_id.to_owned()),
+    };
+    match timeout(Duration::from_secs(10), send_data_over_socket(socket, &frame)).await {
+        Ok(()) => (),
+        Err(_) => panic!("Failed to send data on a websocket client"),
+    }
+}
+
+async fn send_data_over_socket<T: Serialize>(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>, data: &T) {
     let serialized = serde_json::to_string(data).expect("Failed to serialize WebSocketRequest");
     socket
         .send(tungstenite::Message::Text(serialized))
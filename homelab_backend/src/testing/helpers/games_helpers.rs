@@ -1,6 +1,15 @@
-use crate::models::games::{validation::CreateConnectionGameSchema, ConnectionGame, MinimalConnectionsGame, PlayConnectionGame, TrySolveRow};
+use crate::models::chat::message::ChatMessage;
+use crate::models::games::{
+    daily::{DailyPuzzle, ReturnDailyPuzzle},
+    rating::ConnectionGameRating,
+    session::ReturnConnectionGameSession,
+    share::ShareableResult,
+    stats::{AuthorStats, LeaderboardEntry, PlayerStats},
+    validation::{CreateConnectionGameSchema, RateConnectionGameSchema, ScheduleDailyPuzzleSchema, ShareResultSchema, UpdateConnectionGameSchema},
+    ConnectionGame, ListedConnectionsGame, MinimalConnectionsGame, PlayConnectionGame, TrySolveRow,
+};
 use crate::testing::{
-    helpers::{get_request, post_request, put_request},
+    helpers::{delete_request, get_request, post_request, put_request},
     TestHelper,
 };
 use axum::http::StatusCode;
@@ -15,16 +24,37 @@ pub async fn create_connections_game(
     post_request(test_helper, "/games/connections", data, Some(token)).await
 }
 
+pub async fn update_connections_game(
+    test_helper: &TestHelper,
+    token: &str,
+    game_id: &str,
+    update: &UpdateConnectionGameSchema,
+) -> Result<ConnectionGame, (StatusCode, String)> {
+    let path = format!("/games/connections/{game_id}");
+    put_request(test_helper, path.as_str(), json!(update), token).await
+}
+
+pub async fn publish_connections_game(test_helper: &TestHelper, token: &str, game_id: &str) -> Result<ConnectionGame, (StatusCode, String)> {
+    let path = format!("/games/connections/{game_id}/publish");
+    put_request(test_helper, path.as_str(), json!({}), token).await
+}
+
+pub async fn delete_connections_game(test_helper: &TestHelper, token: &str, game_id: &str) -> Result<(), (StatusCode, String)> {
+    let path = format!("/games/connections/{game_id}");
+    delete_request(test_helper, path.as_str(), token).await
+}
+
+pub async fn list_my_connections_games(test_helper: &TestHelper, token: &str) -> Result<Vec<MinimalConnectionsGame>, (StatusCode, String)> {
+    get_request(test_helper, "/games/connections/mine", token).await
+}
+
 pub async fn list_connections_games(
     test_helper: &TestHelper,
     token: &str,
-    my_connections_games: bool,
-) -> Result<Vec<MinimalConnectionsGame>, (StatusCode, String)> {
-    let uri = match my_connections_games {
-        true => "/games/connections/mine",
-        false => "/games/connections",
-    };
-    get_request(test_helper, uri, token).await
+    query_params: &str,
+) -> Result<Vec<ListedConnectionsGame>, (StatusCode, String)> {
+    let path = format!("/games/connections{query_params}");
+    get_request(test_helper, path.as_str(), token).await
 }
 
 pub async fn get_game_to_play(test_helper: &TestHelper, token: &str, game_slug: &str) -> Result<PlayConnectionGame, (StatusCode, String)> {
@@ -32,6 +62,15 @@ pub async fn get_game_to_play(test_helper: &TestHelper, token: &str, game_slug:
     get_request(test_helper, path.as_str(), token).await
 }
 
+pub async fn start_connections_session(
+    test_helper: &TestHelper,
+    token: &str,
+    game_slug: &str,
+) -> Result<ReturnConnectionGameSession, (StatusCode, String)> {
+    let path = format!("/games/connections/play/{game_slug}/session");
+    put_request(test_helper, path.as_str(), json!({}), token).await
+}
+
 pub async fn try_connections_solution(
     test_helper: &TestHelper,
     token: &str,
@@ -42,3 +81,67 @@ pub async fn try_connections_solution(
     let data = json!(guess);
     put_request(test_helper, path.as_str(), data, token).await
 }
+
+pub async fn get_connections_leaderboard(
+    test_helper: &TestHelper,
+    token: &str,
+    game_slug: &str,
+) -> Result<Vec<LeaderboardEntry>, (StatusCode, String)> {
+    let path = format!("/games/connections/play/{game_slug}/leaderboard");
+    get_request(test_helper, path.as_str(), token).await
+}
+
+pub async fn rate_connections_game(
+    test_helper: &TestHelper,
+    token: &str,
+    game_slug: &str,
+    rating: &RateConnectionGameSchema,
+) -> Result<ConnectionGameRating, (StatusCode, String)> {
+    let path = format!("/games/connections/play/{game_slug}/rating");
+    put_request(test_helper, path.as_str(), json!(rating), token).await
+}
+
+pub async fn get_shareable_result(test_helper: &TestHelper, token: &str, game_slug: &str) -> Result<ShareableResult, (StatusCode, String)> {
+    let path = format!("/games/connections/play/{game_slug}/share");
+    get_request(test_helper, path.as_str(), token).await
+}
+
+pub async fn share_connections_result(
+    test_helper: &TestHelper,
+    token: &str,
+    game_slug: &str,
+    share_data: &ShareResultSchema,
+) -> Result<ChatMessage, (StatusCode, String)> {
+    let path = format!("/games/connections/play/{game_slug}/share");
+    post_request(test_helper, path.as_str(), json!(share_data), Some(token)).await
+}
+
+pub async fn get_connections_player_stats(test_helper: &TestHelper, token: &str) -> Result<PlayerStats, (StatusCode, String)> {
+    get_request(test_helper, "/games/connections/stats/me", token).await
+}
+
+pub async fn get_connections_author_stats(test_helper: &TestHelper, token: &str) -> Result<AuthorStats, (StatusCode, String)> {
+    get_request(test_helper, "/games/connections/stats/authored", token).await
+}
+
+pub async fn schedule_daily_puzzle(
+    test_helper: &TestHelper,
+    token: &str,
+    schedule_data: &ScheduleDailyPuzzleSchema,
+) -> Result<DailyPuzzle, (StatusCode, String)> {
+    let data = json!(schedule_data);
+    post_request(test_helper, "/games/connections/daily", data, Some(token)).await
+}
+
+pub async fn get_daily_puzzle(test_helper: &TestHelper, token: &str) -> Result<ReturnDailyPuzzle, (StatusCode, String)> {
+    get_request(test_helper, "/games/connections/daily", token).await
+}
+
+pub async fn get_daily_puzzle_queue(test_helper: &TestHelper, token: &str) -> Result<Vec<DailyPuzzle>, (StatusCode, String)> {
+    get_request(test_helper, "/games/connections/daily/queue", token).await
+}
+
+pub async fn unschedule_daily_puzzle(test_helper: &TestHelper, token: &str, date: &str) -> Result<(), (StatusCode, String)> {
+    let path = format!("/games/connections/daily/{date}");
+    delete_request(test_helper, path.as_str(), token).await
+}
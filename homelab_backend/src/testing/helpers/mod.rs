@@ -12,6 +12,7 @@ pub mod games_helpers;
 pub mod log_helpers;
 pub mod reminder_helpers;
 pub mod user_helpers;
+pub mod word_games_helpers;
 
 pub fn list_to_query_params<T>(list_name: &str, items: Vec<T>) -> String
 where
@@ -86,6 +87,30 @@ where
     }
 }
 
+// Like get_request, for responses which aren't JSON. Returns the content type along with the body
+pub async fn get_text_request(test_helper: &TestHelper, path: &str, token: &str) -> Result<(String, String), (StatusCode, String)> {
+    let address = &test_helper.address;
+    let req = Request::builder()
+        .uri(format!("http://{address}/api{path}"))
+        .method("GET")
+        .header("Host", "localhost")
+        .header("authorization", token)
+        .body(Body::empty())
+        .expect("Failed to construct a GET request");
+    let res = test_helper.client.request(req).await.expect("Failed to make a GET request");
+    let status = res.status();
+    let content_type = res
+        .headers()
+        .get("Content-Type")
+        .map(|content_type| content_type.to_str().unwrap().to_owned())
+        .unwrap_or_default();
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    match status {
+        StatusCode::OK => Ok((content_type, String::from_utf8(body.to_vec()).unwrap())),
+        _ => Err((status, read_error_message(body))),
+    }
+}
+
 pub async fn put_request<T, U>(test_helper: &TestHelper, path: &str, data: T, token: &str) -> Result<U, (StatusCode, String)>
 where
     T: Serialize,
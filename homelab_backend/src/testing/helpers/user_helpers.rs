@@ -2,6 +2,10 @@ use crate::models::user::{validation::UpdateUserSchema, ReturnUser};
 use crate::testing::helpers::{delete_request, get_request, post_request, put_request};
 use crate::testing::TestHelper;
 use axum::http::StatusCode;
+use mongodb::{
+    bson::{doc, Document},
+    Collection,
+};
 use serde_json::json;
 
 pub async fn create_user(test_helper: &TestHelper, username: &str, password: &str) -> Result<String, (StatusCode, String)> {
@@ -25,3 +29,12 @@ pub async fn get_user_me(test_helper: &TestHelper, token: &str) -> Result<Return
 pub async fn delete_user_me(test_helper: &TestHelper, token: &str) -> Result<(), (StatusCode, String)> {
     delete_request(test_helper, "/users/me", token).await
 }
+
+// There is no route for making someone an admin, so it's done straight on the database
+pub async fn promote_to_admin(test_helper: &TestHelper, username: &str) {
+    let users: Collection<Document> = test_helper.database.collection("users");
+    users
+        .update_one(doc! {"username": username}, doc! {"$set": {"auth_level": 1_i64}})
+        .await
+        .expect("Failed to promote a user to admin");
+}
@@ -0,0 +1,47 @@
+use crate::models::games::words::{
+    session::ReturnWordGameSession,
+    stats::WordPlayerStats,
+    validation::{CreateWordGameSchema, WordGuessSchema},
+    MinimalWordGame, WordGuessResult,
+};
+use crate::testing::{
+    helpers::{delete_request, get_request, post_request, put_request},
+    TestHelper,
+};
+use axum::http::StatusCode;
+use serde_json::json;
+
+pub async fn create_word_game(test_helper: &TestHelper, token: &str, answer: Option<&str>) -> Result<MinimalWordGame, (StatusCode, String)> {
+    let data = CreateWordGameSchema {
+        answer: answer.map(|answer| answer.to_string()),
+    };
+    post_request(test_helper, "/games/words", json!(data), Some(token)).await
+}
+
+pub async fn list_word_games(test_helper: &TestHelper, token: &str, my_word_games: bool) -> Result<Vec<MinimalWordGame>, (StatusCode, String)> {
+    let uri = match my_word_games {
+        true => "/games/words/mine",
+        false => "/games/words",
+    };
+    get_request(test_helper, uri, token).await
+}
+
+pub async fn delete_word_game(test_helper: &TestHelper, token: &str, game_id: &str) -> Result<(), (StatusCode, String)> {
+    let path = format!("/games/words/{game_id}");
+    delete_request(test_helper, path.as_str(), token).await
+}
+
+pub async fn start_word_session(test_helper: &TestHelper, token: &str, game_id: &str) -> Result<ReturnWordGameSession, (StatusCode, String)> {
+    let path = format!("/games/words/{game_id}/session");
+    put_request(test_helper, path.as_str(), json!({}), token).await
+}
+
+pub async fn guess_word(test_helper: &TestHelper, token: &str, game_id: &str, guess: &str) -> Result<WordGuessResult, (StatusCode, String)> {
+    let path = format!("/games/words/{game_id}/guess");
+    let data = WordGuessSchema { guess: guess.to_string() };
+    put_request(test_helper, path.as_str(), json!(data), token).await
+}
+
+pub async fn get_word_player_stats(test_helper: &TestHelper, token: &str) -> Result<WordPlayerStats, (StatusCode, String)> {
+    get_request(test_helper, "/games/words/stats/me", token).await
+}
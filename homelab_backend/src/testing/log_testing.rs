@@ -1,5 +1,6 @@
 #[cfg(test)]
 mod log_testing {
+    use crate::tasks::log_creation_task::LogCreationTask;
     use crate::testing::{
         helpers::{
             log_helpers::get_logs_for_user,
@@ -69,4 +70,19 @@ mod log_testing {
         assert_eq!(logs.len(), 1);
         assert_eq!(logs[0].user_id, user_two.id);
     }
+
+    #[test]
+    fn log_redacts_credentials() {
+        // Credentials in the query are hidden, everything else is kept
+        let uri = "/api/users/me?auth_token=secret&page=2&Ticket=secret".to_owned();
+        let log_task = LogCreationTask::new("GET".to_owned(), uri, "-1".to_owned(), 0);
+        assert_eq!(
+            log_task.to_string(),
+            "0: GET /api/users/me?auth_token=<redacted>&page=2&Ticket=<redacted> from -1"
+        );
+
+        // A uri without a query is left alone
+        let log_task = LogCreationTask::new("GET".to_owned(), "/api/users/me".to_owned(), "-1".to_owned(), 0);
+        assert_eq!(log_task.to_string(), "0: GET /api/users/me from -1");
+    }
 }
@@ -6,8 +6,14 @@ mod helpers;
 mod log_testing;
 mod reminder_testing;
 mod user_testing;
+mod word_games_testing;
 
-use crate::{app::generate_app, db::db_setup, tasks::task_manager::TaskManager};
+use crate::{
+    app::{generate_app, Config},
+    db::db_setup,
+    rate_limit::{RateLimit, RateLimits},
+    tasks::task_manager::TaskManager,
+};
 use axum::body::Body;
 use hyper_util::client::legacy::connect::HttpConnector;
 use hyper_util::client::legacy::Client;
@@ -34,26 +40,52 @@ pub struct TestHelper {
 
 impl TestHelper {
     pub async fn init() -> Self {
+        Self::init_with_config(Self::config()).await
+    }
+
+    pub fn config() -> Config {
+        // Tests make requests far faster than any person would, so the limits are raised out of the way
+        let mut config = Config::init();
+        let unlimited = RateLimit::new(100_000, 1);
+        config.rate_limits = RateLimits {
+            socket_requests: unlimited,
+            user_messages: unlimited,
+            channel_messages: unlimited,
+            http_requests: unlimited,
+        };
+        config
+    }
+
+    pub async fn init_with_config(config: Config) -> Self {
         let connection_string = dotenv!("CONNECTION_STRING").to_owned();
         let database = db_setup::initialize_database_handle(connection_string, "test_db").await;
+        let helper = Self::serve(database, config).await;
+        helper.wipe_database().await;
+        helper
+    }
+
+    // Starts another instance of the app on the same database, like a second backend behind a load
+    // balancer. Nothing is wiped so it sees everything the first instance has done
+    pub async fn init_second_instance(&self, config: Config) -> Self {
+        Self::serve(self.database.clone(), config).await
+    }
 
+    async fn serve(database: Database, config: Config) -> Self {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
         let address = listener.local_addr().unwrap();
-        let (app, task_manager) = generate_app(database.clone()).await;
+        let (app, task_manager) = generate_app(database.clone(), config).await;
         tokio::spawn(async move {
             axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
                 .await
                 .unwrap()
         });
         let client = Client::builder(hyper_util::rt::TokioExecutor::new()).build_http();
-        let helper = Self {
+        Self {
             client,
             address,
             database,
             task_manager,
-        };
-        helper.wipe_database().await;
-        helper
+        }
     }
 
     pub async fn wipe_database(&self) {
@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod word_games_testing {
+    use crate::models::games::{
+        session::SessionState,
+        words::{
+            dictionary::{is_valid_word, random_answer},
+            evaluate_guess, LetterResult,
+        },
+    };
+    use crate::testing::{
+        helpers::{
+            user_helpers::{create_user, get_user_me},
+            word_games_helpers::{create_word_game, delete_word_game, get_word_player_stats, guess_word, list_word_games, start_word_session},
+        },
+        TestHelper,
+    };
+    use hyper::StatusCode;
+    use LetterResult::{Absent, Correct, Present};
+
+    #[test]
+    fn word_guess_evaluation() {
+        assert_eq!(evaluate_guess("crane", "crane"), vec![Correct; 5]);
+        assert_eq!(evaluate_guess("crane", "nacre"), vec![Present, Present, Present, Present, Correct]);
+        assert_eq!(evaluate_guess("crane", "pilot"), vec![Absent; 5]);
+
+        // A letter guessed more times than it's in the answer is only scored for as many copies as
+        // the answer has, and an exact match claims its copy before any yellows do
+        assert_eq!(evaluate_guess("abbey", "keeps"), vec![Absent, Present, Absent, Absent, Absent]);
+        assert_eq!(evaluate_guess("crane", "eerie"), vec![Absent, Absent, Present, Absent, Correct]);
+        assert_eq!(evaluate_guess("sheep", "eerie"), vec![Present, Present, Absent, Absent, Absent]);
+        assert_eq!(evaluate_guess("apple", "paper"), vec![Present, Present, Correct, Present, Absent]);
+        assert_eq!(evaluate_guess("speed", "geese"), vec![Absent, Present, Correct, Present, Absent]);
+    }
+
+    #[test]
+    fn word_lists_are_valid() {
+        let answers: Vec<&str> = include_str!("../models/games/words/answers.txt").lines().collect();
+        let guesses: Vec<&str> = include_str!("../models/games/words/guesses.txt").lines().collect();
+        for words in [&answers, &guesses] {
+            assert!(words.iter().all(|word| word.len() == 5 && word.chars().all(|c| c.is_ascii_lowercase())));
+            // Sorted without duplicates, which is_valid_word's binary search relies on
+            assert!(words.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+        // Every answer can be guessed, and plenty of words which are never drawn can be too
+        assert!(answers.iter().all(|word| is_valid_word(word)));
+        assert!(guesses.len() > answers.len() * 5);
+        for word in ["stare", "ghost", "adieu"] {
+            assert!(is_valid_word(word));
+        }
+        assert!(answers.contains(&random_answer()));
+        assert!(!is_valid_word("zzzzz"));
+    }
+
+    #[tokio::test]
+    async fn word_games() {
+        let helper = TestHelper::init().await;
+
+        let author_token = create_user(&helper, "author", "author").await.unwrap();
+        let player_token = create_user(&helper, "player", "player").await.unwrap();
+        let author = get_user_me(&helper, author_token.as_str()).await.unwrap();
+
+        // Answers have to be real five letter words, and are stored lowercase
+        for bad_answer in ["cat", "zzzzz", "cr4ne"] {
+            match create_word_game(&helper, author_token.as_str(), Some(bad_answer)).await {
+                Ok(_) => panic!("Creating a word game with {bad_answer} as the answer should fail"),
+                Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+            }
+        }
+        let game = create_word_game(&helper, author_token.as_str(), Some(" Crane ")).await.unwrap();
+        assert!(!game.from_dictionary);
+        let drawn = create_word_game(&helper, author_token.as_str(), None).await.unwrap();
+        assert!(drawn.from_dictionary);
+
+        assert_eq!(list_word_games(&helper, author_token.as_str(), true).await.unwrap().len(), 2);
+        let others = list_word_games(&helper, player_token.as_str(), false).await.unwrap();
+        assert_eq!(others.len(), 2);
+        assert!(others.iter().all(|game| game.author_id == author.id));
+
+        // Authors can play games drawn for them, but not ones where they picked the answer
+        match start_word_session(&helper, author_token.as_str(), game.id.as_str()).await {
+            Ok(_) => panic!("Playing a word game you picked the answer for should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+        match guess_word(&helper, author_token.as_str(), game.id.as_str(), "crane").await {
+            Ok(_) => panic!("Guessing in a word game you picked the answer for should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+        assert_eq!(get_word_player_stats(&helper, author_token.as_str()).await.unwrap().played, 0);
+        start_word_session(&helper, author_token.as_str(), drawn.id.as_str()).await.unwrap();
+
+        // Guesses have to be in the word list and don't cost anything when they aren't
+        let session = start_word_session(&helper, player_token.as_str(), game.id.as_str()).await.unwrap();
+        assert_eq!(session.guesses_remaining, 6);
+        assert!(session.answer.is_none());
+        for bad_guess in ["cranes", "zzzzz"] {
+            match guess_word(&helper, player_token.as_str(), game.id.as_str(), bad_guess).await {
+                Ok(_) => panic!("Guessing {bad_guess} should fail"),
+                Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+            }
+        }
+
+        let result = guess_word(&helper, player_token.as_str(), game.id.as_str(), "trace").await.unwrap();
+        assert_eq!(result.letters, vec![Absent, Correct, Correct, Present, Correct]);
+        assert_eq!(result.guesses_remaining, 5);
+        assert_eq!(result.state, SessionState::InProgress);
+        assert!(result.answer.is_none());
+        let result = guess_word(&helper, player_token.as_str(), game.id.as_str(), "CRANE").await.unwrap();
+        assert_eq!(result.state, SessionState::Won);
+        assert_eq!(result.answer, Some("crane".to_string()));
+
+        // Coming back to a finished game shows how it went
+        let session = start_word_session(&helper, player_token.as_str(), game.id.as_str()).await.unwrap();
+        assert_eq!(session.guesses.len(), 2);
+        assert_eq!(session.guesses[0].word, "trace");
+        assert_eq!(session.answer, Some("crane".to_string()));
+        match guess_word(&helper, player_token.as_str(), game.id.as_str(), "crane").await {
+            Ok(_) => panic!("Guessing after a game is over should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::BAD_REQUEST),
+        }
+
+        // Six wrong guesses lose a game
+        let lost_game = create_word_game(&helper, author_token.as_str(), Some("pilot")).await.unwrap();
+        for _ in 0..6 {
+            guess_word(&helper, player_token.as_str(), lost_game.id.as_str(), "crane").await.unwrap();
+        }
+        let session = start_word_session(&helper, player_token.as_str(), lost_game.id.as_str()).await.unwrap();
+        assert_eq!(session.state, SessionState::Lost);
+        assert_eq!(session.guesses_remaining, 0);
+
+        let stats = get_word_player_stats(&helper, player_token.as_str()).await.unwrap();
+        assert_eq!(stats.played, 2);
+        assert_eq!(stats.won, 1);
+        assert_eq!(stats.lost, 1);
+        assert_eq!(stats.current_win_streak, 0);
+        assert_eq!(stats.max_win_streak, 1);
+        assert_eq!(stats.guess_distribution, [0, 1, 0, 0, 0, 0]);
+
+        // Only the author can delete a game
+        match delete_word_game(&helper, player_token.as_str(), game.id.as_str()).await {
+            Ok(_) => panic!("Deleting someone else's word game should fail"),
+            Err((status_code, _msg)) => assert_eq!(status_code, StatusCode::FORBIDDEN),
+        }
+        delete_word_game(&helper, author_token.as_str(), game.id.as_str()).await.unwrap();
+        assert_eq!(list_word_games(&helper, player_token.as_str(), false).await.unwrap().len(), 2);
+        assert_eq!(get_word_player_stats(&helper, player_token.as_str()).await.unwrap().played, 1);
+    }
+}
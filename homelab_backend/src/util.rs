@@ -6,3 +6,84 @@ pub fn current_unix_time() -> i64 {
         .expect("Time since the unix epoch should never fail")
         .as_secs() as i64
 }
+
+// Converts days since the epoch into a (year, month, day) civil date, from
+// http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// The inverse of civil_from_days, from the same place
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Formats a unix timestamp as a UTC date and time, like "2024-03-09 14:05:00 UTC"
+pub fn format_unix_time(unix_time: i64) -> String {
+    let (year, month, day) = civil_from_days(unix_time.div_euclid(86_400));
+    let seconds = unix_time.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+// The date at a unix timestamp in a timezone `utc_offset_minutes` ahead of UTC, like "2024-03-09"
+pub fn unix_time_to_date(unix_time: i64, utc_offset_minutes: i64) -> String {
+    let (year, month, day) = civil_from_days((unix_time + utc_offset_minutes * 60).div_euclid(86_400));
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Whether a string is a real date written as "YYYY-MM-DD"
+pub fn is_valid_date(date: &str) -> bool {
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 || parts[1].len() != 2 || parts[2].len() != 2 {
+        return false;
+    }
+    if !parts.iter().all(|part| part.chars().all(|c| c.is_ascii_digit())) {
+        return false;
+    }
+    let numbers: Vec<i64> = parts.iter().filter_map(|part| part.parse::<i64>().ok()).collect();
+    if numbers.len() != 3 || !(1..=12).contains(&numbers[1]) || numbers[2] < 1 {
+        return false;
+    }
+    // Days past the end of a month roll over into the next one
+    civil_from_days(days_from_civil(numbers[0], numbers[1], numbers[2])) == (numbers[0], numbers[1], numbers[2])
+}
+
+// Parses a UTC offset like "+02:00", "-05:30" or "0" into minutes
+pub fn parse_utc_offset(offset: &str) -> Option<i64> {
+    let offset = offset.trim();
+    if offset == "0" || offset.eq_ignore_ascii_case("utc") {
+        return Some(0);
+    }
+    let (sign, rest) = match offset.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':')?;
+    let hours = hours.parse::<i64>().ok()?;
+    let minutes = minutes.parse::<i64>().ok()?;
+    if !(0..=14).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+    Some(sign * (hours * 60 + minutes))
+}